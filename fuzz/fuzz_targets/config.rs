@@ -0,0 +1,9 @@
+#![no_main]
+#![allow(dead_code)]
+
+// the prompt is a binary only crate, its modules are compiled into the target as they are
+include!("../../src/main.rs");
+
+libfuzzer_sys::fuzz_target!(|text: &str| {
+    let _ = config::parse(text);
+});
@@ -0,0 +1,270 @@
+//! Prompt for screen readers, `--accessible`. Everything is told in words,
+//! neither glyphs nor colors carry any meaning.
+
+use crate::date_time;
+use crate::ilsore_format;
+use crate::structs;
+use crate::util;
+
+/// Segments in order of importance, time and user aren't told
+static DEFAULT_ORDER: &[structs::LayoutItem] = &[
+    structs::LayoutItem::prompt(structs::PromptSegment::ViMode),
+    structs::LayoutItem::prompt(structs::PromptSegment::LastStatus),
+    structs::LayoutItem::prompt(structs::PromptSegment::Jobs),
+    structs::LayoutItem::prompt(structs::PromptSegment::CmdDuration),
+    structs::LayoutItem::prompt(structs::PromptSegment::ShellLevel),
+    structs::LayoutItem::prompt(structs::PromptSegment::Python),
+    structs::LayoutItem::prompt(structs::PromptSegment::Git),
+    structs::LayoutItem::Segment(structs::SegmentName::OtherCustom),
+];
+
+pub(crate) fn format_accessible(
+    data: &structs::ThemeData,
+    _symbols: &structs::ThemeSymbols,
+) -> String {
+    let render = |shown| match shown {
+        structs::ShownSegment::Prompt(kind) => match kind {
+            structs::PromptSegment::LastStatus => (data.last_exit_status != 0)
+                .then(|| format!("exit status {}", data.last_exit_status)),
+            structs::PromptSegment::Jobs => (data.jobs > 0).then(|| count(data.jobs, "job")),
+            structs::PromptSegment::CmdDuration => {
+                data.cmd_duration.as_ref().map(|v| format!("took {v}"))
+            }
+            structs::PromptSegment::Python => (!data.python.is_empty()).then(|| {
+                let envs: Vec<_> = data
+                    .python
+                    .iter()
+                    .rev()
+                    .map(|v| format!("{} ({})", v.name, v.manager))
+                    .collect();
+                format!("python {}", envs.join(" in "))
+            }),
+            structs::PromptSegment::Git => match &data.git {
+                Some(git) => Some(describe_git(git, data.branch_max_width)),
+                None if data.is_slow(structs::SegmentKind::Git) => Some("git is slow".to_string()),
+                None => None,
+            },
+            structs::PromptSegment::ShellLevel => {
+                (data.shell_level > 1).then(|| format!("nested shell level {}", data.shell_level))
+            }
+            structs::PromptSegment::ViMode => {
+                data.vi_command.then(|| "vi command mode".to_string())
+            }
+            // time and user are known to the user
+            structs::PromptSegment::DateTime | structs::PromptSegment::UserHost => None,
+        },
+        structs::ShownSegment::Custom(v) => Some(v.text.clone()),
+    };
+
+    // segments of a group are told as a list, its glyphs are left out
+    let parts: Vec<String> = data
+        .arranged(DEFAULT_ORDER, render)
+        .into_iter()
+        .map(|v| match v {
+            structs::Arranged::Segment(part) => part,
+            structs::Arranged::Group(_, parts) => parts.join(", "),
+        })
+        .collect();
+
+    // segments are separated with semicolons as git description is a list itself
+    match parts.is_empty() {
+        true => "%~>".to_string(),
+        false => format!("{}\n%~>", parts.join("; ")),
+    }
+}
+
+/// Git information like `branch main, 2 ahead, dirty: 3 staged`
+fn describe_git(git: &structs::GitOutputOptions, branch_max_width: Option<usize>) -> String {
+    if git.untrusted {
+        return "untrusted repository".to_string();
+    }
+
+    let mut parts = Vec::new();
+    if let Some(h) = &git.head_info {
+        let head = match (h.detached, &h.detached_from, h.branch_label()) {
+            (true, Some(from), _) => Some(format!("detached from {from}")),
+            (true, None, _) => h.oid_short.as_ref().map(|v| format!("detached at {v}")),
+            (false, _, Some(branch)) => Some(format!("branch {branch}")),
+            (false, _, None) => None,
+        };
+        parts.extend(head.map(|v| util::truncate_to_width(&v, branch_max_width).into_owned()));
+    }
+    if let Some(b) = &git.branch_ahead_behind {
+        if b.ahead > 0 {
+            parts.push(format!("{} ahead", b.ahead));
+        }
+        if b.behind > 0 {
+            parts.push(format!("{} behind", b.behind));
+        }
+    }
+    if let Some(state) = &git.state {
+        parts.push(format!("{state} in progress"));
+    }
+    if let Some(f) = &git.file_status {
+        parts.push(describe_files(f));
+    }
+    if let Some(d) = &git.diff_stat {
+        parts.push(format!(
+            "{} added, {} removed",
+            count(d.insertions, "line"),
+            d.deletions
+        ));
+    }
+
+    let flags = [
+        (git.busy, "index is busy"),
+        (git.read_only, "read only"),
+        (git.damaged, "damaged repository"),
+        (git.shallow, "shallow clone"),
+        (git.nested, "nested repository"),
+        (git.hooks, "custom hooks"),
+        (git.from_env, "repository from environment"),
+        (git.identity_mismatch, "unexpected identity"),
+        (git.protected, "protected branch"),
+        (git.large_staged, "large file staged"),
+        (git.secrets_staged, "possible secret staged"),
+        (git.slow, "status is incomplete"),
+        (git.reduced, "reduced status of a big repository"),
+        (git.inside_git_dir, "inside of .git folder"),
+        (git.on_default_branch(), "default branch"),
+    ];
+    parts.extend(
+        flags
+            .into_iter()
+            .filter(|(flag, _)| *flag)
+            .map(|(_, text)| text.to_string()),
+    );
+    if let Some(lfs) = git.lfs.as_ref().filter(|v| v.unpulled > 0) {
+        parts.push(format!("{} not pulled", count(lfs.unpulled, "LFS file")));
+    }
+    if let Some(v) = git.pull_request {
+        parts.push(format!("pull request {v}"));
+    }
+    if let Some(v) = git.ci_status {
+        parts.push(format!("CI {v}"));
+    }
+    if let Some(v) = git.today_commits.filter(|v| *v > 0) {
+        parts.push(format!("{} today", count(v, "commit")));
+    }
+    if let Some(v) = git.base_age_days {
+        parts.push(format!("base {} old", count(v as usize, "day")));
+    }
+    if let Some(v) = git.dirty_age_secs {
+        parts.push(format!(
+            "dirty for {}",
+            date_time::format_duration(v.max(1) * 1000, 1)
+        ));
+    }
+    if let Some(v) = &git.tag_distance {
+        parts.push(match v.ahead {
+            0 => format!("tagged {}", v.tag),
+            n => format!("{} after {}", count(n, "commit"), v.tag),
+        });
+    }
+    if let Some(v) = &git.identity {
+        parts.push(format!(
+            "committing as {}",
+            ilsore_format::format_identity(v)
+        ));
+    }
+
+    match parts.is_empty() {
+        true => "git repository".to_string(),
+        false => parts.join(", "),
+    }
+}
+
+/// `clean` or `dirty:` followed by numbers of files, flags are told if numbers are unknown
+fn describe_files(f: &structs::GitFileStatus) -> String {
+    let c = &f.counts;
+    let files = [
+        (c.conflicted, "conflicted"),
+        (
+            c.staged_new
+                + c.staged_modified
+                + c.staged_deleted
+                + c.staged_renamed
+                + c.staged_typechange,
+            "staged",
+        ),
+        (
+            c.wt_modified + c.wt_deleted + c.wt_renamed + c.wt_typechange,
+            "changed",
+        ),
+        (c.wt_new, "untracked"),
+    ];
+    let mut described: Vec<String> = files
+        .into_iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, text)| format!("{n} {text}"))
+        .collect();
+
+    if described.is_empty() {
+        let flags = [
+            (f.conflict, "conflicted"),
+            (f.staged, "staged"),
+            (f.unstaged || f.typechange, "changed"),
+            (f.untracked, "untracked"),
+        ];
+        described = flags
+            .into_iter()
+            .filter(|(flag, _)| *flag)
+            .map(|(_, text)| text.to_string())
+            .collect();
+    }
+    match described.is_empty() {
+        true => "clean".to_string(),
+        false => format!("dirty: {}", described.join(", ")),
+    }
+}
+
+/// Number with a noun in singular or plural form
+fn count(n: usize, noun: &str) -> String {
+    match n {
+        1 => format!("1 {noun}"),
+        n => format!("{n} {noun}s"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::count;
+    use super::describe_files;
+    use crate::structs;
+
+    #[rstest]
+    #[case(0, "0 jobs")]
+    #[case(1, "1 job")]
+    #[case(2, "2 jobs")]
+    fn count_test(#[case] n: usize, #[case] expected: &str) {
+        assert_eq!(count(n, "job"), expected);
+    }
+
+    #[rstest]
+    #[case(false, 0, 0, "clean")]
+    #[case(true, 0, 0, "dirty: untracked")]
+    #[case(true, 3, 2, "dirty: 3 staged, 2 untracked")]
+    fn describe_files_test(
+        #[case] untracked: bool,
+        #[case] staged_new: usize,
+        #[case] wt_new: usize,
+        #[case] expected: &str,
+    ) {
+        let status = structs::GitFileStatus {
+            conflict: false,
+            untracked,
+            typechange: false,
+            unstaged: false,
+            staged: staged_new > 0,
+            counts: structs::GitFileCounts {
+                staged_new,
+                wt_new,
+                ..structs::GitFileCounts::default()
+            },
+        };
+        assert_eq!(describe_files(&status), expected);
+    }
+}
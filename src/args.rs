@@ -1,9 +1,24 @@
 use std::path;
 use std::sync::OnceLock;
 
+use clap::CommandFactory;
+use clap::FromArgMatches;
+
+use crate::accessible_format;
+use crate::config;
+use crate::date_time;
 use crate::ilsore_format;
+#[cfg(feature = "color")]
 use crate::ilsore_format_color;
+use crate::integration_format;
+use crate::machine_format;
+use crate::minimal_format;
+use crate::output;
+#[cfg(feature = "color")]
+use crate::powerline_format;
+use crate::shell_init;
 use crate::structs;
+use crate::terminal;
 
 static THEME_SYMBOLS: OnceLock<enum_map::EnumMap<ThemeSymbolsNames, structs::ThemeSymbols>> =
     OnceLock::new();
@@ -14,7 +29,7 @@ type ThemeFunction =
     for<'a, 'b> fn(&'a structs::ThemeData, &'b structs::ThemeSymbols) -> std::string::String;
 
 #[derive(clap::Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_override_self = true)]
 pub(crate) struct Args {
     /// Set if hostname is already known
     #[arg(long, value_name = "HOSTNAME", default_value = None)]
@@ -32,6 +47,18 @@ pub(crate) struct Args {
     #[arg(long, value_name = "FOLDER")]
     pub git_start_folder: Option<path::PathBuf>,
 
+    /// Search `.git` above a mount point, `GIT_DISCOVERY_ACROSS_FILESYSTEM` enables it as well
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_discovery_across_filesystem: bool,
+
+    /// Take current folder from `$PWD` keeping symlinks, git itself resolves them
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_logical_path: bool,
+
+    /// Ignore `GIT_DIR`, `GIT_WORK_TREE` and `GIT_INDEX_FILE` git sets for hooks
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_ignore_env: bool,
+
     /// If git status should include submodules
     #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
     pub git_include_submodules: bool,
@@ -40,6 +67,10 @@ pub(crate) struct Args {
     #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
     pub git_exclude_untracked: bool,
 
+    /// If git status should detect renamed files
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_detect_renames: bool,
+
     /// If git status should softly refresh indices
     #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
     pub git_refresh_status: bool,
@@ -48,25 +79,505 @@ pub(crate) struct Args {
     #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
     pub git_exclude_ahead_behind: bool,
 
+    /// Compute ahead/behind even during rebase, merge and other operations
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_ahead_behind_during_operation: bool,
+
+    /// Skip untracked files and ahead/behind in big repositories, the prompt is marked
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_adaptive: bool,
+
+    /// Count changed files only under the current directory
+    #[arg(
+        long,
+        visible_alias = "cwd-scope",
+        default_value_t = false,
+        action=clap::ArgAction::SetTrue
+    )]
+    pub git_cwd_scope: bool,
+
+    /// Show path of the current folder relative to repository root
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_relative_path: bool,
+
+    /// Maximum number of trailing folders to show in the relative path
+    #[arg(long, value_name = "N")]
+    pub git_relative_path_depth: Option<usize>,
+
+    /// Show repository name before the branch
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_repo_name: bool,
+
+    /// Cache tracking branch resolution between invocations
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_cache_upstream: bool,
+
+    /// Show Git LFS marker and files left as LFS pointers
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_lfs: bool,
+
+    /// Show if HEAD commit is signed
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_signature: bool,
+
+    /// Show if custom hooks path or local hooks are active
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_hooks: bool,
+
+    /// Warn if `user.email` doesn't match the pattern, `*` and `?` are wildcards
+    #[arg(long, value_name = "PATTERN")]
+    pub git_expected_email: Option<String>,
+
+    /// Extract ticket id from branch name
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_ticket: bool,
+
+    /// Regular expression matching ticket id in branch name
+    #[arg(long, value_name = "REGEX", default_value = r"[A-Z]+-\d+")]
+    pub git_ticket_pattern: String,
+
+    /// Warn if the branch is a protected one, so commits aren't made on it directly
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_protected: bool,
+
+    /// Comma separated patterns of protected branches, `*` matches within a path component
+    #[arg(long, value_name = "PATTERNS", default_value = "main,master,release/*")]
+    pub git_protected_branches: String,
+
+    /// Mark staged files bigger than `--git-large-file-mb`, e.g. build artifacts
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_large_files: bool,
+
+    /// Size of a staged file in megabytes which is marked by `--git-large-files`
+    #[arg(long, value_name = "MB", default_value_t = 50)]
+    pub git_large_file_mb: usize,
+
+    /// Mark staged files which look like secrets by their names
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_secrets: bool,
+
+    /// Comma separated patterns of secret file names, a pattern with `/` matches the whole path
+    #[arg(
+        long,
+        value_name = "PATTERNS",
+        default_value = ".env,.env.*,id_rsa,id_dsa,id_ecdsa,id_ed25519,*.pem,*.key,*.p12,*.pfx"
+    )]
+    pub git_secret_patterns: String,
+
+    /// Search contents of staged files for private keys for at most this long, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub git_secret_scan_ms: usize,
+
+    /// Wait once for index lock to be released, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub git_lock_retry_ms: usize,
+
     /// Exclude workdir file stats leaving query index only
     #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
     pub git_exclude_workdir_stats: bool,
 
+    /// Show local branch containing detached HEAD
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_detached_containing: bool,
+
+    /// Maximum number of commits to walk per branch looking for detached HEAD
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub git_containing_walk_limit: usize,
+
+    /// Number of characters to show for commit id
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    pub short_oid_len: usize,
+
+    /// Extend commit id to be unambiguous in the repository
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub short_oid_unique: bool,
+
+    /// Show inserted and deleted lines of the worktree against HEAD, it may be slow
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_diff_stat: bool,
+
+    /// Skip counting lines if more files are changed
+    #[arg(long, value_name = "N", default_value_t = 500)]
+    pub git_diff_stat_max_files: usize,
+
+    /// Show divergence from default branch of every remote, e.g. for forks
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_remotes: bool,
+
+    /// Mark repository nested in a worktree of another one
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_nested: bool,
+
+    /// Show branch of the outer repository next to the nested one
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_outer_branch: bool,
+
+    /// Show open pull request of the branch cached by `prcache refresh`
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_pull_request: bool,
+
+    /// Show CI status of HEAD cached by `cicache refresh`
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_ci_status: bool,
+
+    /// Show number of your commits made today on the branch
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_today_commits: bool,
+
+    /// Show age of merge base with the tracking branch to notice a stale base
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_base_age: bool,
+
+    /// Show merge base age only if it's at least this old, in days
+    #[arg(long, value_name = "DAYS", default_value_t = 7)]
+    pub git_base_age_min_days: usize,
+
+    /// Show how long the worktree is dirty, by the oldest changed file, like `dirty 2d`
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_dirty_age: bool,
+
+    /// Show commits HEAD is ahead of the latest reachable tag, like `v1.2.3+14`
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_tag_distance: bool,
+
+    /// Maximum number of commits to walk from HEAD looking for a tag
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub git_tag_walk_limit: usize,
+
+    /// Show `user.name` and `user.email` commits in the repository are made with
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_identity: bool,
+
+    /// Mark the default branch of the remote, the color theme dims it
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_default_branch: bool,
+
+    /// Ignore `ilsore-format.*` options set in configuration of the repository
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub no_repo_config: bool,
+
+    /// Override git configuration option for this run, e.g. `include-untracked=false`.
+    /// It can be repeated
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_option_override)]
+    pub option_overrides: Vec<(String, String)>,
+
+    /// Print effective git options and where every value comes from instead of the prompt
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub explain_options: bool,
+
+    /// Git options given explicitly in command line followed by `--set` ones
+    #[arg(skip)]
+    pub cli_options: Vec<(String, String)>,
+
+    /// Git options of `[options]` table of the configuration file
+    #[arg(skip)]
+    pub file_options: Vec<(String, String)>,
+
+    /// Wrap branch name into a terminal hyperlink to the remote branch page
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub git_hyperlink: bool,
+
     /// Last command exit status
     #[arg(long, value_name = "ERROR_CODE", default_value_t = 0)]
     pub last_exit_status: u8,
 
-    /// Theme symbols to use
-    #[arg(long, value_name = "SYMBOLS", default_value_t, value_enum)]
-    theme_symbols: ThemeSymbolsNames,
+    /// Duration of the last command in milliseconds
+    #[arg(long, value_name = "MS")]
+    pub cmd_duration_ms: Option<u64>,
+
+    /// Show command duration only if it's at least this long, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 2000)]
+    pub cmd_duration_threshold_ms: u64,
+
+    /// Number of units in command duration, e.g. 2 shows `1h02m`
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    pub cmd_duration_precision: usize,
+
+    /// When to show date next to time
+    #[arg(long, value_name = "MODE", default_value_t, value_enum)]
+    pub date_mode: date_time::DateMode,
+
+    /// Show date with day and month names of the locale, e.g. `de_DE`
+    #[cfg(feature = "locales")]
+    #[arg(long, value_name = "LOCALE", value_parser = date_time::parse_locale)]
+    pub date_locale: Option<chrono::Locale>,
+
+    /// Show date in a calendar given by BCP-47 name, e.g. `hebrew` or `persian`
+    #[cfg(feature = "calendars")]
+    #[arg(long, value_name = "NAME", value_parser = date_time::parse_calendar)]
+    pub calendar: Option<icu_calendar::AnyCalendarKind>,
+
+    /// Number of background jobs in the shell
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub jobs: usize,
+
+    /// Nesting level of the shell, `SHLVL`. Shells nested in other ones are marked
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub shlvl: usize,
+
+    /// Keymap of the line editor, vi command mode is marked
+    #[arg(long, value_name = "KEYMAP", value_enum)]
+    pub keymap: Option<Keymap>,
+
+    /// Theme symbols to use. Default is `utf8-power`, or `ascii` if the locale isn't UTF-8
+    #[arg(long, value_name = "SYMBOLS", value_enum)]
+    theme_symbols: Option<ThemeSymbolsNames>,
+
+    /// Separator between stacked python environments, e.g. `base>proj`
+    #[arg(long, value_name = "SYMBOL")]
+    python_separator: Option<String>,
+
+    /// Separator between segments of different colors in powerline theme
+    #[arg(long, value_name = "SYMBOL")]
+    separator: Option<String>,
+
+    /// Separator between segments of the same color in powerline theme
+    #[arg(long, value_name = "SYMBOL")]
+    separator_thin: Option<String>,
+
+    /// Symbol before the first segment in powerline theme
+    #[arg(long, value_name = "SYMBOL")]
+    cap_left: Option<String>,
+
+    /// Symbol after the last segment in powerline theme
+    #[arg(long, value_name = "SYMBOL")]
+    cap_right: Option<String>,
+
+    /// Configuration file. Default is `$XDG_CONFIG_HOME/ilsore-format/config.toml`
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<path::PathBuf>,
+
+    /// Theme defined in configuration file, it takes precedence over theme name
+    #[arg(long, value_name = "NAME")]
+    pub user_theme: Option<String>,
 
     /// Theme to use
-    #[arg(long, value_name = "THEME", default_value_t, value_enum)]
+    #[arg(
+        long,
+        visible_alias = "format",
+        value_name = "THEME",
+        default_value_t,
+        value_enum
+    )]
     theme_name: ThemeNames,
 
+    /// When to use colors, colored themes fall back to `ilsore-no-color` without them
+    #[arg(long, value_name = "WHEN", default_value_t, value_enum)]
+    color: terminal::ColorMode,
+
+    /// Time budget of every segment in milliseconds, slow segments are skipped
+    #[arg(long, value_name = "MS")]
+    pub timeout_ms: Option<u64>,
+
+    /// Maximum width of the prompt line. Default is terminal width
+    #[arg(long, value_name = "N")]
+    pub max_width: Option<usize>,
+
+    /// Maximum display width of the branch name
+    #[arg(long, value_name = "N")]
+    pub branch_max_width: Option<usize>,
+
+    /// Show battery charge
+    #[cfg(feature = "system")]
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub battery: bool,
+
+    /// Show load average for the last minute
+    #[cfg(feature = "system")]
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub load_average: bool,
+
+    /// Show if direnv has loaded the environment or `.envrc` of the folder is blocked
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub direnv: bool,
+
+    /// Show if the shell is a nix or devenv shell with its name
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub nix_shell: bool,
+
+    /// Show if the shell runs in a container with its name or image
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub container: bool,
+
+    /// Show node version in a folder of a node project
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub node: bool,
+
+    /// Show the current context of kubectl
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub kube: bool,
+
+    /// Read branch from HEAD file without opening the repository, status isn't shown
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub fast: bool,
+
+    /// Print a cheap prompt with branch from HEAD file first, then the full one.
+    /// Each prompt ends with ASCII record separator
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub two_phase: bool,
+
+    /// Print a short hash of the prompt content on a line before every prompt,
+    /// it stays the same when only colors or time change
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub print_hash: bool,
+
+    /// Write prompt into the file or fifo instead of stdout
+    #[arg(long, value_name = "FILE", conflicts_with = "fd")]
+    output: Option<path::PathBuf>,
+
+    /// Write prompt into the open file descriptor instead of stdout
+    #[arg(long, value_name = "N")]
+    fd: Option<i32>,
+
+    /// Render only branch and a dirty marker, e.g. for transient prompts
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub minimal: bool,
+
+    /// Describe the prompt in words without glyphs and colors, for screen readers
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub accessible: bool,
+
+    /// Print time spent in every stage to stderr
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub timings: bool,
+
+    /// Append latency and repository size of this run to the local statistics log
+    #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+    pub stats: bool,
+
     /// Output errros for debugging purposes
     #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
     pub error_output: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Print shell snippet updating prompt before every command
+    Init {
+        #[arg(value_enum)]
+        shell: shell_init::Shell,
+
+        /// Show a quick prompt first and refresh it when the full one is ready
+        #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+        two_phase: bool,
+    },
+    /// Add a line loading init snippet of this binary into the shell rc file
+    Install {
+        #[arg(long, value_enum)]
+        shell: shell_init::Shell,
+
+        /// Rc file to write to. Default is the startup file of the shell in the home folder
+        #[arg(long, value_name = "PATH")]
+        rc: Option<path::PathBuf>,
+
+        /// Show a quick prompt first and refresh it when the full one is ready
+        #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+        two_phase: bool,
+    },
+    /// Write completions, man page and init snippets into the folder for distribution packages
+    #[command(hide = true)]
+    PackageMetadata {
+        /// Target folder, created if it doesn't exist
+        dir: path::PathBuf,
+
+        /// Path of the installed binary used by init snippets. Default is the name found in `PATH`
+        #[arg(long, value_name = "PATH", default_value = env!("CARGO_BIN_NAME"))]
+        binary: path::PathBuf,
+    },
+    /// Render the prompt for synthetic repository states to preview the theme
+    Demo,
+    /// Print the prompt followed by meaning of its symbols, effective git options
+    /// with their origin and time of every stage
+    Explain,
+    /// Write collected data of the prompt, git options, references, status entries and
+    /// configuration into a file for a bug report. Host, user, identity and texts of
+    /// custom segments are replaced, branch names and paths are kept
+    #[cfg(feature = "json")]
+    Record {
+        /// Recording file to write
+        #[arg(long, value_name = "PATH")]
+        out: path::PathBuf,
+    },
+    /// Render the prompt from a file written by `record` subcommand
+    #[cfg(feature = "json")]
+    Render {
+        /// Recording file to read
+        #[arg(long, value_name = "PATH")]
+        from: path::PathBuf,
+    },
+    /// Print every symbol with its meaning to check the font has all glyphs
+    Symbols {
+        /// Symbol set to show. Default is the one used by the prompt
+        #[arg(long, value_name = "SYMBOLS", value_enum)]
+        theme: Option<ThemeSymbolsNames>,
+    },
+    /// Summarize p50 and p95 latency per repository recorded with `--stats`
+    Stats,
+    /// Replace this binary with the latest release if it's newer
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        /// Only report if a newer release is available
+        #[arg(long, default_value_t = false, action=clap::ArgAction::SetTrue)]
+        check: bool,
+    },
+    /// Cache open pull requests of the repository for `--git-pull-request`
+    #[cfg(all(feature = "json", feature = "git"))]
+    Prcache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Cache CI status of HEAD for `--git-ci-status`
+    #[cfg(all(feature = "json", feature = "git"))]
+    Cicache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Serve status requests over a unix socket or a named pipe using JSON-RPC
+    #[cfg(feature = "daemon")]
+    Daemon {
+        /// Socket path. Default is `$XDG_RUNTIME_DIR/ilsore-format.sock`,
+        /// `ilsore-format-<uid>/daemon.sock` in temporary folder without it,
+        /// `\\.\pipe\ilsore-format-<user>` on Windows
+        #[arg(long, value_name = "PATH", global = true)]
+        socket: Option<path::PathBuf>,
+
+        /// Reuse status results for this long, in milliseconds
+        #[arg(long, value_name = "MS", default_value_t = 1000)]
+        cache_ttl_ms: u64,
+
+        /// Stop after this long without connections, in seconds. 0 keeps the daemon running
+        #[arg(long, value_name = "SECONDS", default_value_t = 3600)]
+        idle_timeout_s: u64,
+
+        #[command(subcommand)]
+        action: Option<DaemonAction>,
+    },
+}
+
+#[cfg(feature = "daemon")]
+#[derive(clap::Subcommand, Debug)]
+pub(crate) enum DaemonAction {
+    /// Tell if the daemon is running and its version
+    Status,
+    /// Ask the running daemon to stop
+    Stop,
+}
+
+#[cfg(all(feature = "json", feature = "git"))]
+#[derive(clap::Subcommand, Debug)]
+pub(crate) enum CacheAction {
+    /// Ask the forge with `gh` or `glab` and replace cached data of the current repository
+    Refresh,
+}
+
+/// Keymaps of vi mode in zsh, `KEYMAP`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Keymap {
+    Vicmd,
+    Viins,
 }
 
 #[derive(clap::ValueEnum, Clone)] // required for clap::ValueEnum
@@ -87,16 +598,144 @@ pub(crate) enum ThemeSymbolsNames {
 #[derive(enum_map::Enum, Copy)] // for EnumMap[] operator
 #[clap(rename_all = "kebab_case")]
 pub(crate) enum ThemeNames {
+    #[cfg(feature = "color")]
     #[default]
     IlsoreColor,
+    #[cfg_attr(not(feature = "color"), default)]
     IlsoreNoColor,
+    #[cfg(feature = "color")]
+    IlsorePowerline,
+    Porcelain,
+    #[cfg(feature = "json")]
+    Json,
+    VimAirline,
+    #[cfg(feature = "json")]
+    Waybar,
+    /// Single line for starship `custom` module. Exit code is 1 outside of repository,
+    /// so the module can be hidden with `when`
+    Starship,
+}
+
+impl ThemeSymbolsNames {
+    pub(crate) fn symbols(self) -> structs::ThemeSymbols {
+        THEME_SYMBOLS.get().expect("Uninitialized theme symbols")[self].clone()
+    }
+}
+
+impl ThemeNames {
+    /// Theme showing the same information without colors
+    fn without_color(self) -> ThemeNames {
+        match self {
+            #[cfg(feature = "color")]
+            ThemeNames::IlsoreColor | ThemeNames::IlsorePowerline => ThemeNames::IlsoreNoColor,
+            _ => self,
+        }
+    }
+}
+
+/// Git configuration options with arguments setting them, negated arguments set
+/// the opposite value. Only arguments given explicitly override git configuration
+const GIT_OPTION_ARGS: [(&str, &str, bool); 44] = [
+    ("include-submodules", "git_include_submodules", false),
+    ("include-untracked", "git_exclude_untracked", true),
+    ("detect-renames", "git_detect_renames", false),
+    ("refresh-status", "git_refresh_status", false),
+    ("include-ahead-behind", "git_exclude_ahead_behind", true),
+    (
+        "ahead-behind-during-operation",
+        "git_ahead_behind_during_operation",
+        false,
+    ),
+    ("adaptive", "git_adaptive", false),
+    ("cache-upstream", "git_cache_upstream", false),
+    ("include-lfs", "git_lfs", false),
+    ("include-signature", "git_signature", false),
+    ("include-hooks", "git_hooks", false),
+    ("expected-email", "git_expected_email", false),
+    ("ticket", "git_ticket", false),
+    ("ticket-pattern", "git_ticket_pattern", false),
+    ("protected", "git_protected", false),
+    ("protected-branches", "git_protected_branches", false),
+    ("large-files", "git_large_files", false),
+    ("large-file-mb", "git_large_file_mb", false),
+    ("secrets", "git_secrets", false),
+    ("secret-patterns", "git_secret_patterns", false),
+    ("secret-scan-ms", "git_secret_scan_ms", false),
+    ("lock-retry-ms", "git_lock_retry_ms", false),
+    ("include-workdir-stats", "git_exclude_workdir_stats", true),
+    ("cwd-scope", "git_cwd_scope", false),
+    ("include-relative-path", "git_relative_path", false),
+    ("include-repo-name", "git_repo_name", false),
+    ("include-hyperlink", "git_hyperlink", false),
+    (
+        "include-detached-containing",
+        "git_detached_containing",
+        false,
+    ),
+    ("containing-walk-limit", "git_containing_walk_limit", false),
+    ("short-oid-len", "short_oid_len", false),
+    ("short-oid-unique", "short_oid_unique", false),
+    ("include-diff-stat", "git_diff_stat", false),
+    ("diff-stat-max-files", "git_diff_stat_max_files", false),
+    ("include-remotes", "git_remotes", false),
+    ("include-pull-request", "git_pull_request", false),
+    ("include-ci-status", "git_ci_status", false),
+    ("include-today-commits", "git_today_commits", false),
+    ("include-base-age", "git_base_age", false),
+    ("base-age-min-days", "git_base_age_min_days", false),
+    ("include-dirty-age", "git_dirty_age", false),
+    ("include-tag-distance", "git_tag_distance", false),
+    ("tag-walk-limit", "git_tag_walk_limit", false),
+    ("include-identity", "git_identity", false),
+    ("include-default-branch", "git_default_branch", false),
+];
+
+/// Git options set by arguments given in command line, defaults of arguments are skipped
+fn explicit_git_options(matches: &clap::ArgMatches) -> Vec<(String, String)> {
+    GIT_OPTION_ARGS
+        .iter()
+        .filter(|(_, id, _)| {
+            matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine)
+        })
+        .filter_map(|(name, id, negated)| {
+            let value = matches.get_raw(id)?.next_back()?.to_string_lossy();
+            let value = match (negated, value.as_ref()) {
+                (true, "true") => "false".to_string(),
+                (true, _) => "true".to_string(),
+                (false, _) => value.into_owned(),
+            };
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Splits `key=value` of `--set`, key is a name of `ilsore-format.*` git configuration option
+fn parse_option_override(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("'{value}' isn't in KEY=VALUE form"))?;
+    let key = key
+        .trim()
+        .strip_prefix(concat!(env!("CARGO_BIN_NAME"), "."))
+        .unwrap_or(key.trim());
+    Ok((key.to_string(), value.to_string()))
 }
 
 pub(crate) fn init_argument_parser() {
     let _ = THEME_NAMES.get_or_init(|| {
         enum_map::enum_map! {
+            #[cfg(feature = "color")]
             ThemeNames::IlsoreColor => ilsore_format_color::format_ilsore_color,
             ThemeNames::IlsoreNoColor => ilsore_format::format_ilsore_no_color,
+            #[cfg(feature = "color")]
+            ThemeNames::IlsorePowerline => powerline_format::format_ilsore_powerline,
+            ThemeNames::Porcelain => machine_format::format_porcelain,
+            #[cfg(feature = "json")]
+            ThemeNames::Json => machine_format::format_json,
+            ThemeNames::VimAirline => integration_format::format_statusline,
+            #[cfg(feature = "json")]
+            ThemeNames::Waybar => integration_format::format_waybar,
+            ThemeNames::Starship => integration_format::format_starship,
         }
     });
 
@@ -110,11 +749,121 @@ pub(crate) fn init_argument_parser() {
 }
 
 impl Args {
-    pub fn symbols(&self) -> &structs::ThemeSymbols {
-        &THEME_SYMBOLS.get().expect("Uninitialized theme symbols")[self.theme_symbols]
+    pub fn output(&self) -> output::Output<'_> {
+        match (&self.output, self.fd) {
+            (Some(path), _) => output::Output::File(path),
+            (None, Some(fd)) => output::Output::Fd(fd),
+            (None, None) => output::Output::Stdout,
+        }
+    }
+
+    /// Parses command line arguments remembering git options given explicitly
+    pub fn parse_explicit() -> Self {
+        Args::from_matches(Args::command().get_matches())
     }
 
-    pub fn theme(&self) -> ThemeFunction {
-        THEME_NAMES.get().expect("Uninitialized theme names")[self.theme_name]
+    /// Parses profile arguments followed by actual ones, so the latter win
+    pub fn parse_with_profile(profile_args: &[String]) -> Self {
+        let mut args = std::env::args_os();
+        let program = args.next().unwrap_or_default();
+        Args::from_matches(
+            Args::command().get_matches_from(
+                std::iter::once(program)
+                    .chain(profile_args.iter().map(Into::into))
+                    .chain(args),
+            ),
+        )
+    }
+
+    fn from_matches(matches: clap::ArgMatches) -> Self {
+        let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+        args.cli_options = explicit_git_options(&matches);
+        args.cli_options
+            .extend(args.option_overrides.iter().cloned());
+        args
+    }
+
+    /// Option layers of the configuration file and command line
+    pub fn option_layers(&self) -> structs::OptionLayers<'_> {
+        structs::OptionLayers {
+            file: &self.file_options,
+            cli: &self.cli_options,
+        }
+    }
+
+    /// Symbols of the user theme or of the command line set, overridden by command line
+    pub fn symbols(&'static self, user_theme: Option<&config::UserTheme>) -> structs::ThemeSymbols {
+        let set = user_theme
+            .and_then(|t| t.symbols_set)
+            .or(self.theme_symbols)
+            .unwrap_or_else(|| match terminal::utf8_locale() {
+                true => ThemeSymbolsNames::default(),
+                false => ThemeSymbolsNames::Ascii,
+            });
+        let mut symbols = set.symbols();
+        if let Some(user_theme) = user_theme {
+            user_theme.apply_symbols(&mut symbols);
+        }
+        structs::ThemeSymbols {
+            python_separator: self
+                .python_separator
+                .as_deref()
+                .unwrap_or(symbols.python_separator),
+            separator: self.separator.as_deref().unwrap_or(symbols.separator),
+            separator_thin: self
+                .separator_thin
+                .as_deref()
+                .unwrap_or(symbols.separator_thin),
+            cap_left: self.cap_left.as_deref().unwrap_or(symbols.cap_left),
+            cap_right: self.cap_right.as_deref().unwrap_or(symbols.cap_right),
+            ..symbols
+        }
+    }
+
+    /// Flag if nothing is printed and exit code is 1 outside of repository
+    pub fn hides_outside_repo(&self, user_theme: Option<&config::UserTheme>) -> bool {
+        !self.minimal
+            && !self.accessible
+            && matches!(self.theme_name(user_theme), ThemeNames::Starship)
+    }
+
+    pub fn theme(&self, user_theme: Option<&config::UserTheme>) -> ThemeFunction {
+        if self.minimal {
+            return minimal_format::format_minimal;
+        }
+        if self.accessible {
+            return accessible_format::format_accessible;
+        }
+        let name = match terminal::colors_enabled(self.color) {
+            true => self.theme_name(user_theme),
+            false => self.theme_name(user_theme).without_color(),
+        };
+        THEME_NAMES.get().expect("Uninitialized theme names")[name]
+    }
+
+    /// Built-in theme, user theme inherits one
+    fn theme_name(&self, user_theme: Option<&config::UserTheme>) -> ThemeNames {
+        user_theme.map_or(self.theme_name, |t| t.inherits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+
+    use super::Args;
+    use super::GIT_OPTION_ARGS;
+
+    #[test]
+    fn git_option_args_test() {
+        let command = Args::command();
+        for (name, id, _) in GIT_OPTION_ARGS {
+            let arg = command.get_arguments().find(|v| v.get_id() == id);
+            let long = arg.and_then(|v| v.get_long());
+            assert!(
+                long.is_some_and(|v| v.starts_with("git-") || v.starts_with("short-")),
+                "{name}: {id}"
+            );
+        }
     }
 }
@@ -0,0 +1,133 @@
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io::Write;
+use std::path;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Result;
+
+/// Directory for cache files, `$XDG_CACHE_HOME/ilsore-format` or `~/.cache/ilsore-format`
+pub(crate) fn cache_dir() -> Option<path::PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(path::PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|v| Path::new(&v).join(".cache")))
+        .map(|v| v.join(env!("CARGO_BIN_NAME")))
+}
+
+/// Hashes all parts of the key into a short cache key
+pub(crate) fn key<T: Hash>(parts: T) -> String {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Modification time of the file in nanoseconds, zero if file doesn't exist
+pub(crate) fn mtime(path: &Path) -> u128 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos())
+}
+
+/// Reads value stored for the key in the namespace
+pub(crate) fn get(namespace: &str, key: &str) -> Option<String> {
+    let content = fs::read_to_string(cache_dir()?.join(namespace)).ok()?;
+    find_value(&content, key).map(String::from)
+}
+
+/// Stores value for the key in the namespace replacing a previous one
+pub(crate) fn set(namespace: &str, key: &str, value: &str) -> Result<()> {
+    let dir = cache_dir().ok_or("cache directory is unknown")?;
+    fs::create_dir_all(&dir)?;
+
+    let file = dir.join(namespace);
+    let content = fs::read_to_string(&file).unwrap_or_default();
+    write(&dir, namespace, &replace_value(&content, key, value))
+}
+
+/// Removes values of the namespace the predicate rejects
+pub(crate) fn retain(namespace: &str, keep: impl Fn(&str) -> bool) -> Result<()> {
+    let dir = cache_dir().ok_or("cache directory is unknown")?;
+    let Ok(content) = fs::read_to_string(dir.join(namespace)) else {
+        return Ok(());
+    };
+    write(&dir, namespace, &retain_values(&content, keep))
+}
+
+fn write(dir: &Path, namespace: &str, content: &str) -> Result<()> {
+    // write into a temporary file first, so concurrent prompts never read a partial file
+    let tmp_file = dir.join(format!("{namespace}.{}", std::process::id()));
+    fs::File::create(&tmp_file)?.write_all(content.as_bytes())?;
+    fs::rename(tmp_file, dir.join(namespace))?;
+    Ok(())
+}
+
+fn find_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+fn replace_value(content: &str, key: &str, value: &str) -> String {
+    let mut result: String = content
+        .lines()
+        .filter(|line| line.split_once('\t').is_some_and(|(k, _)| k != key))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    result.push_str(&format!("{key}\t{}\n", value.replace(['\n', '\r'], " ")));
+    result
+}
+
+fn retain_values(content: &str, keep: impl Fn(&str) -> bool) -> String {
+    content
+        .lines()
+        .filter(|line| line.split_once('\t').is_some_and(|(_, v)| keep(v)))
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::find_value;
+    use super::replace_value;
+    use super::retain_values;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", "a", None)]
+    #[case("a\tvalue\n", "a", Some("value"))]
+    #[case("a\tvalue\nb\tother\tpart\n", "b", Some("other\tpart"))]
+    #[case("broken\na\tvalue\n", "broken", None)]
+    fn find_value_test(#[case] content: &str, #[case] key: &str, #[case] expected: Option<&str>) {
+        assert_eq!(find_value(content, key), expected);
+    }
+
+    #[rstest]
+    #[case("", "a", "1", "a\t1\n")]
+    #[case("a\t0\nb\t2\n", "a", "1", "b\t2\na\t1\n")]
+    #[case("b\t2\n", "a", "multi\nline", "b\t2\na\tmulti line\n")]
+    fn replace_value_test(
+        #[case] content: &str,
+        #[case] key: &str,
+        #[case] value: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(replace_value(content, key, value), expected);
+    }
+
+    #[rstest]
+    #[case("", "")]
+    #[case("a\t1\nb\t2\nc\t1\n", "a\t1\nc\t1\n")]
+    #[case("broken\na\t1\n", "a\t1\n")]
+    fn retain_values_test(#[case] content: &str, #[case] expected: &str) {
+        assert_eq!(retain_values(content, |v| v == "1"), expected);
+    }
+}
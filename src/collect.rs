@@ -0,0 +1,181 @@
+use std::panic;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Set by SIGINT and SIGTERM handlers, see [`CancelToken::interrupted`]
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Never set, for callers which can't be interrupted like the daemon
+#[cfg_attr(not(feature = "daemon"), allow(dead_code))]
+static NEVER: AtomicBool = AtomicBool::new(false);
+
+/// Waiting segments check for cancellation this often
+const CANCEL_POLL: Duration = Duration::from_millis(10);
+
+/// Stops waiting for segments, they're reported as slow and the partial prompt is printed
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CancelToken(&'static AtomicBool);
+
+impl CancelToken {
+    /// Token cancelled by SIGINT or SIGTERM once handlers are installed
+    pub(crate) fn interrupted() -> Self {
+        CancelToken(&INTERRUPTED)
+    }
+
+    #[cfg_attr(not(feature = "daemon"), allow(dead_code))]
+    pub(crate) fn never() -> Self {
+        CancelToken(&NEVER)
+    }
+
+    pub(crate) fn is_cancelled(self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Cancels [`CancelToken::interrupted`] on SIGINT and SIGTERM instead of killing the process.
+///
+/// The first signal restores default handling, so the second one kills a stuck process.
+#[cfg(unix)]
+pub(crate) fn install_signal_handlers() {
+    extern "C" fn handler(signal: libc::c_int) {
+        // only atomic stores and `signal` are safe in a signal handler
+        INTERRUPTED.store(true, Ordering::Relaxed);
+        unsafe { libc::signal(signal, libc::SIG_DFL) };
+    }
+
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        unsafe { libc::signal(signal, handler as *const () as libc::sighandler_t) };
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn install_signal_handlers() {}
+
+/// Outcome of a segment collected under its own time budget
+#[derive(Debug, PartialEq)]
+pub(crate) enum Collected<T> {
+    Value(T),
+    /// Segment didn't finish in time and is skipped
+    Slow,
+    /// Segment has nothing to show
+    Missing,
+}
+
+impl<T> Collected<T> {
+    pub(crate) fn value(self) -> Option<T> {
+        match self {
+            Collected::Value(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_slow(&self) -> bool {
+        matches!(self, Collected::Slow)
+    }
+}
+
+/// Segment running in background thread
+pub(crate) struct Pending<T> {
+    started: Instant,
+    receiver: mpsc::Receiver<Option<T>>,
+}
+
+/// Starts collecting segment in background.
+///
+/// Thread isn't joined, so a slow segment is left behind and dies with the process.
+pub(crate) fn spawn<T, F>(f: F) -> Pending<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Option<T> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // a panic is reported by the hook of `error::setup_panic_hook`, segment is left empty
+        let value = panic::catch_unwind(panic::AssertUnwindSafe(f)).unwrap_or(None);
+        let _ = sender.send(value);
+    });
+    Pending {
+        started: Instant::now(),
+        receiver,
+    }
+}
+
+impl<T> Pending<T> {
+    /// Waits for the segment until budget counted from the start is over or it's cancelled,
+    /// `None` budget waits until it finishes
+    pub(crate) fn wait(self, budget: Option<Duration>, cancel: CancelToken) -> Collected<T> {
+        loop {
+            let left = budget.map(|b| b.saturating_sub(self.started.elapsed()));
+            let slice = left.map_or(CANCEL_POLL, |v| v.min(CANCEL_POLL));
+            match self.receiver.recv_timeout(slice) {
+                Ok(Some(v)) => return Collected::Value(v),
+                Ok(None) | Err(mpsc::RecvTimeoutError::Disconnected) => return Collected::Missing,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if cancel.is_cancelled() || left.is_some_and(|v| v.is_zero()) {
+                        return Collected::Slow;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use super::spawn;
+    use super::CancelToken;
+    use super::Collected;
+
+    #[test]
+    fn collected_value_test() {
+        assert_eq!(
+            spawn(|| Some(1)).wait(None, CancelToken::never()),
+            Collected::Value(1)
+        );
+        assert_eq!(
+            spawn(|| Some(1)).wait(Some(Duration::from_secs(10)), CancelToken::never()),
+            Collected::Value(1)
+        );
+    }
+
+    #[test]
+    fn collected_missing_test() {
+        assert_eq!(
+            spawn(|| None::<u8>).wait(None, CancelToken::never()),
+            Collected::Missing
+        );
+        assert_eq!(
+            spawn(|| -> Option<u8> { panic!("segment failed") }).wait(None, CancelToken::never()),
+            Collected::Missing
+        );
+    }
+
+    #[test]
+    fn collected_slow_test() {
+        let pending = spawn(|| {
+            std::thread::sleep(Duration::from_secs(1));
+            Some(1)
+        });
+        assert_eq!(
+            pending.wait(Some(Duration::from_millis(10)), CancelToken::never()),
+            Collected::Slow
+        );
+    }
+
+    #[test]
+    fn collected_cancelled_test() {
+        let cancelled = CancelToken(Box::leak(Box::new(AtomicBool::new(true))));
+        let pending = spawn(|| {
+            std::thread::sleep(Duration::from_secs(1));
+            Some(1)
+        });
+        assert_eq!(pending.wait(None, cancelled), Collected::Slow);
+    }
+}
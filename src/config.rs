@@ -0,0 +1,803 @@
+//! User configuration file, `$XDG_CONFIG_HOME/ilsore-format/config.toml` by default.
+//!
+//! Themes defined in the file inherit a built-in theme and override its symbols,
+//! order and colors of segments. Order names prompt segments, custom ones, groups
+//! and `custom` for the rest of custom segments:
+//!
+//! ```toml
+//! theme = "mine"
+//!
+//! [themes.mine]
+//! inherits = "ilsore-powerline"
+//! symbols = "utf8"
+//! order = ["env", "git", "date_time", "last_status"]
+//!
+//! [themes.mine.symbol]
+//! git_branch = "on"
+//! separator = "|"
+//!
+//! [themes.mine.colors]
+//! git = "33"
+//!
+//! # segments of a group share a separator, the group is hidden when all of them are
+//! [themes.mine.groups.env]
+//! segments = ["python", "workspace", "direnv"]
+//! separator = " "
+//! prefix = "{"
+//! suffix = "}"
+//!
+//! [scripts]
+//! ticket = 'branch.sub_string(0, 8)'
+//!
+//! [env.workspace]
+//! var = "TF_WORKSPACE"
+//! prefix = "tf:"
+//! color = "99"
+//!
+//! # git options below git configuration, environment and command line
+//! [options]
+//! include-untracked = false
+//!
+//! # the first profile matching current folder selects theme and adds arguments
+//! [[profiles]]
+//! path = "~/work/**"
+//! theme = "mine"
+//! args = ["--git-hyperlink"]
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::args::ThemeNames;
+use crate::args::ThemeSymbolsNames;
+use crate::error::Result;
+use crate::structs;
+use crate::util;
+
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    /// Name of the user theme used when none is given in command line
+    pub theme: Option<String>,
+    pub themes: HashMap<String, UserTheme>,
+    /// Custom segment scripts as name and source, in the file order
+    pub scripts: Vec<(String, String)>,
+    /// Segments showing environment variables, in the file order
+    pub env: Vec<EnvSegment>,
+    pub profiles: Vec<Profile>,
+    /// Git options below git configuration as name and value, in the file order
+    pub options: Vec<(String, String)>,
+}
+
+/// Segment showing value of an environment variable, hidden if it's empty or not set
+#[derive(Debug, PartialEq)]
+pub(crate) struct EnvSegment {
+    pub name: String,
+    pub var: String,
+    pub prefix: String,
+    pub color: Option<&'static str>,
+}
+
+impl EnvSegment {
+    pub(crate) fn segment(&self) -> Option<structs::CustomSegment> {
+        let value = env::var(&self.var).ok().filter(|v| !v.is_empty())?;
+        Some(structs::CustomSegment {
+            name: self.name.clone(),
+            text: format!("{}{value}", self.prefix),
+            color: self.color,
+        })
+    }
+}
+
+/// Settings activated by current folder
+#[derive(Debug, PartialEq)]
+pub(crate) struct Profile {
+    /// Path pattern with `~` expanded, see [`util::path_match`]
+    pub path: String,
+    /// User theme name, it takes precedence over default theme in the file
+    pub theme: Option<String>,
+    /// Command line arguments added before actual ones
+    pub args: Vec<String>,
+}
+
+/// Theme defined in the configuration file
+#[derive(Debug)]
+pub(crate) struct UserTheme {
+    pub inherits: ThemeNames,
+    /// Symbol set to start with instead of the one from command line
+    pub symbols_set: Option<ThemeSymbolsNames>,
+    /// Symbol overrides, names are validated
+    pub symbols: Vec<(String, &'static str)>,
+    pub layout: Option<structs::ThemeLayout>,
+}
+
+impl UserTheme {
+    pub(crate) fn apply_symbols(&self, symbols: &mut structs::ThemeSymbols) {
+        for (name, value) in &self.symbols {
+            if let Some(symbol) = symbols.symbol_mut(name) {
+                *symbol = value;
+            }
+        }
+    }
+}
+
+impl Config {
+    /// First profile matching the folder
+    pub(crate) fn profile(&self, folder: &Path) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .find(|p| util::path_match(&p.path, folder))
+    }
+
+    /// Theme by name from command line or by default theme name in the file
+    pub(crate) fn user_theme(&self, name: Option<&str>) -> Result<Option<&UserTheme>> {
+        match name.or(self.theme.as_deref()) {
+            Some(name) => self
+                .themes
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| format!("theme '{name}' is not defined in config").into()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Default configuration file path
+pub(crate) fn config_path() -> Option<path::PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(path::PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|v| Path::new(&v).join(".config")))
+        .map(|v| v.join(env!("CARGO_BIN_NAME")).join("config.toml"))
+}
+
+/// Loads configuration, missing file at default location is not an error
+pub(crate) fn load(path: Option<&Path>) -> Result<Config> {
+    let (path, required) = match path {
+        Some(v) => (v.to_path_buf(), true),
+        None => match config_path() {
+            Some(v) => (v, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(v) => v,
+        Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Config::default())
+        }
+        Err(err) => return Err(format!("{}: {err}", path.display()).into()),
+    };
+    parse(&text).map_err(|err| format!("{}: {err}", path.display()).into())
+}
+
+pub(crate) fn parse(text: &str) -> Result<Config, String> {
+    let table: toml::Table = text
+        .parse()
+        .map_err(|err: toml::de::Error| err.to_string())?;
+
+    let mut config = Config::default();
+    for (key, value) in &table {
+        match key.as_str() {
+            "theme" => config.theme = Some(string(value, "theme")?.to_string()),
+            // themes name custom segments, which may follow them in the file
+            "themes" => {}
+            "scripts" => {
+                for (name, value) in table_of(value, "scripts")? {
+                    let script = string(value, &format!("scripts.{name}"))?;
+                    config.scripts.push((name.to_string(), script.to_string()));
+                }
+            }
+            "env" => {
+                for (name, value) in table_of(value, "env")? {
+                    let segment = env_segment(name, value, &format!("env.{name}"))?;
+                    config.env.push(segment);
+                }
+            }
+            "options" => {
+                for (name, value) in table_of(value, "options")? {
+                    let value = option_value(value, &format!("options.{name}"))?;
+                    config.options.push((name.to_string(), value));
+                }
+            }
+            "profiles" => {
+                let items = value
+                    .as_array()
+                    .ok_or_else(|| "profiles: expected an array of tables".to_string())?;
+                for (index, value) in items.iter().enumerate() {
+                    config
+                        .profiles
+                        .push(profile(value, &format!("profiles[{index}]"))?);
+                }
+            }
+            _ => return Err(format!("{key}: unknown key")),
+        }
+    }
+
+    if let Some(themes) = table.get("themes") {
+        let custom: Vec<&str> = FLAG_SEGMENTS
+            .into_iter()
+            .chain(config.env.iter().map(|v| v.name.as_str()))
+            .chain(config.scripts.iter().map(|(name, _)| name.as_str()))
+            .collect();
+        for (name, value) in table_of(themes, "themes")? {
+            let theme = user_theme(value, &format!("themes.{name}"), &custom)?;
+            config.themes.insert(name.to_string(), theme);
+        }
+    }
+
+    for (index, profile) in config.profiles.iter().enumerate() {
+        if let Some(theme) = profile.theme.as_ref() {
+            if !config.themes.contains_key(theme) {
+                return Err(format!(
+                    "profiles[{index}].theme: theme '{theme}' is not defined in config"
+                ));
+            }
+        }
+    }
+    Ok(config)
+}
+
+fn user_theme(value: &toml::Value, path: &str, custom: &[&str]) -> Result<UserTheme, String> {
+    let table = table_of(value, path)?;
+
+    let inherits = table
+        .get("inherits")
+        .ok_or_else(|| format!("{path}.inherits: required to name a built-in theme"))?;
+    let inherits = value_enum::<ThemeNames>(inherits, &format!("{path}.inherits"))?;
+
+    let mut theme = UserTheme {
+        inherits,
+        symbols_set: None,
+        symbols: Vec::new(),
+        layout: None,
+    };
+    let mut order: Option<&toml::Value> = None;
+    let mut groups: Vec<(&str, structs::SegmentGroup)> = Vec::new();
+    let mut colors: enum_map::EnumMap<structs::PromptSegment, Option<&'static str>> =
+        enum_map::EnumMap::default();
+
+    for (key, value) in table {
+        let key_path = format!("{path}.{key}");
+        match key.as_str() {
+            "inherits" => {}
+            "symbols" => theme.symbols_set = Some(value_enum(value, &key_path)?),
+            "symbol" => {
+                let mut probe = structs::ThemeSymbols::ascii();
+                for (name, value) in table_of(value, &key_path)? {
+                    if probe.symbol_mut(name).is_none() {
+                        return Err(format!("{key_path}.{name}: unknown symbol"));
+                    }
+                    let value = string(value, &format!("{key_path}.{name}"))?;
+                    theme.symbols.push((name.to_string(), leak(value)));
+                }
+            }
+            // groups may follow the order in the file
+            "order" => order = Some(value),
+            "groups" => {
+                for (name, value) in table_of(value, &key_path)? {
+                    let group_path = format!("{key_path}.{name}");
+                    if structs::PromptSegment::from_name(name).is_some()
+                        || custom.contains(&&**name)
+                    {
+                        return Err(format!("{group_path}: name of a segment"));
+                    }
+                    groups.push((name, group(value, &group_path, custom)?));
+                }
+            }
+            "colors" => {
+                for (name, value) in table_of(value, &key_path)? {
+                    colors[segment(name, &key_path)?] =
+                        Some(color(value, &format!("{key_path}.{name}"))?);
+                }
+            }
+            _ => return Err(format!("{key_path}: unknown key")),
+        }
+    }
+
+    let order = match order {
+        Some(value) => {
+            let key_path = format!("{path}.order");
+            let items = value
+                .as_array()
+                .ok_or_else(|| format!("{key_path}: expected an array of segment names"))?;
+            let items = items
+                .iter()
+                .map(|v| {
+                    let name = string(v, &key_path)?;
+                    match groups.iter().find(|(group, _)| *group == name) {
+                        Some((_, group)) => Ok(structs::LayoutItem::Group(group.clone())),
+                        None => layout_segment(name, &key_path, custom)
+                            .map(structs::LayoutItem::Segment),
+                    }
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Some(items)
+        }
+        None if !groups.is_empty() => {
+            return Err(format!("{path}.groups: shown through order only"));
+        }
+        None => None,
+    };
+
+    if order.is_some() || colors.values().any(Option::is_some) {
+        theme.layout = Some(structs::ThemeLayout { order, colors });
+    }
+    Ok(theme)
+}
+
+/// Segments of a group shown together, `[themes.<name>.groups.<group>]`
+fn group(
+    value: &toml::Value,
+    path: &str,
+    custom: &[&str],
+) -> Result<structs::SegmentGroup, String> {
+    let table = table_of(value, path)?;
+
+    let segments = table
+        .get("segments")
+        .ok_or_else(|| format!("{path}.segments: required to name segments of the group"))?;
+    let segments_path = format!("{path}.segments");
+    let segments = segments
+        .as_array()
+        .ok_or_else(|| format!("{segments_path}: expected an array of segment names"))?
+        .iter()
+        .map(|v| layout_segment(string(v, &segments_path)?, &segments_path, custom))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut group = structs::SegmentGroup {
+        segments,
+        separator: "",
+        prefix: "",
+        suffix: "",
+        color: None,
+    };
+
+    for (key, value) in table {
+        let key_path = format!("{path}.{key}");
+        match key.as_str() {
+            "segments" => {}
+            "separator" => group.separator = leak(string(value, &key_path)?),
+            "prefix" => group.prefix = leak(string(value, &key_path)?),
+            "suffix" => group.suffix = leak(string(value, &key_path)?),
+            "color" => group.color = Some(color(value, &key_path)?),
+            _ => return Err(format!("{key_path}: unknown key")),
+        }
+    }
+    Ok(group)
+}
+
+/// Prompt segment, custom segment or `custom` for custom segments the order doesn't name
+fn layout_segment(name: &str, path: &str, custom: &[&str]) -> Result<structs::SegmentName, String> {
+    if name == "custom" {
+        return Ok(structs::SegmentName::OtherCustom);
+    }
+    if custom.contains(&name) {
+        return Ok(structs::SegmentName::Custom(name.to_string()));
+    }
+    segment(name, path).map(structs::SegmentName::Prompt)
+}
+
+fn env_segment(name: &str, value: &toml::Value, path: &str) -> Result<EnvSegment, String> {
+    let table = table_of(value, path)?;
+
+    let var = table
+        .get("var")
+        .ok_or_else(|| format!("{path}.var: required to name an environment variable"))?;
+    let mut segment = EnvSegment {
+        name: name.to_string(),
+        var: string(var, &format!("{path}.var"))?.to_string(),
+        prefix: String::new(),
+        color: None,
+    };
+
+    for (key, value) in table {
+        let key_path = format!("{path}.{key}");
+        match key.as_str() {
+            "var" => {}
+            "prefix" => segment.prefix = string(value, &key_path)?.to_string(),
+            "color" => segment.color = Some(color(value, &key_path)?),
+            _ => return Err(format!("{key_path}: unknown key")),
+        }
+    }
+    Ok(segment)
+}
+
+/// Git option value in the form of git configuration
+fn option_value(value: &toml::Value, path: &str) -> Result<String, String> {
+    match value {
+        toml::Value::Boolean(v) => Ok(v.to_string()),
+        toml::Value::Integer(v) => Ok(v.to_string()),
+        toml::Value::String(v) => Ok(v.clone()),
+        _ => Err(format!("{path}: expected a boolean, a number or a string")),
+    }
+}
+
+fn profile(value: &toml::Value, path: &str) -> Result<Profile, String> {
+    let table = table_of(value, path)?;
+
+    let pattern = table
+        .get("path")
+        .ok_or_else(|| format!("{path}.path: required to match current folder"))?;
+    let mut profile = Profile {
+        path: expand_home(string(pattern, &format!("{path}.path"))?),
+        theme: None,
+        args: Vec::new(),
+    };
+
+    for (key, value) in table {
+        let key_path = format!("{path}.{key}");
+        match key.as_str() {
+            "path" => {}
+            "theme" => profile.theme = Some(string(value, &key_path)?.to_string()),
+            "args" => {
+                let items = value
+                    .as_array()
+                    .ok_or_else(|| format!("{key_path}: expected an array of arguments"))?;
+                for item in items {
+                    profile.args.push(string(item, &key_path)?.to_string());
+                }
+            }
+            _ => return Err(format!("{key_path}: unknown key")),
+        }
+    }
+    Ok(profile)
+}
+
+/// Replaces leading `~` with home folder
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{home}{rest}")
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn table_of<'a>(value: &'a toml::Value, path: &str) -> Result<&'a toml::Table, String> {
+    value
+        .as_table()
+        .ok_or_else(|| format!("{path}: expected a table"))
+}
+
+fn string<'a>(value: &'a toml::Value, path: &str) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{path}: expected a string"))
+}
+
+fn value_enum<T: ValueEnum>(value: &toml::Value, path: &str) -> Result<T, String> {
+    let name = string(value, path)?;
+    T::from_str(name, false).map_err(|_| {
+        let variants: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        format!(
+            "{path}: unknown value '{name}', expected one of {}",
+            variants.join(", ")
+        )
+    })
+}
+
+fn segment(name: &str, path: &str) -> Result<structs::PromptSegment, String> {
+    structs::PromptSegment::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = structs::PromptSegment::ALL
+            .iter()
+            .map(|v| v.name())
+            .collect();
+        format!(
+            "{path}: unknown segment '{name}', expected one of {}",
+            names.join(", ")
+        )
+    })
+}
+
+fn color(value: &toml::Value, path: &str) -> Result<&'static str, String> {
+    let color = string(value, path)?;
+    if !is_color(color) {
+        return Err(format!(
+            "{path}: '{color}' is not a color, expected 0-255, #rrggbb or a color name"
+        ));
+    }
+    Ok(leak(color))
+}
+
+/// Checks color is accepted by zsh `%F{...}`
+fn is_color(color: &str) -> bool {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    color.parse::<u8>().is_ok()
+        || NAMES.contains(&color)
+        || color
+            .strip_prefix('#')
+            .is_some_and(|v| v.len() == 6 && v.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Custom segments of command line flags, they can be named in layouts as well
+const FLAG_SEGMENTS: [&str; 5] = ["battery", "load", "direnv", "nix", "container"];
+
+/// Configuration is loaded once per run, symbols have to live as long as built-in ones
+fn leak(value: &str) -> &'static str {
+    Box::leak(value.to_string().into_boxed_str())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::is_color;
+    use super::parse;
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn parse_test() {
+        use crate::args::ThemeNames;
+        use crate::structs::LayoutItem;
+        use crate::structs::PromptSegment;
+
+        let config = parse(
+            r##"
+            theme = "mine"
+
+            [themes.mine]
+            inherits = "ilsore-powerline"
+            order = ["git", "date_time"]
+
+            [themes.mine.symbol]
+            git_branch = "on"
+
+            [themes.mine.colors]
+            git = "#00ff00"
+            "##,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.theme.as_deref(), Some("mine"));
+        let theme = config
+            .user_theme(None)
+            .expect("theme exists")
+            .expect("theme is set");
+        assert!(matches!(theme.inherits, ThemeNames::IlsorePowerline));
+        assert_eq!(theme.symbols, [("git_branch".to_string(), "on")]);
+        let layout = theme.layout.as_ref().expect("layout is set");
+        assert_eq!(
+            layout.order.as_deref(),
+            Some(
+                &[
+                    LayoutItem::prompt(PromptSegment::Git),
+                    LayoutItem::prompt(PromptSegment::DateTime)
+                ][..]
+            )
+        );
+        assert_eq!(layout.colors[PromptSegment::Git], Some("#00ff00"));
+        assert_eq!(layout.colors[PromptSegment::Python], None);
+    }
+
+    #[test]
+    fn parse_groups_test() {
+        use crate::structs::LayoutItem;
+        use crate::structs::PromptSegment;
+        use crate::structs::SegmentGroup;
+        use crate::structs::SegmentName;
+
+        let config = parse(
+            r##"
+            [themes.mine]
+            inherits = "ilsore-no-color"
+            order = ["last_status", "env", "git", "custom"]
+
+            [themes.mine.groups.env]
+            segments = ["cmd_duration", "aws", "direnv"]
+            separator = " "
+            prefix = "{"
+            suffix = "}"
+
+            [env.aws]
+            var = "AWS_PROFILE"
+            "##,
+        )
+        .expect("valid config");
+
+        let layout = config.themes["mine"]
+            .layout
+            .as_ref()
+            .expect("layout is set");
+        let env = SegmentGroup {
+            segments: vec![
+                SegmentName::Prompt(PromptSegment::CmdDuration),
+                SegmentName::Custom("aws".to_string()),
+                SegmentName::Custom("direnv".to_string()),
+            ],
+            separator: " ",
+            prefix: "{",
+            suffix: "}",
+            color: None,
+        };
+        assert_eq!(
+            layout.order.as_deref(),
+            Some(
+                &[
+                    LayoutItem::prompt(PromptSegment::LastStatus),
+                    LayoutItem::Group(env),
+                    LayoutItem::prompt(PromptSegment::Git),
+                    LayoutItem::Segment(SegmentName::OtherCustom),
+                ][..]
+            )
+        );
+    }
+
+    #[rstest]
+    #[case("colour = 1", "colour: unknown key")]
+    #[case("[themes.a]", "themes.a.inherits: required to name a built-in theme")]
+    #[case(
+        "[themes.a]\ninherits = \"fancy\"",
+        "themes.a.inherits: unknown value 'fancy', expected one of"
+    )]
+    #[cfg_attr(
+        feature = "json",
+        case(
+            "[themes.a]\ninherits = \"json\"\n[themes.a.symbol]\nbranch = \"b\"",
+            "themes.a.symbol.branch: unknown symbol"
+        )
+    )]
+    #[case(
+        "[themes.a]\ninherits = \"porcelain\"\n[themes.a.symbol]\nbranch = \"b\"",
+        "themes.a.symbol.branch: unknown symbol"
+    )]
+    #[case(
+        "[themes.a]\ninherits = \"porcelain\"\n[themes.a.groups.git]\nsegments = []",
+        "themes.a.groups.git: name of a segment"
+    )]
+    #[case(
+        "[themes.a]\ninherits = \"porcelain\"\n[themes.a.groups.g]\nsegments = [\"git\"]",
+        "themes.a.groups: shown through order only"
+    )]
+    #[case(
+        "[themes.a]\ninherits = \"porcelain\"\norder = [\"g\"]\n[themes.a.groups.g]\nsegments = [\"g\"]",
+        "themes.a.groups.g.segments: unknown segment 'g'"
+    )]
+    #[cfg_attr(
+        feature = "color",
+        case(
+            "[themes.a]\ninherits = \"ilsore-powerline\"\norder = [\"node\"]",
+            "themes.a.order: unknown segment 'node'"
+        )
+    )]
+    #[case(
+        "[themes.a]\ninherits = \"porcelain\"\norder = [\"node\"]",
+        "themes.a.order: unknown segment 'node'"
+    )]
+    #[cfg_attr(
+        feature = "color",
+        case(
+            "[themes.a]\ninherits = \"ilsore-powerline\"\n[themes.a.colors]\ngit = \"256\"",
+            "themes.a.colors.git: '256' is not a color"
+        )
+    )]
+    #[case(
+        "[themes.a]\ninherits = \"porcelain\"\n[themes.a.colors]\ngit = \"256\"",
+        "themes.a.colors.git: '256' is not a color"
+    )]
+    #[case("[[profiles]]\ntheme = \"a\"", "profiles[0].path: required")]
+    #[case(
+        "[[profiles]]\npath = \"/**\"\ntheme = \"a\"",
+        "profiles[0].theme: theme 'a' is not defined in config"
+    )]
+    #[case("[env.a]\nprefix = \"a\"", "env.a.var: required")]
+    #[case(
+        "[env.a]\nvar = \"A\"\ncolor = \"teal\"",
+        "env.a.color: 'teal' is not a color"
+    )]
+    #[case("profiles = 1", "profiles: expected an array of tables")]
+    #[case(
+        "[options]\ninclude-untracked = [true]",
+        "options.include-untracked: expected a boolean, a number or a string"
+    )]
+    fn parse_error_test(#[case] text: &str, #[case] expected: &str) {
+        let err = parse(text).expect_err("invalid config");
+        assert!(err.starts_with(expected), "{err}");
+    }
+
+    #[test]
+    fn parse_scripts_test() {
+        let config = parse("[scripts]\nb = 'x'\na = 'y'").expect("valid config");
+        assert_eq!(
+            config.scripts,
+            [
+                ("b".to_string(), "x".to_string()),
+                ("a".to_string(), "y".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn env_segment_test() {
+        let config = parse(
+            "[env.home]\nvar = \"HOME\"\nprefix = \"~\"\ncolor = \"99\"\n[env.none]\nvar = \"\"",
+        )
+        .expect("valid config");
+
+        let segment = config.env[0].segment().expect("HOME is set");
+        assert_eq!(segment.name, "home");
+        assert!(segment.text.starts_with('~'), "{}", segment.text);
+        assert_eq!(segment.color, Some("99"));
+        assert_eq!(config.env[1].segment(), None);
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "json", case("json"))]
+    #[case("porcelain")]
+    fn profile_test(#[case] inherits: &str) {
+        let config = parse(&format!(
+            r#"
+            [[profiles]]
+            path = "/work/**"
+            theme = "w"
+            args = ["--git-hyperlink"]
+
+            [[profiles]]
+            path = "/**"
+
+            [themes.w]
+            inherits = "{inherits}"
+            "#,
+        ))
+        .expect("valid config");
+
+        let profile = config.profile(Path::new("/work/app")).expect("profile");
+        assert_eq!(profile.theme.as_deref(), Some("w"));
+        assert_eq!(profile.args, ["--git-hyperlink"]);
+        let profile = config.profile(Path::new("/oss/app")).expect("profile");
+        assert_eq!(profile.path, "/**");
+        assert_eq!(profile.theme, None);
+    }
+
+    #[test]
+    fn options_test() {
+        let config = parse(
+            r#"
+            [options]
+            include-untracked = false
+            lock-retry-ms = 50
+            ticket-pattern = "GH-\\d+"
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(
+            config.options,
+            [
+                ("include-untracked".to_string(), "false".to_string()),
+                ("lock-retry-ms".to_string(), "50".to_string()),
+                ("ticket-pattern".to_string(), r"GH-\d+".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_user_theme_test() {
+        let config = parse("theme = \"a\"").expect("valid config");
+        assert!(config.user_theme(None).is_err());
+        assert!(config.user_theme(Some("b")).is_err());
+    }
+
+    #[rstest]
+    #[case("0", true)]
+    #[case("255", true)]
+    #[case("256", false)]
+    #[case("red", true)]
+    #[case("#a0b1c2", true)]
+    #[case("#a0b1c", false)]
+    #[case("", false)]
+    fn is_color_test(#[case] color: &str, #[case] expected: bool) {
+        assert_eq!(is_color(color), expected);
+    }
+}
@@ -0,0 +1,117 @@
+//! Container segment, shown when the shell runs inside of a container, with the name
+//! or image of the container when it's known.
+//!
+//! Docker creates `/.dockerenv`, podman creates `/run/.containerenv` with the name and
+//! image in it, the rest of engines are found by control groups of the init process.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::structs;
+
+const DOCKER_ENV: &str = "/.dockerenv";
+const CONTAINER_ENV: &str = "/run/.containerenv";
+const INIT_CGROUP: &str = "/proc/1/cgroup";
+
+/// Engines by parts of control group paths, in order of precedence
+const CGROUP_ENGINES: [(&str, &str); 5] = [
+    ("kubepods", "kubernetes"),
+    ("docker", "docker"),
+    ("libpod", "podman"),
+    ("containerd", "containerd"),
+    ("lxc", "lxc"),
+];
+
+/// Segment of the current environment
+pub(crate) fn segment() -> Option<structs::CustomSegment> {
+    let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+    let container_env = fs::read_to_string(CONTAINER_ENV).ok();
+    let engine = match &container_env {
+        Some(_) => "podman",
+        None if Path::new(DOCKER_ENV).exists() => "docker",
+        None => fs::read_to_string(INIT_CGROUP)
+            .ok()
+            .as_deref()
+            .and_then(cgroup_engine)
+            .or_else(|| var("container").map(|_| "container"))?,
+    };
+    let name = var("CONTAINER_NAME")
+        .or_else(|| var("CONTAINER_ID"))
+        .or_else(|| container_env.as_deref().and_then(container_env_name));
+    let text = match name {
+        Some(name) => format!("{engine} {name}"),
+        None => engine.to_string(),
+    };
+    Some(structs::CustomSegment {
+        name: "container".to_string(),
+        text,
+        color: None,
+    })
+}
+
+/// Engine by control groups of the init process, cgroup v2 hides them in a container,
+/// so it's a fallback for v1 hosts and privileged containers
+fn cgroup_engine(content: &str) -> Option<&'static str> {
+    let paths: Vec<_> = content
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .collect();
+    CGROUP_ENGINES
+        .iter()
+        .find(|(part, _)| paths.iter().any(|path| path.contains(part)))
+        .map(|&(_, engine)| engine)
+}
+
+/// Name of the container, image if the name isn't there. Podman writes them to
+/// `.containerenv` of privileged containers and toolbox ones only
+fn container_env_name(content: &str) -> Option<String> {
+    let value = |key: &str| {
+        content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.trim_matches('"'))
+            .filter(|v| !v.is_empty())
+    };
+    value("name").or_else(|| value("image")).map(String::from)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::cgroup_engine;
+    use super::container_env_name;
+
+    #[rstest]
+    #[case("", None)]
+    #[case("0::/\n", None)]
+    #[case("0::/init.scope\n", None)]
+    #[case("12:cpuset:/docker/3f6e1a\n11:memory:/docker/3f6e1a\n", Some("docker"))]
+    #[case(
+        "1:name=systemd:/kubepods/besteffort/pod1/docker-3f6e1a.scope\n",
+        Some("kubernetes")
+    )]
+    #[case("0::/machine.slice/libpod-3f6e1a.scope\n", Some("podman"))]
+    #[case("0::/lxc.payload.dev/\n", Some("lxc"))]
+    fn cgroup_engine_test(#[case] content: &str, #[case] expected: Option<&str>) {
+        assert_eq!(cgroup_engine(content), expected);
+    }
+
+    #[rstest]
+    #[case("", None)]
+    #[case("engine=\"podman-4.9.3\"\n", None)]
+    #[case(
+        "engine=\"podman-4.9.3\"\nname=\"fedora-toolbox-40\"\nimage=\"registry.fedoraproject.org/fedora-toolbox:40\"\n",
+        Some("fedora-toolbox-40")
+    )]
+    #[case(
+        "name=\"\"\nimage=\"docker.io/library/alpine:3\"\n",
+        Some("docker.io/library/alpine:3")
+    )]
+    fn container_env_name_test(#[case] content: &str, #[case] expected: Option<&str>) {
+        assert_eq!(container_env_name(content).as_deref(), expected);
+    }
+}
@@ -0,0 +1,42 @@
+//! Settings of a single run, passed along instead of process wide globals, so runs
+//! with different settings may share one process.
+
+/// How errors of a run are reported
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Context {
+    /// Name printed before every error
+    pub app_name: String,
+    /// Flag if errors are printed to stderr
+    pub verbose_errors: bool,
+}
+
+impl Context {
+    /// Context of the binary, errors are printed with `--error-output` only
+    pub(crate) fn new(error_output: bool) -> Self {
+        if !error_output {
+            return Context::silent();
+        }
+        let app_name = std::env::current_exe()
+            .map_or_else(
+                |_| Some(env!("CARGO_BIN_NAME").to_string()),
+                |p| p.file_stem().map(|s| s.to_string_lossy().to_string()),
+            )
+            .expect("filename by env");
+        Context {
+            app_name,
+            verbose_errors: true,
+        }
+    }
+
+    /// Context which keeps errors silent
+    pub(crate) fn silent() -> Self {
+        Context::default()
+    }
+
+    /// Prints the message to stderr if errors are verbose
+    pub(crate) fn log(&self, message: &dyn std::fmt::Display) {
+        if self.verbose_errors {
+            eprintln!("{}:  {message}", self.app_name);
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Daemon answering status requests over a unix socket or a Windows named pipe,
+//! see [`protocol`] and [`transport`]. The daemon stops after being idle
+//! for `--idle-timeout-s`.
+
+use std::collections::HashMap;
+#[cfg(any(unix, windows))]
+use std::io::Read;
+#[cfg(any(unix, windows))]
+use std::io::Write;
+use std::path;
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde_json::Value;
+
+use crate::collect;
+use crate::context;
+use crate::error::MapLog;
+use crate::error::Result;
+use crate::git_utils;
+use crate::schema;
+use crate::structs;
+
+pub(crate) mod protocol;
+pub(crate) mod transport;
+
+use transport::Transport;
+
+type CacheKey = (path::PathBuf, protocol::StatusOptions);
+
+/// State shared between connections
+struct State {
+    ctx: context::Context,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<CacheKey, (Instant, Value)>>,
+    /// Number of open connections, the daemon isn't idle while there are some
+    connections: AtomicUsize,
+    last_activity: Mutex<Instant>,
+}
+
+/// How long management commands wait for the answer of the daemon
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Responses kept at most, a long running daemon sees many folders and option sets
+const CACHE_LIMIT: usize = 256;
+
+/// Default endpoint of the daemon, see [`transport::default_path`]
+pub(crate) fn default_socket_path() -> path::PathBuf {
+    transport::default_path()
+}
+
+#[cfg(any(unix, windows))]
+pub(crate) fn serve(
+    socket: &Path,
+    cache_ttl: Duration,
+    idle_timeout: Option<Duration>,
+    ctx: &context::Context,
+) -> Result<()> {
+    let listener = transport::Local::bind(socket)?;
+    let state = Arc::new(State {
+        ctx: ctx.clone(),
+        cache_ttl,
+        cache: Mutex::new(HashMap::new()),
+        connections: AtomicUsize::new(0),
+        last_activity: Mutex::new(Instant::now()),
+    });
+
+    if let Some(idle_timeout) = idle_timeout {
+        let state = Arc::clone(&state);
+        let socket = socket.to_path_buf();
+        std::thread::spawn(move || stop_when_idle(&state, &socket, idle_timeout));
+    }
+
+    loop {
+        let stream = match listener.accept().ok_or_log(ctx) {
+            Some(v) => v,
+            None => continue,
+        };
+        let state = Arc::clone(&state);
+        let socket = socket.to_path_buf();
+        state.connections.fetch_add(1, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            let shutdown = handle_connection(stream, &state).ok_or_log(&state.ctx);
+            *state.last_activity.lock().expect("poisoned daemon state") = Instant::now();
+            state.connections.fetch_sub(1, Ordering::SeqCst);
+            if let Some(true) = shutdown {
+                transport::Local::release(&socket);
+                std::process::exit(0);
+            }
+        });
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn serve(
+    _socket: &Path,
+    _cache_ttl: Duration,
+    _idle_timeout: Option<Duration>,
+    _ctx: &context::Context,
+) -> Result<()> {
+    Err("daemon requires unix domain sockets or named pipes".into())
+}
+
+/// Frees the endpoint and exits once there were no connections for the timeout
+#[cfg(any(unix, windows))]
+fn stop_when_idle(state: &State, socket: &Path, idle_timeout: Duration) {
+    loop {
+        let idle = state
+            .last_activity
+            .lock()
+            .expect("poisoned daemon state")
+            .elapsed();
+        if state.connections.load(Ordering::SeqCst) == 0 && idle >= idle_timeout {
+            transport::Local::release(socket);
+            std::process::exit(0);
+        }
+        std::thread::sleep(
+            idle_timeout
+                .saturating_sub(idle)
+                .max(Duration::from_millis(100)),
+        );
+    }
+}
+
+/// Prints if the daemon is running, exits with status 1 if it isn't
+pub(crate) fn status_command(socket: &Path) -> Result<()> {
+    match call(socket, "version") {
+        Ok(version) => {
+            println!(
+                "running at {}, version {}, protocol {}",
+                socket.display(),
+                version["version"].as_str().unwrap_or("unknown"),
+                version["protocol"]
+            );
+            Ok(())
+        }
+        Err(_) => {
+            println!("not running at {}", socket.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Stops the running daemon, it's fine if there is none
+pub(crate) fn stop_command(socket: &Path) -> Result<()> {
+    match call(socket, "shutdown") {
+        Ok(_) => println!("stopped daemon at {}", socket.display()),
+        Err(_) => println!("not running at {}", socket.display()),
+    }
+    Ok(())
+}
+
+/// Sends a request without params and returns its result
+#[cfg(any(unix, windows))]
+fn call(socket: &Path, method: &str) -> Result<Value> {
+    use std::io::BufRead;
+
+    let stream = transport::Local::connect(socket, COMMAND_TIMEOUT)?;
+    let mut reader = std::io::BufReader::new(stream);
+    writeln!(reader.get_mut(), "{}", protocol::request_line(1, method))?;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let value: Value =
+        serde_json::from_str(&line).map_err(|err| format!("invalid response: {err}"))?;
+    match value.get("error") {
+        Some(err) => Err(format!("daemon refused {method}: {}", err["message"]).into()),
+        None => Ok(value["result"].clone()),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn call(_socket: &Path, _method: &str) -> Result<Value> {
+    Err("daemon requires unix domain sockets or named pipes".into())
+}
+
+/// Answers requests until connection is closed, returns true if shutdown is requested
+#[cfg(any(unix, windows))]
+fn handle_connection(stream: impl Read + Write, state: &State) -> Result<bool> {
+    use std::io::BufRead;
+
+    let mut reader = std::io::BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        *state.last_activity.lock().expect("poisoned daemon state") = Instant::now();
+        let (id, request) = protocol::parse_request(&line);
+        let shutdown = request == Ok(protocol::Request::Shutdown);
+        let result = request.map(|r| dispatch(r, state));
+        writeln!(reader.get_mut(), "{}", protocol::response(id, result))?;
+        if shutdown {
+            return Ok(true);
+        }
+    }
+}
+
+fn dispatch(request: protocol::Request, state: &State) -> Value {
+    match request {
+        protocol::Request::Version => protocol::version(),
+        protocol::Request::Status { path, options } => status(path, options, state),
+        protocol::Request::Invalidate { path } => {
+            let mut cache = state.cache.lock().expect("poisoned daemon cache");
+            let before = cache.len();
+            cache.retain(|(p, _), _| p != &path);
+            Value::from(before - cache.len())
+        }
+        protocol::Request::Shutdown => Value::Bool(true),
+    }
+}
+
+fn status(path: path::PathBuf, options: protocol::StatusOptions, state: &State) -> Value {
+    let key = (path, options);
+    if let Some((stamp, value)) = state.cache.lock().expect("poisoned daemon cache").get(&key) {
+        if stamp.elapsed() < state.cache_ttl {
+            return value.clone();
+        }
+    }
+
+    let (path, options) = &key;
+    let start_folder = Some(path.clone());
+    let git_info_options = structs::GetGitInfoOptions {
+        start_folder: &start_folder,
+        reference_name: &options.reference,
+        discovery_across_filesystem: git_utils::discovery_across_filesystem(false),
+        logical_path: false,
+        // environment of the daemon isn't one of the client
+        use_git_env: false,
+        include_submodules: options.include_submodules,
+        include_untracked: options.include_untracked,
+        detect_renames: options.detect_renames,
+        refresh_status: options.refresh_status,
+        include_ahead_behind: options.include_ahead_behind,
+        ahead_behind_during_operation: false,
+        adaptive: false,
+        cache_upstream: false,
+        include_lfs: false,
+        include_signature: false,
+        include_hooks: false,
+        expected_email: None,
+        include_ticket: false,
+        ticket_pattern: "",
+        include_protected: false,
+        include_large_files: false,
+        large_file_mb: 0,
+        include_secrets: false,
+        secret_patterns: "",
+        secret_scan_ms: 0,
+        protected_branches: "",
+        lock_retry_ms: 0,
+        include_workdir_stats: options.include_workdir_stats,
+        cwd_scope: options.cwd_scope,
+        include_relative_path: options.include_relative_path,
+        include_repo_name: options.include_repo_name,
+        include_hyperlink: false,
+        include_detached_containing: false,
+        containing_walk_limit: 1000,
+        short_oid_len: 8,
+        short_oid_unique: false,
+        include_diff_stat: false,
+        diff_stat_max_files: 0,
+        include_remotes: false,
+        include_nested: false,
+        include_outer_branch: false,
+        include_pull_request: false,
+        include_ci_status: false,
+        include_today_commits: false,
+        include_base_age: false,
+        base_age_min_days: 0,
+        include_dirty_age: false,
+        include_tag_distance: false,
+        tag_walk_limit: 0,
+        include_identity: false,
+        include_default_branch: false,
+        repo_config: true,
+        option_layers: structs::OptionLayers::default(),
+        timeout: None,
+        cancel: collect::CancelToken::never(),
+        context: state.ctx.clone(),
+    };
+    let value = git_utils::process_current_dir(&git_info_options)
+        .ok_or_log(&state.ctx)
+        .as_ref()
+        .map_or(Value::Null, |git| {
+            serde_json::to_value(schema::Git::from(git)).unwrap_or_default()
+        });
+
+    let mut cache = state.cache.lock().expect("poisoned daemon cache");
+    remember(&mut cache, key, value.clone(), state.cache_ttl, CACHE_LIMIT);
+    value
+}
+
+/// Stores the response dropping expired ones, the oldest one is dropped if there's no room
+fn remember<K: std::hash::Hash + Eq + Clone>(
+    cache: &mut HashMap<K, (Instant, Value)>,
+    key: K,
+    value: Value,
+    ttl: Duration,
+    limit: usize,
+) {
+    cache.retain(|_, (stamp, _)| stamp.elapsed() < ttl);
+    if cache.len() >= limit && !cache.contains_key(&key) {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, (stamp, _))| *stamp)
+            .map(|(k, _)| k.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(key, (Instant::now(), value));
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    use super::remember;
+
+    #[test]
+    fn remember_limit_test() {
+        let ttl = Duration::from_secs(60);
+        let mut cache = HashMap::new();
+        let now = Instant::now();
+        cache.insert("a", (now - Duration::from_secs(2), Value::from(1)));
+        cache.insert("b", (now - Duration::from_secs(1), Value::from(2)));
+
+        remember(&mut cache, "c", Value::from(3), ttl, 2);
+        let mut keys: Vec<_> = cache.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, ["b", "c"]);
+
+        // a refreshed response doesn't push another one out
+        remember(&mut cache, "b", Value::from(4), ttl, 2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache["b"].1, Value::from(4));
+    }
+
+    #[test]
+    fn remember_expired_test() {
+        let mut cache = HashMap::new();
+        let expired = Instant::now() - Duration::from_secs(10);
+        cache.insert("a", (expired, Value::from(1)));
+        cache.insert("b", (expired, Value::from(2)));
+
+        remember(&mut cache, "c", Value::from(3), Duration::from_secs(5), 10);
+        assert_eq!(cache.keys().copied().collect::<Vec<_>>(), ["c"]);
+    }
+}
@@ -0,0 +1,331 @@
+//! Versioned JSON-RPC 2.0 protocol of the daemon.
+//!
+//! Every request and every response is a single line of JSON terminated by `\n`,
+//! several requests can be sent over one connection. Requests may pass `protocol`
+//! in params, requests for a different protocol version are rejected with
+//! [`UNSUPPORTED_PROTOCOL`] error, so clients can detect an outdated daemon.
+//!
+//! Methods:
+//!
+//! - `version` returns `{"protocol": 1, "schema": 1, "version": "<crate version>"}`,
+//!   where `schema` is the version of git object, see [`crate::schema`]
+//! - `status` with params `{"path": "...", "options": {...}}` returns git object in the
+//!   same form as `--theme-name json` prints, `null` outside of a repository.
+//!   Known options are `reference` (string), `include_submodules`, `include_untracked`,
+//!   `detect_renames`, `refresh_status`, `include_ahead_behind`, `include_workdir_stats`,
+//!   `cwd_scope`, `include_relative_path` and `include_repo_name` (booleans),
+//!   missing options have the same defaults as command line.
+//! - `invalidate` with params `{"path": "..."}` drops cached results for the path
+//!   and returns their number
+//! - `shutdown` returns `true` and stops the daemon
+//!
+//! Example:
+//!
+//! ```text
+//! > {"jsonrpc": "2.0", "id": 1, "method": "status", "params": {"path": "/src/repo"}}
+//! < {"jsonrpc":"2.0","id":1,"result":{"head_info":{...},...}}
+//! ```
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use serde_json::json;
+use serde_json::Value;
+
+use crate::schema;
+
+/// Version of the protocol, incremented on incompatible changes only
+pub(crate) const PROTOCOL_VERSION: u64 = 1;
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+pub(crate) const UNSUPPORTED_PROTOCOL: i64 = -32000;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Request {
+    Version,
+    Status {
+        path: PathBuf,
+        options: StatusOptions,
+    },
+    Invalidate {
+        path: PathBuf,
+    },
+    Shutdown,
+}
+
+/// Options of `status` request, a subset of command line git options
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct StatusOptions {
+    pub reference: String,
+    pub include_submodules: bool,
+    pub include_untracked: bool,
+    pub detect_renames: bool,
+    pub refresh_status: bool,
+    pub include_ahead_behind: bool,
+    pub include_workdir_stats: bool,
+    pub cwd_scope: bool,
+    pub include_relative_path: bool,
+    pub include_repo_name: bool,
+}
+
+impl Default for StatusOptions {
+    fn default() -> Self {
+        StatusOptions {
+            reference: "HEAD".to_string(),
+            include_submodules: false,
+            include_untracked: true,
+            detect_renames: false,
+            refresh_status: false,
+            include_ahead_behind: true,
+            include_workdir_stats: true,
+            cwd_scope: false,
+            include_relative_path: false,
+            include_repo_name: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Error {
+    pub code: i64,
+    pub message: Cow<'static, str>,
+}
+
+impl Error {
+    pub(crate) fn new(code: i64, message: impl Into<Cow<'static, str>>) -> Self {
+        Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Parses a request line, returns request id to answer with even if request is invalid
+pub(crate) fn parse_request(line: &str) -> (Value, Result<Request, Error>) {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(err) => return (Value::Null, Err(Error::new(PARSE_ERROR, err.to_string()))),
+    };
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    (id, request(&value))
+}
+
+fn request(value: &Value) -> Result<Request, Error> {
+    if value.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(Error::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""));
+    }
+    let method = value
+        .get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::new(INVALID_REQUEST, "method is required"))?;
+    let params = value.get("params").unwrap_or(&Value::Null);
+
+    match params.get("protocol").map(Value::as_u64) {
+        None | Some(Some(PROTOCOL_VERSION)) => {}
+        Some(_) => {
+            return Err(Error::new(
+                UNSUPPORTED_PROTOCOL,
+                format!("protocol version {PROTOCOL_VERSION} is supported only"),
+            ))
+        }
+    }
+
+    match method {
+        "version" => Ok(Request::Version),
+        "status" => Ok(Request::Status {
+            path: path_param(params)?,
+            options: status_options(params.get("options").unwrap_or(&Value::Null))?,
+        }),
+        "invalidate" => Ok(Request::Invalidate {
+            path: path_param(params)?,
+        }),
+        "shutdown" => Ok(Request::Shutdown),
+        _ => Err(Error::new(
+            METHOD_NOT_FOUND,
+            format!("method '{method}' not found"),
+        )),
+    }
+}
+
+fn path_param(params: &Value) -> Result<PathBuf, Error> {
+    params
+        .get("path")
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::new(INVALID_PARAMS, "path is required"))
+}
+
+fn status_options(value: &Value) -> Result<StatusOptions, Error> {
+    let mut options = StatusOptions::default();
+    let object = match value {
+        Value::Null => return Ok(options),
+        Value::Object(v) => v,
+        _ => return Err(Error::new(INVALID_PARAMS, "options must be an object")),
+    };
+
+    for (key, value) in object {
+        let flag = match key.as_str() {
+            "reference" => {
+                options.reference = value
+                    .as_str()
+                    .ok_or_else(|| Error::new(INVALID_PARAMS, "reference must be a string"))?
+                    .to_string();
+                continue;
+            }
+            "include_submodules" => &mut options.include_submodules,
+            "include_untracked" => &mut options.include_untracked,
+            "detect_renames" => &mut options.detect_renames,
+            "refresh_status" => &mut options.refresh_status,
+            "include_ahead_behind" => &mut options.include_ahead_behind,
+            "include_workdir_stats" => &mut options.include_workdir_stats,
+            "cwd_scope" => &mut options.cwd_scope,
+            "include_relative_path" => &mut options.include_relative_path,
+            "include_repo_name" => &mut options.include_repo_name,
+            _ => {
+                return Err(Error::new(
+                    INVALID_PARAMS,
+                    format!("unknown option '{key}'"),
+                ))
+            }
+        };
+        *flag = value
+            .as_bool()
+            .ok_or_else(|| Error::new(INVALID_PARAMS, format!("{key} must be a boolean")))?;
+    }
+    Ok(options)
+}
+
+/// Formats request line without params and trailing new line, used by management commands
+pub(crate) fn request_line(id: u64, method: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+    })
+    .to_string()
+}
+
+/// Formats response line without trailing new line
+pub(crate) fn response(id: Value, result: Result<Value, Error>) -> String {
+    let value = match result {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result,
+        }),
+        Err(err) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": err.code,
+                "message": err.message,
+            },
+        }),
+    };
+    value.to_string()
+}
+
+/// Result of `version` request
+pub(crate) fn version() -> Value {
+    json!({
+        "protocol": PROTOCOL_VERSION,
+        "schema": schema::SCHEMA_VERSION,
+        "version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::parse_request;
+    use super::request_line;
+    use super::response;
+    use super::Error;
+    use super::Request;
+    use super::StatusOptions;
+    use super::INVALID_PARAMS;
+    use super::INVALID_REQUEST;
+    use super::METHOD_NOT_FOUND;
+    use super::PARSE_ERROR;
+    use super::UNSUPPORTED_PROTOCOL;
+
+    #[rstest]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "version"}"#,
+        Request::Version
+    )]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}"#,
+        Request::Shutdown
+    )]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "invalidate", "params": {"path": "/r"}}"#,
+        Request::Invalidate { path: PathBuf::from("/r") }
+    )]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "status", "params": {"path": "/r", "protocol": 1}}"#,
+        Request::Status { path: PathBuf::from("/r"), options: StatusOptions::default() }
+    )]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "status", "params": {"path": "/r", "options": {"include_untracked": false, "reference": "main"}}}"#,
+        Request::Status {
+            path: PathBuf::from("/r"),
+            options: StatusOptions {
+                reference: "main".to_string(),
+                include_untracked: false,
+                ..StatusOptions::default()
+            },
+        }
+    )]
+    fn parse_request_test(#[case] line: &str, #[case] expected: Request) {
+        assert_eq!(parse_request(line), (json!(1), Ok(expected)));
+    }
+
+    #[rstest]
+    #[case("{", PARSE_ERROR)]
+    #[case(r#"{"id": 1, "method": "version"}"#, INVALID_REQUEST)]
+    #[case(r#"{"jsonrpc": "2.0", "id": 1}"#, INVALID_REQUEST)]
+    #[case(r#"{"jsonrpc": "2.0", "id": 1, "method": "stat"}"#, METHOD_NOT_FOUND)]
+    #[case(r#"{"jsonrpc": "2.0", "id": 1, "method": "status"}"#, INVALID_PARAMS)]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "status", "params": {"path": "/r", "options": {"color": true}}}"#,
+        INVALID_PARAMS
+    )]
+    #[case(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "version", "params": {"protocol": 2}}"#,
+        UNSUPPORTED_PROTOCOL
+    )]
+    fn parse_request_error_test(#[case] line: &str, #[case] code: i64) {
+        assert_eq!(parse_request(line).1.map_err(|e| e.code), Err(code));
+    }
+
+    #[rstest]
+    #[case("version", Request::Version)]
+    #[case("shutdown", Request::Shutdown)]
+    fn request_line_test(#[case] method: &str, #[case] expected: Request) {
+        assert_eq!(
+            parse_request(&request_line(1, method)),
+            (json!(1), Ok(expected))
+        );
+    }
+
+    #[test]
+    fn response_test() {
+        assert_eq!(
+            response(json!(7), Ok(json!(true))),
+            r#"{"id":7,"jsonrpc":"2.0","result":true}"#
+        );
+        assert_eq!(
+            response(json!(null), Err(Error::new(PARSE_ERROR, "bad"))),
+            r#"{"error":{"code":-32700,"message":"bad"},"id":null,"jsonrpc":"2.0"}"#
+        );
+    }
+}
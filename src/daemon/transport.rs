@@ -0,0 +1,391 @@
+//! Local transports of the daemon: unix domain sockets and Windows named pipes.
+//!
+//! Both are private to the user running the daemon. The socket is readable by its owner
+//! only and connections of other users are refused by peer credentials, the default
+//! socket lives in a folder private to the user. The pipe is created with a DACL
+//! allowing its owner only and rejects remote clients.
+
+use std::io::Read;
+use std::io::Write;
+use std::path;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::Result;
+
+/// Listening endpoint of the daemon and the way clients connect to it
+pub(crate) trait Transport: Sized {
+    type Stream: Read + Write + Send + 'static;
+
+    /// Starts listening at the path, only the current user may connect
+    fn bind(path: &Path) -> Result<Self>;
+
+    /// Waits for the next connection, connections of other users are errors
+    fn accept(&self) -> Result<Self::Stream>;
+
+    /// Connects to a listening daemon, reading waits for the timeout at most
+    fn connect(path: &Path, timeout: Duration) -> Result<Self::Stream>;
+
+    /// Frees the path once the daemon stops
+    fn release(_path: &Path) {}
+}
+
+/// Transport of the platform
+#[cfg(unix)]
+pub(crate) type Local = UnixSocket;
+
+/// Transport of the platform
+#[cfg(windows)]
+pub(crate) type Local = NamedPipe;
+
+/// Default socket path, `$XDG_RUNTIME_DIR/ilsore-format.sock` or in a folder of
+/// the user in temporary folder
+#[cfg(unix)]
+pub(crate) fn default_path() -> path::PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|v| !v.is_empty()) {
+        Some(dir) => path::PathBuf::from(dir).join(concat!(env!("CARGO_BIN_NAME"), ".sock")),
+        None => std::env::temp_dir()
+            .join(format!("{}-{}", env!("CARGO_BIN_NAME"), current_uid()))
+            .join("daemon.sock"),
+    }
+}
+
+/// Default pipe name, `\\.\pipe\ilsore-format-<user>`
+#[cfg(windows)]
+pub(crate) fn default_path() -> path::PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    path::PathBuf::from(format!(r"\\.\pipe\{}-{user}", env!("CARGO_BIN_NAME")))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn default_path() -> path::PathBuf {
+    std::env::temp_dir().join(concat!(env!("CARGO_BIN_NAME"), ".sock"))
+}
+
+#[cfg(unix)]
+pub(crate) struct UnixSocket(std::os::unix::net::UnixListener);
+
+#[cfg(unix)]
+impl Transport for UnixSocket {
+    type Stream = std::os::unix::net::UnixStream;
+
+    fn bind(path: &Path) -> Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixListener;
+        use std::os::unix::net::UnixStream;
+
+        if *path == default_path() {
+            if let Some(dir) = path.parent() {
+                private_dir(dir)?;
+            }
+        }
+        // socket file left by a crashed daemon prevents binding
+        if path.exists() && UnixStream::connect(path).is_err() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(UnixSocket(listener))
+    }
+
+    fn accept(&self) -> Result<Self::Stream> {
+        let (stream, _) = self.0.accept()?;
+        match peer_uid(&stream)? {
+            uid if uid == current_uid() => Ok(stream),
+            uid => Err(format!("connection of user {uid} is refused").into()),
+        }
+    }
+
+    fn connect(path: &Path, timeout: Duration) -> Result<Self::Stream> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(stream)
+    }
+
+    fn release(path: &Path) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+
+/// Creates the folder accessible by the user only, an existing one must be such already
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    use std::os::unix::fs::MetadataExt;
+
+    if !dir.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+    let metadata = std::fs::metadata(dir)?;
+    if metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        return Err(format!(
+            "socket folder {} is accessible by other users, set --socket",
+            dir.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// User id of the process on the other side of the connection
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> std::io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    match result {
+        0 => Ok(cred.uid),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// User id of the process on the other side of the connection
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> std::io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut uid = 0;
+    let mut gid = 0;
+    match unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } {
+        0 => Ok(uid),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Server end of a named pipe, a new instance is created for every connection
+#[cfg(windows)]
+pub(crate) struct NamedPipe {
+    /// Pipe name as a null-terminated wide string
+    name: Vec<u16>,
+    /// Instance waiting for the next client, so clients don't find the pipe missing
+    /// while a connection is being handed over
+    next: std::sync::Mutex<Option<std::fs::File>>,
+}
+
+/// Connected end of a named pipe, reads wait for the timeout at most if it's set.
+///
+/// Synchronous pipe handles have no read timeout, so such a read runs on a thread
+/// and is cancelled once the timeout passes.
+#[cfg(windows)]
+pub(crate) struct PipeStream {
+    pipe: std::sync::Arc<std::fs::File>,
+    read_timeout: Option<Duration>,
+}
+
+#[cfg(windows)]
+impl Read for PipeStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::IO::CancelSynchronousIo;
+
+        let Some(timeout) = self.read_timeout else {
+            return (&*self.pipe).read(buf);
+        };
+        let pipe = self.pipe.clone();
+        let mut chunk = vec![0; buf.len()];
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            let result = (&*pipe).read(&mut chunk).map(|n| {
+                chunk.truncate(n);
+                chunk
+            });
+            let _ = sender.send(result);
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => {
+                let chunk = result?;
+                buf[..chunk.len()].copy_from_slice(&chunk);
+                Ok(chunk.len())
+            }
+            // the thread isn't joined, a read which hasn't started yet isn't cancelled
+            Err(_) => {
+                unsafe { CancelSynchronousIo(reader.as_raw_handle() as _) };
+                Err(std::io::ErrorKind::TimedOut.into())
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Write for PipeStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        (&*self.pipe).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        (&*self.pipe).flush()
+    }
+}
+
+/// Security descriptor allowing the owner of the pipe only
+#[cfg(windows)]
+const PIPE_SDDL: &str = "D:P(A;;GA;;;OW)";
+
+/// `ERROR_PIPE_BUSY`, every instance of the pipe is connected
+#[cfg(windows)]
+const ERROR_PIPE_BUSY: i32 = 231;
+
+#[cfg(windows)]
+impl NamedPipe {
+    fn instance(name: &[u16], first: bool) -> Result<std::fs::File> {
+        use std::os::windows::io::FromRawHandle;
+        use windows_sys::Win32::Foundation::LocalFree;
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+        use windows_sys::Win32::Security::Authorization::ConvertStringSecurityDescriptorToSecurityDescriptorW;
+        use windows_sys::Win32::Security::Authorization::SDDL_REVISION_1;
+        use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+        use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_FIRST_PIPE_INSTANCE;
+        use windows_sys::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+        use windows_sys::Win32::System::Pipes::CreateNamedPipeW;
+        use windows_sys::Win32::System::Pipes::PIPE_REJECT_REMOTE_CLIENTS;
+        use windows_sys::Win32::System::Pipes::PIPE_TYPE_BYTE;
+        use windows_sys::Win32::System::Pipes::PIPE_UNLIMITED_INSTANCES;
+        use windows_sys::Win32::System::Pipes::PIPE_WAIT;
+
+        let sddl: Vec<u16> = PIPE_SDDL.encode_utf16().chain([0]).collect();
+        let mut descriptor = std::ptr::null_mut();
+        let converted = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                std::ptr::null_mut(),
+            )
+        };
+        if converted == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor,
+            bInheritHandle: 0,
+        };
+        // the first instance fails if another process owns the name already
+        let open_mode = match first {
+            true => PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            false => PIPE_ACCESS_DUPLEX,
+        };
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                4096,
+                0,
+                &attributes,
+            )
+        };
+        let error = std::io::Error::last_os_error();
+        unsafe { LocalFree(descriptor) };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(error.into());
+        }
+        Ok(unsafe { std::fs::File::from_raw_handle(handle as _) })
+    }
+}
+
+#[cfg(windows)]
+impl Transport for NamedPipe {
+    type Stream = PipeStream;
+
+    fn bind(path: &Path) -> Result<Self> {
+        use std::os::windows::ffi::OsStrExt;
+
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+        let first = NamedPipe::instance(&name, true)?;
+        Ok(NamedPipe {
+            name,
+            next: std::sync::Mutex::new(Some(first)),
+        })
+    }
+
+    fn accept(&self) -> Result<Self::Stream> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Foundation::ERROR_PIPE_CONNECTED;
+        use windows_sys::Win32::System::Pipes::ConnectNamedPipe;
+
+        let waiting = self.next.lock().expect("poisoned pipe").take();
+        let pipe = match waiting {
+            Some(v) => v,
+            None => NamedPipe::instance(&self.name, false)?,
+        };
+        let connected =
+            unsafe { ConnectNamedPipe(pipe.as_raw_handle() as _, std::ptr::null_mut()) };
+        // a client connecting between creation and the call is connected already
+        if connected == 0 {
+            let error = std::io::Error::last_os_error();
+            if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                return Err(error.into());
+            }
+        }
+        *self.next.lock().expect("poisoned pipe") = NamedPipe::instance(&self.name, false).ok();
+        Ok(PipeStream {
+            pipe: std::sync::Arc::new(pipe),
+            read_timeout: None,
+        })
+    }
+
+    fn connect(path: &Path, timeout: Duration) -> Result<Self::Stream> {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::System::Pipes::WaitNamedPipeW;
+
+        let open = || {
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+        };
+        let pipe = match open() {
+            Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                let name: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+                let millis = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+                unsafe { WaitNamedPipeW(name.as_ptr(), millis) };
+                open()?
+            }
+            result => result?,
+        };
+        Ok(PipeStream {
+            pipe: std::sync::Arc::new(pipe),
+            read_timeout: Some(timeout),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::current_uid;
+    use super::peer_uid;
+
+    #[test]
+    fn peer_uid_test() {
+        let (client, server) = std::os::unix::net::UnixStream::pair().expect("socket pair");
+        assert_eq!(peer_uid(&server).expect("peer credentials"), current_uid());
+        assert_eq!(peer_uid(&client).expect("peer credentials"), current_uid());
+    }
+}
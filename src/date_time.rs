@@ -1,9 +1,259 @@
+use std::fmt::Display;
+
+use crate::args;
+use crate::cache;
+use crate::context;
+use crate::error::MapLog;
 use crate::structs;
 
-pub(crate) fn date_time() -> structs::DateTime {
-    let dt: chrono::DateTime<chrono::Local> = chrono::Local::now();
-    return structs::DateTime {
-        date: Box::new(dt.format("%F")),
-        time: Box::new(dt.format("%T")),
+/// Cache namespace with the last shown date per shell
+const DATE_CACHE: &str = "date";
+
+/// Date format with locale-aware day and month names
+#[cfg(feature = "locales")]
+const LOCALIZED_DATE_FORMAT: &str = "%a %-d %b %Y";
+
+/// When date is shown next to time
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum DateMode {
+    /// Every time
+    #[default]
+    Full,
+    /// Only when it differs from the previous prompt of the same shell
+    Smart,
+}
+
+/// Source of the current time
+pub(crate) trait Clock: Sync {
+    fn now(&self) -> chrono::DateTime<chrono::Local>;
+}
+
+/// Clock of the operating system
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::DateTime<chrono::Local> {
+        chrono::Local::now()
+    }
+}
+
+pub(crate) fn date_time(
+    args: &args::Args,
+    clock: &dyn Clock,
+    ctx: &context::Context,
+) -> structs::DateTime {
+    let dt = clock.now();
+    let date = date(&dt, args, ctx);
+    let show_date = match args.date_mode {
+        DateMode::Full => true,
+        DateMode::Smart => date_changed(&date.to_string(), ctx),
     };
+    structs::DateTime {
+        date,
+        time: Box::new(dt.format("%T")),
+        show_date,
+    }
+}
+
+#[cfg_attr(not(feature = "calendars"), allow(unused_variables))]
+fn date(
+    dt: &chrono::DateTime<chrono::Local>,
+    args: &args::Args,
+    ctx: &context::Context,
+) -> Box<dyn Display> {
+    #[cfg(feature = "calendars")]
+    if let Some(kind) = args.calendar {
+        return Box::new(
+            calendar_date(dt, kind, ctx).unwrap_or_else(|| dt.format("%F").to_string()),
+        );
+    }
+    #[cfg(feature = "locales")]
+    if let Some(locale) = args.date_locale {
+        return Box::new(dt.format_localized(LOCALIZED_DATE_FORMAT, locale));
+    }
+    Box::new(dt.format("%F"))
+}
+
+/// Date in another calendar as numeric `year-month-day`
+#[cfg(feature = "calendars")]
+fn calendar_date(
+    dt: &chrono::DateTime<chrono::Local>,
+    kind: icu_calendar::AnyCalendarKind,
+    ctx: &context::Context,
+) -> Option<String> {
+    use chrono::Datelike;
+
+    let month = u8::try_from(dt.month()).ok()?;
+    let day = u8::try_from(dt.day()).ok()?;
+    let date = icu_calendar::Date::try_new_iso_date(dt.year(), month, day)
+        .map_err(|err| crate::error::Error::from(err.to_string()))
+        .ok_or_log(ctx)?
+        .to_calendar(icu_calendar::AnyCalendar::new(kind));
+    Some(format!(
+        "{}-{:02}-{:02}",
+        date.year().number,
+        date.month().ordinal,
+        date.day_of_month().0
+    ))
+}
+
+#[cfg(feature = "calendars")]
+pub(crate) fn parse_calendar(name: &str) -> Result<icu_calendar::AnyCalendarKind, String> {
+    icu_calendar::AnyCalendarKind::get_for_bcp47_string(name)
+        .ok_or_else(|| format!("unknown calendar '{name}', expected a BCP-47 name like hebrew"))
+}
+
+#[cfg(feature = "locales")]
+pub(crate) fn parse_locale(name: &str) -> Result<chrono::Locale, String> {
+    chrono::Locale::try_from(name)
+        .map_err(|_| format!("unknown locale '{name}', expected a POSIX name like de_DE"))
+}
+
+/// Units of humanized duration from the largest one
+const DURATION_UNITS: [(u64, &str); 4] = [
+    (86_400_000, "d"),
+    (3_600_000, "h"),
+    (60_000, "m"),
+    (1000, "s"),
+];
+
+/// Formats duration like `1m23s` or `2h03m` using at most `precision` units.
+///
+/// Units after the first one are zero padded, durations under a second are shown in milliseconds.
+pub(crate) fn format_duration(ms: u64, precision: usize) -> String {
+    if ms < 1000 {
+        return format!("{ms}ms");
+    }
+    let mut rest = ms;
+    let mut result = String::new();
+    for (size, unit) in DURATION_UNITS
+        .iter()
+        .skip_while(|(size, _)| ms < *size)
+        .take(precision.max(1))
+    {
+        let value = rest / size;
+        rest %= size;
+        match result.is_empty() {
+            true => result.push_str(&format!("{value}{unit}")),
+            false => result.push_str(&format!("{value:02}{unit}")),
+        }
+    }
+    result
+}
+
+/// Remembers date for the parent shell and checks if it differs from the previous one.
+///
+/// Dates of other days are dropped, a shell which is still open finds its date missing
+/// and shows the new one anyway.
+fn date_changed(date: &str, ctx: &context::Context) -> bool {
+    let key = shell_id();
+    let changed = cache::get(DATE_CACHE, &key).as_deref() != Some(date);
+    if changed {
+        cache::set(DATE_CACHE, &key, date).ok_or_log(ctx);
+        cache::retain(DATE_CACHE, |v| v == date).ok_or_log(ctx);
+    }
+    changed
+}
+
+#[cfg(unix)]
+fn shell_id() -> String {
+    std::os::unix::process::parent_id().to_string()
+}
+
+#[cfg(not(unix))]
+fn shell_id() -> String {
+    "shell".to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use rstest::rstest;
+
+    use super::format_duration;
+
+    struct FixedClock(chrono::DateTime<chrono::Local>);
+
+    impl super::Clock for FixedClock {
+        fn now(&self) -> chrono::DateTime<chrono::Local> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn date_time_test() {
+        use chrono::TimeZone;
+
+        let clock = FixedClock(
+            chrono::Local
+                .with_ymd_and_hms(2025, 4, 12, 9, 5, 7)
+                .single()
+                .expect("valid date"),
+        );
+        let args = crate::args::Args::parse_from(["ilsore-format"]);
+        let dt = super::date_time(&args, &clock, &crate::context::Context::silent());
+        assert_eq!(dt.date.to_string(), "2025-04-12");
+        assert_eq!(dt.time.to_string(), "09:05:07");
+        assert!(dt.show_date);
+    }
+
+    #[rstest]
+    #[case(0, 2, "0ms")]
+    #[case(999, 2, "999ms")]
+    #[case(1000, 2, "1s")]
+    #[case(83_000, 2, "1m23s")]
+    #[case(83_999, 1, "1m")]
+    #[case(7_380_000, 2, "2h03m")]
+    #[case(7_383_000, 3, "2h03m03s")]
+    #[case(3_600_000, 2, "1h00m")]
+    #[case(90_061_000, 4, "1d01h01m01s")]
+    #[case(90_061_000, 0, "1d")]
+    #[case(61_000, 9, "1m01s")]
+    fn format_duration_test(#[case] ms: u64, #[case] precision: usize, #[case] expected: &str) {
+        assert_eq!(format_duration(ms, precision), expected);
+    }
+
+    #[cfg(feature = "calendars")]
+    #[rstest]
+    #[case("hebrew", "5785-07-14")]
+    #[case("persian", "1404-01-23")]
+    #[case("gregory", "2025-04-12")]
+    fn calendar_date_test(#[case] name: &str, #[case] expected: &str) {
+        use chrono::TimeZone;
+
+        let dt = chrono::Local
+            .with_ymd_and_hms(2025, 4, 12, 12, 0, 0)
+            .single()
+            .expect("valid date");
+        let kind = super::parse_calendar(name).expect("known calendar");
+        let ctx = crate::context::Context::silent();
+        assert_eq!(
+            super::calendar_date(&dt, kind, &ctx).as_deref(),
+            Some(expected)
+        );
+    }
+
+    #[cfg(feature = "calendars")]
+    #[test]
+    fn parse_calendar_test() {
+        assert!(super::parse_calendar("martian").is_err());
+    }
+
+    #[cfg(feature = "locales")]
+    #[test]
+    fn localized_date_test() {
+        use chrono::TimeZone;
+
+        let dt = chrono::Local
+            .with_ymd_and_hms(2025, 4, 12, 12, 0, 0)
+            .single()
+            .expect("valid date");
+        let locale = super::parse_locale("de_DE").expect("known locale");
+        assert_eq!(
+            dt.format_localized(super::LOCALIZED_DATE_FORMAT, locale)
+                .to_string(),
+            "Sa 12 Apr 2025"
+        );
+        assert!(super::parse_locale("xx").is_err());
+    }
 }
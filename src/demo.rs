@@ -0,0 +1,186 @@
+//! Synthetic repository states rendered by `demo` subcommand,
+//! so themes can be previewed without crafting real repositories.
+
+use crate::structs;
+use crate::util;
+
+/// Folder shown in place of `%~`
+const FOLDER: &str = "~/src/project";
+
+/// Names of scenarios with their git information, in order of rendering
+pub(crate) fn scenarios() -> Vec<(&'static str, structs::GitOutputOptions)> {
+    let clean_status = structs::GitFileStatus {
+        conflict: false,
+        untracked: false,
+        typechange: false,
+        unstaged: false,
+        staged: false,
+        counts: structs::GitFileCounts::default(),
+    };
+    vec![
+        (
+            "clean",
+            git(head("main"), clean_status.clone(), (0, 0), None),
+        ),
+        (
+            "dirty",
+            structs::GitOutputOptions {
+                diff_stat: Some(structs::GitDiffStat {
+                    insertions: 120,
+                    deletions: 43,
+                }),
+                ..git(
+                    head("main"),
+                    structs::GitFileStatus {
+                        untracked: true,
+                        unstaged: true,
+                        staged: true,
+                        ..clean_status.clone()
+                    },
+                    (0, 0),
+                    None,
+                )
+            },
+        ),
+        (
+            "conflicted",
+            git(
+                head("feature/login"),
+                structs::GitFileStatus {
+                    conflict: true,
+                    staged: true,
+                    ..clean_status.clone()
+                },
+                (1, 0),
+                Some(structs::GitRepoState::Merge),
+            ),
+        ),
+        (
+            "detached",
+            git(
+                structs::GitHeadInfo {
+                    reference_short: Some("v1.2.0".to_string()),
+                    reference_name: Some("refs/tags/v1.2.0".to_string()),
+                    reference_kind: Some(structs::GitReferenceKind::Direct),
+                    detached: true,
+                    detached_from: Some("v1.2.0".to_string()),
+                    ..head("main")
+                },
+                clean_status.clone(),
+                (0, 0),
+                None,
+            ),
+        ),
+        (
+            "rebasing",
+            git(
+                head("feature/login"),
+                structs::GitFileStatus {
+                    unstaged: true,
+                    ..clean_status.clone()
+                },
+                (2, 5),
+                Some(structs::GitRepoState::Rebase),
+            ),
+        ),
+        (
+            "ahead-behind",
+            git(head("main"), clean_status, (3, 12), None),
+        ),
+    ]
+}
+
+pub(crate) fn head(branch: &str) -> structs::GitHeadInfo {
+    structs::GitHeadInfo {
+        reference_short: Some(branch.to_string()),
+        reference_name: Some(format!("refs/heads/{branch}")),
+        reference_kind: Some(structs::GitReferenceKind::Symbolic),
+        oid: Some("3f9c1e0a7b2d4c6e8f1a3b5c7d9e0f2a4b6c8d0e".to_string()),
+        oid_short: Some("3f9c1e0a".to_string()),
+        detached: false,
+        detached_from: None,
+        branch_url: None,
+        signed: None,
+        ticket: None,
+    }
+}
+
+pub(crate) fn git(
+    head_info: structs::GitHeadInfo,
+    file_status: structs::GitFileStatus,
+    (ahead, behind): (usize, usize),
+    state: Option<structs::GitRepoState>,
+) -> structs::GitOutputOptions {
+    structs::GitOutputOptions {
+        head_info: Some(head_info),
+        file_status: Some(file_status),
+        branch_ahead_behind: Some(structs::GitBranchAheadBehind { ahead, behind }),
+        state,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn theme_data(
+    git: structs::GitOutputOptions,
+    layout: Option<structs::ThemeLayout>,
+) -> structs::ThemeData {
+    structs::ThemeData {
+        last_exit_status: 0,
+        jobs: 0,
+        shell_level: 0,
+        vi_command: false,
+        cmd_duration: None,
+        datetime: structs::DateTime {
+            date: Box::new("2025-04-12"),
+            time: Box::new("12:00:00"),
+            show_date: true,
+        },
+        hostname: Some("host".to_string()),
+        username: Some("user".to_string()),
+        python: Vec::new(),
+        git: Some(git),
+        repo_path: None,
+        max_width: None,
+        branch_max_width: None,
+        slow: Vec::new(),
+        layout,
+        custom: Vec::new(),
+    }
+}
+
+/// Every scenario under its name, prompt escapes are replaced to show it in the terminal
+pub(crate) fn render(
+    theme: fn(&structs::ThemeData, &structs::ThemeSymbols) -> String,
+    symbols: &structs::ThemeSymbols,
+    layout: Option<&structs::ThemeLayout>,
+) -> String {
+    scenarios()
+        .into_iter()
+        .map(|(name, git)| {
+            let prompt = theme(&theme_data(git, layout.cloned()), symbols);
+            format!("{name}:\n{}\n\n", util::render_prompt(&prompt, FOLDER))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use super::scenarios;
+    use crate::ilsore_format;
+    use crate::structs;
+
+    #[test]
+    fn render_test() {
+        let output = render(
+            ilsore_format::format_ilsore_no_color,
+            &structs::ThemeSymbols::ascii(),
+            None,
+        );
+        for (name, _) in scenarios() {
+            assert!(output.contains(&format!("{name}:\n")), "{output}");
+        }
+        assert!(!output.contains("%~"), "{output}");
+        assert!(output.contains("~/src/project"), "{output}");
+    }
+}
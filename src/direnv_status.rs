@@ -0,0 +1,163 @@
+//! Direnv segment, shown when direnv has loaded the environment of the folder or when
+//! `.envrc` of the folder is blocked, as direnv only warns about it once on `cd`.
+//!
+//! Loaded environment is found by `DIRENV_DIR` and `DIRENV_DIFF`. Whether `.envrc`
+//! is allowed is asked from `direnv status`, its answer is cached for a short time.
+
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::cache;
+use crate::context;
+use crate::error::MapLog;
+use crate::structs;
+
+static DIRENV_CACHE: &str = "direnv";
+
+/// Answer of `direnv status` is trusted for this long, `direnv allow` isn't noticed
+/// before it expires
+const STATUS_TTL_SECS: u64 = 30;
+
+/// Segment of the folder, `quick` doesn't run `direnv` and uses the cache only
+pub(crate) fn segment(
+    cwd: &Path,
+    quick: bool,
+    ctx: &context::Context,
+) -> Option<structs::CustomSegment> {
+    let loaded = loaded_dir(|name| env::var(name).ok());
+    // direnv refuses the file when environment of another folder or none is loaded
+    let blocked = envrc(cwd)
+        .filter(|envrc| loaded.as_deref() != envrc.parent())
+        .and_then(|envrc| blocked(&envrc, quick, ctx))
+        .unwrap_or_default();
+    let text = match (blocked, loaded) {
+        (true, _) => "direnv blocked",
+        (false, Some(_)) => "direnv",
+        (false, None) => return None,
+    };
+    Some(structs::CustomSegment {
+        name: "direnv".to_string(),
+        text: text.to_string(),
+        color: None,
+    })
+}
+
+/// Folder of the loaded `.envrc`, direnv prefixes `DIRENV_DIR` with a dash
+fn loaded_dir(var: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    match var("DIRENV_DIR").filter(|v| !v.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir.strip_prefix('-').unwrap_or(&dir))),
+        // environment is modified, but the folder isn't known
+        None => var("DIRENV_DIFF")
+            .filter(|v| !v.is_empty())
+            .map(|_| PathBuf::new()),
+    }
+}
+
+/// Nearest `.envrc` of the folder or its parents
+fn envrc(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(".envrc"))
+        .find(|path| path.is_file())
+}
+
+/// Flag if direnv refuses to load the file, unknown when direnv isn't installed
+fn blocked(envrc: &Path, quick: bool, ctx: &context::Context) -> Option<bool> {
+    let key = cache::key((envrc, cache::mtime(envrc)));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    if let Some(blocked) = cache::get(DIRENV_CACHE, &key).and_then(|v| parse_cached(&v, now)) {
+        return Some(blocked);
+    }
+    if quick {
+        return None;
+    }
+
+    let output = std::process::Command::new("direnv")
+        .arg("status")
+        .current_dir(envrc.parent()?)
+        .output()
+        .ok()?;
+    let blocked = parse_status(&String::from_utf8_lossy(&output.stdout))?;
+    cache::set(DIRENV_CACHE, &key, &format!("{now}\t{blocked}")).ok_or_log(ctx);
+    Some(blocked)
+}
+
+/// Cached answer if it isn't outdated
+fn parse_cached(value: &str, now: u64) -> Option<bool> {
+    let (checked_at, blocked) = value.split_once('\t')?;
+    let checked_at: u64 = checked_at.parse().ok()?;
+    if now.saturating_sub(checked_at) > STATUS_TTL_SECS {
+        return None;
+    }
+    blocked.parse().ok()
+}
+
+/// Finds whether `.envrc` of the folder is allowed in `direnv status` output.
+///
+/// Older versions print `Found RC allowed true`, newer ones print `0` for allowed,
+/// `1` for not allowed yet and `2` for denied.
+fn parse_status(output: &str) -> Option<bool> {
+    let allowed = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Found RC allowed "))?;
+    match allowed.trim() {
+        "true" | "0" => Some(false),
+        "false" | "1" | "2" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    use super::loaded_dir;
+    use super::parse_cached;
+    use super::parse_status;
+    use super::STATUS_TTL_SECS;
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&[("DIRENV_DIR", "-/home/me/proj")], Some("/home/me/proj"))]
+    #[case(&[("DIRENV_DIR", ""), ("DIRENV_DIFF", "eJzs")], Some(""))]
+    #[case(&[("DIRENV_DIFF", "")], None)]
+    fn loaded_dir_test(#[case] vars: &[(&str, &str)], #[case] expected: Option<&str>) {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        };
+        assert_eq!(loaded_dir(var), expected.map(PathBuf::from));
+    }
+
+    #[rstest]
+    #[case("", None)]
+    #[case("direnv exec path /usr/bin/direnv\nNo .envrc or .env loaded\n", None)]
+    #[case(
+        "Found RC path /home/me/proj/.envrc\nFound RC allowed false\nFound RC allowPath /a\n",
+        Some(true)
+    )]
+    #[case(
+        "Loaded RC allowed 0\nFound RC path /home/me/proj/.envrc\nFound RC allowed 0\n",
+        Some(false)
+    )]
+    #[case("Found RC allowed 2\n", Some(true))]
+    fn parse_status_test(#[case] output: &str, #[case] expected: Option<bool>) {
+        assert_eq!(parse_status(output), expected);
+    }
+
+    #[rstest]
+    #[case("1000\ttrue", 1000, Some(true))]
+    #[case("1000\tfalse", 1000 + STATUS_TTL_SECS, Some(false))]
+    #[case("1000\ttrue", 1001 + STATUS_TTL_SECS, None)]
+    #[case("broken", 1000, None)]
+    fn parse_cached_test(#[case] value: &str, #[case] now: u64, #[case] expected: Option<bool>) {
+        assert_eq!(parse_cached(value, now), expected);
+    }
+}
@@ -1,27 +1,133 @@
 use std::borrow::Cow;
-use std::env;
-use std::sync::OnceLock;
 
-pub(crate) static APP_NAME: OnceLock<String> = OnceLock::new();
-
-/// Flag if error required
-pub(crate) static VERBOSE_ERRORS: OnceLock<bool> = OnceLock::new();
+use crate::context;
 
 #[derive(Debug)]
 pub(crate) enum Error {
     Io(std::io::Error),
+    #[cfg(feature = "git")]
     Git(git2::Error),
+    /// Git error recognized as a known kind of failure
+    #[cfg(feature = "git")]
+    GitClass(GitClass, git2::Error),
     Message(Cow<'static, str>),
+    /// Neither the current folder nor its parents are in a git repository
+    NotARepository,
+    /// Branch has no tracking branch configured or HEAD isn't on a branch
+    UpstreamMissing {
+        branch: String,
+    },
+    /// A collection phase didn't finish in time, e.g. `status`
+    Timeout {
+        phase: &'static str,
+    },
+    /// Repository has unreadable objects, references or index, details are logged before
+    Damaged {
+        repo: std::path::PathBuf,
+    },
+    /// Option value can't be parsed, `reason` tells the value and where it's from
+    ConfigInvalid {
+        key: Cow<'static, str>,
+        reason: String,
+    },
+    /// Error of an operation with a label of it, e.g. `while reading HEAD`
+    Context(Cow<'static, str>, Box<Error>),
 }
 
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GitClass {
+    /// Objects, references or index can't be read
+    Damaged,
+}
+
+impl std::fmt::Display for GitClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitClass::Damaged => write!(f, "repository is damaged"),
+        }
+    }
+}
+
+#[cfg(feature = "git")]
+impl GitClass {
+    /// Recognizes a git error by its class and code, `None` for ordinary errors
+    pub(crate) fn of(err: &git2::Error) -> Option<Self> {
+        use git2::{ErrorClass, ErrorCode};
+
+        let damaged = match err.class() {
+            ErrorClass::Object | ErrorClass::Zlib => true,
+            // a missing object is referenced from somewhere
+            ErrorClass::Odb => err.code() != ErrorCode::Locked,
+            ErrorClass::Index => err.code() != ErrorCode::Locked,
+            // a missing reference is an ordinary case, an unreadable one isn't
+            ErrorClass::Reference => {
+                matches!(err.code(), ErrorCode::GenericError | ErrorCode::Invalid)
+            }
+            _ => false,
+        };
+        damaged.then_some(GitClass::Damaged)
+    }
+}
+
+impl Error {
+    /// Converts a git error to a classified one if it's recognized
+    #[cfg(feature = "git")]
+    pub(crate) fn classified(self) -> Self {
+        match self {
+            Error::Git(err) => match GitClass::of(&err) {
+                Some(class) => Error::GitClass(class, err),
+                None => Error::Git(err),
+            },
+            Error::Context(context, err) => Error::Context(context, Box::new(err.classified())),
+            err => err,
+        }
+    }
+
+    /// Error under all context labels, to match on the kind of failure
+    pub(crate) fn root(&self) -> &Error {
+        match self {
+            Error::Context(_, err) => err.root(),
+            err => err,
+        }
+    }
+
+    #[cfg(feature = "git")]
+    pub(crate) fn git_class(&self) -> Option<GitClass> {
+        match self {
+            Error::GitClass(class, _) => Some(*class),
+            Error::Context(_, err) => err.git_class(),
+            _ => None,
+        }
+    }
+}
+
+/// Labels errors with the operation which failed, outer labels are printed first
+pub(crate) trait Context<T> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T>;
+
+    /// Same as `context`, the label is only made for an error
+    fn with_context<C: Into<Cow<'static, str>>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T> {
+        self.map_err(|err| Error::Context(context.into(), Box::new(err.into())))
+    }
+
+    fn with_context<C: Into<Cow<'static, str>>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|err| Error::Context(context().into(), Box::new(err.into())))
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Self::Io(err)
     }
 }
 
+#[cfg(feature = "git")]
 impl From<git2::Error> for Error {
     fn from(err: git2::Error) -> Self {
         Self::Git(err)
@@ -44,8 +150,17 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::Io(err) => Some(err),
+            #[cfg(feature = "git")]
             Error::Git(err) => Some(err),
-            Error::Message(_) => None,
+            #[cfg(feature = "git")]
+            Error::GitClass(_, err) => Some(err),
+            Error::Message(_)
+            | Error::NotARepository
+            | Error::UpstreamMissing { .. }
+            | Error::Timeout { .. }
+            | Error::Damaged { .. }
+            | Error::ConfigInvalid { .. } => None,
+            Error::Context(_, err) => Some(err.as_ref()),
         }
     }
 }
@@ -54,38 +169,50 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Io(err) => err.fmt(f),
+            #[cfg(feature = "git")]
             Error::Git(err) => err.fmt(f),
+            #[cfg(feature = "git")]
+            Error::GitClass(class, err) => write!(f, "{}: {}", class, err),
             Error::Message(err) => err.fmt(f),
+            Error::NotARepository => write!(f, "not a git repository"),
+            Error::UpstreamMissing { branch } => {
+                write!(f, "branch '{branch}' has no tracking branch")
+            }
+            Error::Timeout { phase } => write!(f, "{phase} took too long"),
+            Error::Damaged { repo } => write!(
+                f,
+                "repository {} is damaged, `git fsck` shows what is broken",
+                repo.display()
+            ),
+            Error::ConfigInvalid { key, reason } => write!(f, "option {key} is invalid: {reason}"),
+            Error::Context(context, err) => write!(f, "{}: {}", context, err),
         }
     }
 }
 
 pub trait LogError {
-    fn log(&self);
+    fn log(&self, ctx: &context::Context);
 }
 
 impl<T> LogError for T
 where
     T: std::error::Error,
 {
-    /// Prints the error message to the console.
+    /// Prints the error message to the console if the context asks for it.
     ///
     /// Example:
     /// ```ignore
     /// use error::LogError;
     ///
-    /// err.log();
+    /// err.log(&ctx);
     /// ```
-    fn log(&self) {
-        if *VERBOSE_ERRORS.get().unwrap() {
-            let app_name = APP_NAME.get().unwrap();
-            eprintln!("{app_name}:  {self:}");
-        }
+    fn log(&self, ctx: &context::Context) {
+        ctx.log(self);
     }
 }
 
 pub trait MapLog<T> {
-    fn ok_or_log(self) -> Option<T>;
+    fn ok_or_log(self, ctx: &context::Context) -> Option<T>;
 }
 
 impl<T, E> MapLog<T> for Result<T, E>
@@ -101,29 +228,116 @@ where
     /// ```ignore
     /// use error::MapLog;
     ///
-    /// result.map_log();
+    /// result.ok_or_log(&ctx);
     /// ```
-    fn ok_or_log(self) -> Option<T> {
+    fn ok_or_log(self, ctx: &context::Context) -> Option<T> {
         let _ = self.as_ref().map_err(|err| {
-            err.log();
+            err.log(ctx);
         });
         self.ok()
     }
 }
 
-pub(crate) fn setup_errors(error_output: bool) {
-    let _ = VERBOSE_ERRORS.get_or_init(|| error_output);
+/// Replaces default panic message, which would break the prompt line, with an error log.
+/// Binary only, tests keep the default hook
+pub(crate) fn setup_panic_hook(ctx: &context::Context) {
+    let ctx = ctx.clone();
+    std::panic::set_hook(Box::new(move |info| ctx.log(info)));
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "git")]
+    use git2::{ErrorClass, ErrorCode};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
 
-    let _ = APP_NAME.get_or_init(|| {
-        if error_output {
-            env::current_exe()
-                .map_or_else(
-                    |_| Some(env!("CARGO_BIN_NAME").to_string()),
-                    |p| p.file_stem().map(|s| s.to_string_lossy().to_string()),
-                )
-                .expect("filename by env")
-        } else {
-            "".to_string()
-        }
-    });
+    use super::Context;
+    use super::Error;
+    #[cfg(feature = "git")]
+    use super::GitClass;
+    use super::Result;
+
+    #[cfg(feature = "git")]
+    #[rstest]
+    #[case(ErrorClass::Object, ErrorCode::GenericError, Some(GitClass::Damaged))]
+    #[case(ErrorClass::Odb, ErrorCode::NotFound, Some(GitClass::Damaged))]
+    #[case(ErrorClass::Index, ErrorCode::GenericError, Some(GitClass::Damaged))]
+    #[case(ErrorClass::Index, ErrorCode::Locked, None)]
+    #[case(
+        ErrorClass::Reference,
+        ErrorCode::GenericError,
+        Some(GitClass::Damaged)
+    )]
+    #[case(ErrorClass::Reference, ErrorCode::NotFound, None)]
+    #[case(ErrorClass::Config, ErrorCode::GenericError, None)]
+    fn git_class_test(
+        #[case] class: ErrorClass,
+        #[case] code: ErrorCode,
+        #[case] expected: Option<GitClass>,
+    ) {
+        let err = git2::Error::new(code, class, "test");
+        assert_eq!(GitClass::of(&err), expected);
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn context_test() {
+        let result: Result<()> = Err(git2::Error::new(
+            ErrorCode::GenericError,
+            ErrorClass::Object,
+            "object is corrupted",
+        ))
+        .context("while reading HEAD")
+        .with_context(|| format!("in repository {}", "/src/repo"));
+        let err = result.map_err(Error::classified).unwrap_err();
+
+        assert_eq!(err.git_class(), Some(GitClass::Damaged));
+        assert_eq!(
+            err.to_string(),
+            "in repository /src/repo: while reading HEAD: \
+             repository is damaged: object is corrupted; class=Object (11)"
+        );
+    }
+
+    #[rstest]
+    #[case(Error::NotARepository, "not a git repository")]
+    #[case(
+        Error::UpstreamMissing { branch: "main".to_string() },
+        "branch 'main' has no tracking branch"
+    )]
+    #[case(Error::Timeout { phase: "status" }, "status took too long")]
+    #[case(
+        Error::Damaged { repo: "/src/repo".into() },
+        "repository /src/repo is damaged, `git fsck` shows what is broken"
+    )]
+    #[case(
+        Error::ConfigInvalid {
+            key: "include-untracked".into(),
+            reason: "maybe from command line".to_string(),
+        },
+        "option include-untracked is invalid: maybe from command line"
+    )]
+    fn display_test(#[case] err: Error, #[case] expected: &str) {
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[test]
+    fn root_test() {
+        let result: Result<()> = Err(Error::UpstreamMissing {
+            branch: "main".to_string(),
+        })
+        .context("while counting commits ahead and behind")
+        .context("in repository /src/repo");
+        let err = result.unwrap_err();
+
+        assert!(matches!(
+            err.root(),
+            Error::UpstreamMissing { branch } if branch == "main"
+        ));
+        assert!(matches!(
+            Error::NotARepository.root(),
+            Error::NotARepository
+        ));
+    }
 }
@@ -0,0 +1,158 @@
+//! Prompt followed by what it consists of, printed by `explain` subcommand: symbols
+//! the prompt shows with their meaning, effective git options with where every value
+//! comes from and time spent in every stage.
+
+use crate::ilsore_format;
+use crate::structs;
+use crate::util;
+
+/// Prompt as it is printed followed by its breakdown
+pub(crate) fn report(
+    prompt: &str,
+    data: &structs::ThemeData,
+    symbols: &structs::ThemeSymbols,
+    options: Option<&str>,
+    timings: &str,
+) -> String {
+    let shown = data
+        .git
+        .as_ref()
+        .map(|git| ilsore_format::format_ilsore_git_symbols(git, symbols))
+        .unwrap_or_default();
+    let symbols = match shown_symbols(&shown, symbols) {
+        v if v.is_empty() => "  none\n".to_string(),
+        v => format_symbols(&v),
+    };
+    let options = match options {
+        Some(v) => indent(v),
+        None => "  not in a git repository\n".to_string(),
+    };
+
+    format!(
+        "{prompt}\n\nSymbols:\n{symbols}\nOptions:\n{options}\nTimings:\n{}",
+        indent(timings)
+    )
+}
+
+/// Symbols found in the text with their names and meaning, in order of appearance.
+///
+/// The longest symbol wins, so `!S` isn't taken as `!` and `S`. Symbols sharing a glyph
+/// are told by the first one in [`structs::ThemeSymbols::DESCRIPTIONS`], git ones go first.
+fn shown_symbols(
+    text: &str,
+    symbols: &structs::ThemeSymbols,
+) -> Vec<(&'static str, &'static str, &'static str)> {
+    let known: Vec<_> = structs::ThemeSymbols::DESCRIPTIONS
+        .iter()
+        .filter_map(|(name, meaning)| {
+            let symbol = symbols.symbol(name).filter(|v| !v.is_empty())?;
+            Some((symbol, *name, *meaning))
+        })
+        .collect();
+
+    let mut shown = Vec::new();
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let found = known
+            .iter()
+            .filter(|(symbol, _, _)| rest.starts_with(symbol))
+            .min_by_key(|(symbol, _, _)| std::cmp::Reverse(symbol.len()));
+        match found {
+            Some(&(symbol, name, meaning)) => {
+                if !shown.iter().any(|(_, v, _)| *v == name) {
+                    shown.push((symbol, name, meaning));
+                }
+                rest = &rest[symbol.len()..];
+            }
+            None => rest = &rest[first.len_utf8()..],
+        }
+    }
+    shown
+}
+
+fn format_symbols(shown: &[(&str, &str, &str)]) -> String {
+    let width = shown
+        .iter()
+        .map(|(symbol, _, _)| util::display_width(symbol))
+        .max()
+        .unwrap_or_default();
+    shown
+        .iter()
+        .map(|(symbol, name, meaning)| {
+            let padding = " ".repeat(width - util::display_width(symbol));
+            format!("  {symbol}{padding}  {meaning} ({name})\n")
+        })
+        .collect()
+}
+
+/// Effective git options with their sources, one per line
+pub(crate) fn format_options(trace: &[(&'static str, String, structs::OptionLayer)]) -> String {
+    let name_width = trace.iter().map(|(n, _, _)| n.len()).max().unwrap_or(0);
+    let value_width = trace.iter().map(|(_, v, _)| v.len()).max().unwrap_or(0);
+    trace
+        .iter()
+        .map(|(name, value, layer)| {
+            let value = match value.is_empty() {
+                true => "-",
+                false => value,
+            };
+            format!("{name:name_width$}  {value:value_width$}  {layer}\n")
+        })
+        .collect()
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("  {line}\n")).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::format_options;
+    use super::shown_symbols;
+    use crate::structs;
+
+    #[rstest]
+    #[case("", &[])]
+    #[case("!S", &["git_unsigned"])]
+    #[case("P!S^", &["git_protected", "git_signed", "git_is_ahead"])]
+    #[case("^^", &["git_is_ahead"])]
+    #[case("&", &["git_has_no_upstream"])]
+    fn shown_symbols_test(#[case] text: &str, #[case] expected: &[&str]) {
+        let names: Vec<_> = shown_symbols(text, &structs::ThemeSymbols::ascii())
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn format_options_test() {
+        assert_eq!(
+            format_options(&[
+                (
+                    "include-untracked",
+                    "false".to_string(),
+                    structs::OptionLayer::GitConfig
+                ),
+                (
+                    "expected-email",
+                    String::new(),
+                    structs::OptionLayer::Default
+                ),
+                (
+                    "short-oid-len",
+                    "12".to_string(),
+                    structs::OptionLayer::CommandLine
+                ),
+            ]),
+            concat!(
+                "include-untracked  false  git config\n",
+                "expected-email     -      default\n",
+                "short-oid-len      12     command line\n",
+            )
+        );
+    }
+}
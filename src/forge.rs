@@ -0,0 +1,398 @@
+//! Pull requests and CI status of the repository on its forge, cached locally
+//! by `prcache refresh` and `cicache refresh` subcommands.
+//!
+//! Prompt only reads the cache, the forge is asked by `gh` or `glab` CLI during refresh,
+//! so the network is never touched while the prompt is rendered.
+//!
+//! The cache line of a repository is `<key>:<value>` pairs separated by spaces:
+//! branch with pull request number or commit id with CI status.
+//! Neither branch nor commit id can contain a colon or a space.
+
+use std::path::Path;
+
+use crate::cache;
+#[cfg(feature = "json")]
+use crate::error::Result;
+#[cfg(feature = "json")]
+use crate::git_utils;
+use crate::structs;
+
+const PULL_REQUESTS_CACHE: &str = "pull_requests";
+const CI_STATUSES_CACHE: &str = "ci_statuses";
+
+/// Number of latest commits with known CI status kept per repository
+#[cfg(feature = "json")]
+const CI_STATUSES_KEEP: usize = 20;
+
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Forge {
+    GitHub,
+    GitLab,
+}
+
+#[cfg(feature = "json")]
+impl Forge {
+    /// Forge hosting the remote, self-hosted instances are recognized by the host name
+    pub(crate) fn from_url(url: &str) -> Option<Forge> {
+        let host = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .split(['/', ':'])
+            .next()?;
+        let host = host.rsplit_once('@').map_or(host, |(_, h)| h);
+        if host.contains("gitlab") {
+            Some(Forge::GitLab)
+        } else if host.contains("github") {
+            Some(Forge::GitHub)
+        } else {
+            None
+        }
+    }
+}
+
+/// Cache key of the repository, shared by all its worktrees
+fn repo_key(common_dir: &Path) -> String {
+    let common_dir = common_dir
+        .canonicalize()
+        .unwrap_or_else(|_| common_dir.to_path_buf());
+    cache::key(common_dir)
+}
+
+/// Number of the open pull request of the branch known at the last refresh
+pub(crate) fn pull_request(common_dir: &Path, branch: &str) -> Option<u64> {
+    let value = cache::get(PULL_REQUESTS_CACHE, &repo_key(common_dir))?;
+    find_pair(&value, branch)?.parse().ok()
+}
+
+/// CI status of the commit known at the last refresh
+pub(crate) fn ci_status(common_dir: &Path, oid: &str) -> Option<structs::GitCiStatus> {
+    let value = cache::get(CI_STATUSES_CACHE, &repo_key(common_dir))?;
+    parse_ci_status(find_pair(&value, oid)?)
+}
+
+/// Value of the key in `<key>:<value>` pairs separated by spaces
+fn find_pair<'a>(value: &'a str, key: &str) -> Option<&'a str> {
+    value
+        .split(' ')
+        .filter_map(|pair| pair.rsplit_once(':'))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+fn parse_ci_status(value: &str) -> Option<structs::GitCiStatus> {
+    match value {
+        "passed" => Some(structs::GitCiStatus::Passed),
+        "failed" => Some(structs::GitCiStatus::Failed),
+        "running" => Some(structs::GitCiStatus::Running),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "json")]
+fn encode_pull_requests(pull_requests: &[(String, u64)]) -> String {
+    pull_requests
+        .iter()
+        .filter(|(branch, _)| !branch.contains([':', ' ']))
+        .map(|(branch, number)| format!("{branch}:{number}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Open pull requests from `gh pr list` or `glab mr list` JSON output
+#[cfg(feature = "json")]
+fn parse_pull_requests(forge: Forge, output: &[u8]) -> Result<Vec<(String, u64)>> {
+    let (branch_field, number_field) = match forge {
+        Forge::GitHub => ("headRefName", "number"),
+        Forge::GitLab => ("source_branch", "iid"),
+    };
+    let value: serde_json::Value =
+        serde_json::from_slice(output).map_err(|err| format!("invalid pull requests: {err}"))?;
+    Ok(value
+        .as_array()
+        .ok_or("pull requests aren't a list")?
+        .iter()
+        .filter_map(|v| {
+            Some((
+                v[branch_field].as_str()?.to_string(),
+                v[number_field].as_u64()?,
+            ))
+        })
+        .collect())
+}
+
+/// Statuses of GitHub workflow runs of a commit from `gh run list` JSON output
+/// or GitLab pipelines of a commit from `glab api` JSON output.
+///
+/// A commit with any unfinished run is running, with any unsuccessful one is failed.
+#[cfg(feature = "json")]
+fn parse_ci_runs(forge: Forge, output: &[u8]) -> Result<Option<structs::GitCiStatus>> {
+    let value: serde_json::Value =
+        serde_json::from_slice(output).map_err(|err| format!("invalid CI runs: {err}"))?;
+    let statuses: Vec<structs::GitCiStatus> = value
+        .as_array()
+        .ok_or("CI runs aren't a list")?
+        .iter()
+        .filter_map(|v| match forge {
+            Forge::GitHub => match (v["status"].as_str()?, v["conclusion"].as_str()) {
+                ("completed", Some("success" | "skipped" | "neutral")) => {
+                    Some(structs::GitCiStatus::Passed)
+                }
+                ("completed", _) => Some(structs::GitCiStatus::Failed),
+                _ => Some(structs::GitCiStatus::Running),
+            },
+            Forge::GitLab => match v["status"].as_str()? {
+                "success" => Some(structs::GitCiStatus::Passed),
+                "failed" | "canceled" => Some(structs::GitCiStatus::Failed),
+                "skipped" | "manual" => None,
+                _ => Some(structs::GitCiStatus::Running),
+            },
+        })
+        .collect();
+    Ok([
+        structs::GitCiStatus::Running,
+        structs::GitCiStatus::Failed,
+        structs::GitCiStatus::Passed,
+    ]
+    .into_iter()
+    .find(|status| statuses.contains(status)))
+}
+
+/// Puts CI status of the commit first and drops the oldest commits
+#[cfg(feature = "json")]
+fn replace_ci_status(value: &str, oid: &str, status: Option<structs::GitCiStatus>) -> String {
+    status
+        .map(|status| format!("{oid}:{status}"))
+        .into_iter()
+        .chain(
+            value
+                .split(' ')
+                .filter(|pair| pair.rsplit_once(':').is_some_and(|(k, _)| k != oid))
+                .map(String::from),
+        )
+        .take(CI_STATUSES_KEEP)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Repository of the folder with the forge hosting its `origin` remote
+#[cfg(feature = "json")]
+fn forge_repo(path: &Path) -> Result<(git2::Repository, Forge)> {
+    let repo = git2::Repository::discover(path)?;
+    let forge = {
+        let remote = repo.find_remote("origin")?;
+        let url = remote.url().ok_or("remote url isn't UTF-8")?;
+        Forge::from_url(url).ok_or_else(|| format!("forge of {url} isn't supported"))?
+    };
+    Ok((repo, forge))
+}
+
+/// Runs forge CLI in the worktree of the repository, returns its output
+#[cfg(feature = "json")]
+fn run_cli(repo: &git2::Repository, program: &str, args: &[&str]) -> Result<Vec<u8>> {
+    let workdir = repo.workdir().ok_or("bare repositories aren't supported")?;
+    let output = std::process::Command::new(program)
+        .args(args)
+        .current_dir(workdir)
+        .output()
+        .map_err(|err| format!("{program}: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+/// Asks the forge for open pull requests and stores them, returns their number
+#[cfg(feature = "json")]
+pub(crate) fn refresh_pull_requests(path: &Path) -> Result<usize> {
+    let (repo, forge) = forge_repo(path)?;
+    let output = match forge {
+        Forge::GitHub => run_cli(
+            &repo,
+            "gh",
+            &[
+                "pr",
+                "list",
+                "--state",
+                "open",
+                "--limit",
+                "500",
+                "--json",
+                "number,headRefName",
+            ],
+        )?,
+        Forge::GitLab => run_cli(
+            &repo,
+            "glab",
+            &["mr", "list", "--per-page", "100", "--output", "json"],
+        )?,
+    };
+
+    let pull_requests = parse_pull_requests(forge, &output)?;
+    cache::set(
+        PULL_REQUESTS_CACHE,
+        &repo_key(&git_utils::common_dir(&repo)),
+        &encode_pull_requests(&pull_requests),
+    )?;
+    Ok(pull_requests.len())
+}
+
+/// Asks the forge for CI status of HEAD and stores it, returns HEAD and its status
+#[cfg(feature = "json")]
+pub(crate) fn refresh_ci_status(path: &Path) -> Result<(String, Option<structs::GitCiStatus>)> {
+    let (repo, forge) = forge_repo(path)?;
+    let oid = repo.head()?.peel_to_commit()?.id().to_string();
+    let output = match forge {
+        Forge::GitHub => run_cli(
+            &repo,
+            "gh",
+            &[
+                "run",
+                "list",
+                "--commit",
+                &oid,
+                "--limit",
+                "100",
+                "--json",
+                "status,conclusion",
+            ],
+        )?,
+        Forge::GitLab => run_cli(
+            &repo,
+            "glab",
+            &[
+                "api",
+                &format!("projects/:id/pipelines?sha={oid}&per_page=1"),
+            ],
+        )?,
+    };
+
+    let status = parse_ci_runs(forge, &output)?;
+    let key = repo_key(&git_utils::common_dir(&repo));
+    let value = cache::get(CI_STATUSES_CACHE, &key).unwrap_or_default();
+    cache::set(
+        CI_STATUSES_CACHE,
+        &key,
+        &replace_ci_status(&value, &oid, status),
+    )?;
+    Ok((oid, status))
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::find_pair;
+    use super::parse_ci_status;
+    #[cfg(feature = "json")]
+    use super::Forge;
+    use crate::structs;
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    #[case("https://github.com/owner/repo.git", Some(Forge::GitHub))]
+    #[case("git@github.com:owner/repo.git", Some(Forge::GitHub))]
+    #[case("ssh://git@gitlab.example.com/group/repo.git", Some(Forge::GitLab))]
+    #[case("https://example.com/github/repo.git", None)]
+    #[case("/srv/git/repo.git", None)]
+    fn forge_from_url_test(#[case] url: &str, #[case] expected: Option<Forge>) {
+        assert_eq!(Forge::from_url(url), expected);
+    }
+
+    #[rstest]
+    #[case("main:1 feature/login:42", "feature/login", Some("42"))]
+    #[case("main:1 feature/login:42", "feature", None)]
+    #[case("main", "main", None)]
+    #[case("", "main", None)]
+    fn find_pair_test(#[case] value: &str, #[case] key: &str, #[case] expected: Option<&str>) {
+        assert_eq!(find_pair(value, key), expected);
+    }
+
+    #[rstest]
+    #[case(structs::GitCiStatus::Passed)]
+    #[case(structs::GitCiStatus::Failed)]
+    #[case(structs::GitCiStatus::Running)]
+    fn ci_status_round_trip_test(#[case] status: structs::GitCiStatus) {
+        assert_eq!(parse_ci_status(&status.to_string()), Some(status));
+    }
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    #[case(Forge::GitHub, r#"[]"#, None)]
+    #[case(
+        Forge::GitHub,
+        r#"[{"status":"completed","conclusion":"success"},{"status":"completed","conclusion":"skipped"}]"#,
+        Some(structs::GitCiStatus::Passed)
+    )]
+    #[case(
+        Forge::GitHub,
+        r#"[{"status":"completed","conclusion":"success"},{"status":"completed","conclusion":"failure"}]"#,
+        Some(structs::GitCiStatus::Failed)
+    )]
+    #[case(
+        Forge::GitHub,
+        r#"[{"status":"in_progress","conclusion":""},{"status":"completed","conclusion":"failure"}]"#,
+        Some(structs::GitCiStatus::Running)
+    )]
+    #[case(
+        Forge::GitLab,
+        r#"[{"status":"success"}]"#,
+        Some(structs::GitCiStatus::Passed)
+    )]
+    #[case(
+        Forge::GitLab,
+        r#"[{"status":"pending"}]"#,
+        Some(structs::GitCiStatus::Running)
+    )]
+    #[case(Forge::GitLab, r#"[{"status":"manual"}]"#, None)]
+    fn parse_ci_runs_test(
+        #[case] forge: Forge,
+        #[case] output: &str,
+        #[case] expected: Option<structs::GitCiStatus>,
+    ) {
+        assert_eq!(
+            super::parse_ci_runs(forge, output.as_bytes()).unwrap(),
+            expected
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    #[case("", Some(structs::GitCiStatus::Passed), "c:passed")]
+    #[case(
+        "a:failed c:running",
+        Some(structs::GitCiStatus::Passed),
+        "c:passed a:failed"
+    )]
+    #[case("a:failed c:running", None, "a:failed")]
+    fn replace_ci_status_test(
+        #[case] value: &str,
+        #[case] status: Option<structs::GitCiStatus>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(super::replace_ci_status(value, "c", status), expected);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn parse_pull_requests_test() {
+        use super::encode_pull_requests;
+        use super::parse_pull_requests;
+
+        let github = br#"[{"headRefName":"feature/login","number":42},{"number":7}]"#;
+        let parsed = parse_pull_requests(Forge::GitHub, github).unwrap();
+        assert_eq!(parsed, vec![("feature/login".to_string(), 42)]);
+        assert_eq!(encode_pull_requests(&parsed), "feature/login:42");
+
+        let gitlab = br#"[{"source_branch":"fix","iid":3,"id":1000}]"#;
+        assert_eq!(
+            parse_pull_requests(Forge::GitLab, gitlab).unwrap(),
+            vec![("fix".to_string(), 3)]
+        );
+        assert!(parse_pull_requests(Forge::GitHub, b"{}").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Golden files of every theme, `tests/golden/<theme>.txt`, so refactoring of a theme
+//! can't silently change prompts users see.
+//!
+//! Every file has a section per repository state of `demo` subcommand and a few more.
+//! Rules of `ilsore` themes the files pin down:
+//!
+//! - first line is `[date time]user@host`, then `[shell level]`, `[status]`, `[jobs]`,
+//!   `[duration]`, `[python]`, custom segments, git information and `[vi mode]`, no spaces
+//!   between segments
+//! - git information is `(Git: <branch> <flags>)` followed by diff stat, remotes,
+//!   pull request, CI status, today's commits, base age, dirty age, tag distance,
+//!   identity and state, each one after a space, e.g. `(Git: ᚠ feature/login ↑✘● merge)`
+//! - second line is `%~>`
+//!
+//! Escape character is written as `\e`. After an intended change files are rewritten
+//! with `UPDATE_GOLDEN=1 cargo test format_golden`, the diff is reviewed as part of it.
+
+use std::path::PathBuf;
+
+use crate::demo;
+use crate::format_properties::ThemeFunction;
+use crate::format_properties::THEMES;
+use crate::structs;
+
+/// States of `demo` subcommand followed by ones covering the rest of segments
+fn states() -> Vec<(&'static str, structs::ThemeData)> {
+    let mut states: Vec<_> = demo::scenarios()
+        .into_iter()
+        .map(|(name, git)| (name, demo::theme_data(git, None)))
+        .collect();
+
+    let clean = || {
+        demo::git(
+            demo::head("main"),
+            structs::GitFileStatus {
+                conflict: false,
+                untracked: false,
+                typechange: false,
+                unstaged: false,
+                staged: false,
+                counts: structs::GitFileCounts::default(),
+            },
+            (0, 0),
+            None,
+        )
+    };
+
+    let mut failed = demo::theme_data(clean(), None);
+    failed.last_exit_status = 1;
+    failed.jobs = 2;
+    failed.cmd_duration = Some("12s".to_string());
+    failed.python = vec![structs::PythonEnv {
+        name: "venv".to_string(),
+        manager: structs::PythonManager::Virtualenv,
+    }];
+    states.push(("failed-with-jobs", failed));
+
+    let mut nested = demo::theme_data(clean(), None);
+    nested.shell_level = 3;
+    nested.vi_command = true;
+    states.push(("nested-vi-command", nested));
+
+    let everything = structs::GitOutputOptions {
+        repo_name: Some("project".to_string()),
+        diff_stat: Some(structs::GitDiffStat {
+            insertions: 7,
+            deletions: 3,
+        }),
+        remotes: vec![structs::GitRemoteDivergence {
+            remote: "upstream".to_string(),
+            ahead: 1,
+            behind: 4,
+        }],
+        pull_request: Some(42),
+        ci_status: Some(structs::GitCiStatus::Passed),
+        today_commits: Some(3),
+        base_age_days: Some(12),
+        dirty_age_secs: Some(2 * 86_400 + 3_600),
+        tag_distance: Some(structs::GitTagDistance {
+            tag: "v1.2.3".to_string(),
+            ahead: 14,
+        }),
+        identity: Some(structs::GitIdentity {
+            name: Some("Jane Doe".to_string()),
+            email: Some("jane@example.com".to_string()),
+        }),
+        default_branch: Some("main".to_string()),
+        protected: true,
+        large_staged: true,
+        secrets_staged: true,
+        ..clean()
+    };
+    states.push(("everything", demo::theme_data(everything, None)));
+
+    let busy = structs::GitOutputOptions {
+        busy: true,
+        shallow: true,
+        file_status: None,
+        ..clean()
+    };
+    states.push(("busy-shallow", demo::theme_data(busy, None)));
+
+    for (name, signed) in [("signed", true), ("unsigned", false)] {
+        let git = structs::GitOutputOptions {
+            head_info: Some(structs::GitHeadInfo {
+                signed: Some(signed),
+                ..demo::head("main")
+            }),
+            ..clean()
+        };
+        states.push((name, demo::theme_data(git, None)));
+    }
+
+    let untrusted = structs::GitOutputOptions {
+        untrusted: true,
+        ..clean()
+    };
+    states.push(("untrusted", demo::theme_data(untrusted, None)));
+
+    let inside_git_dir = structs::GitOutputOptions {
+        inside_git_dir: true,
+        file_status: None,
+        ..clean()
+    };
+    states.push(("inside-git-dir", demo::theme_data(inside_git_dir, None)));
+
+    let mut outside = demo::theme_data(clean(), None);
+    outside.git = None;
+    states.push(("outside-repository", outside));
+    states
+}
+
+fn golden_path(theme: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{theme}.txt"))
+}
+
+fn render(theme: ThemeFunction) -> String {
+    let symbols = structs::ThemeSymbols::utf8();
+    states()
+        .iter()
+        .map(|(name, data)| {
+            let output = theme(data, &symbols).replace('\x1b', "\\e");
+            format!("## {name}\n{output}\n\n")
+        })
+        .collect()
+}
+
+#[test]
+fn golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some_and(|v| v == "1");
+    for &(name, theme) in THEMES {
+        let path = golden_path(name);
+        let actual = render(theme);
+        if update {
+            std::fs::create_dir_all(path.parent().expect("golden folder"))
+                .expect("golden folder is created");
+            std::fs::write(&path, &actual).expect("golden file is written");
+            continue;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("{}: {err}, run with UPDATE_GOLDEN=1", path.display()));
+        pretty_assertions::assert_eq!(actual, expected, "{name}");
+    }
+}
@@ -0,0 +1,210 @@
+use proptest::prelude::*;
+
+use crate::accessible_format;
+use crate::ilsore_format;
+#[cfg(feature = "color")]
+use crate::ilsore_format_color;
+use crate::integration_format;
+use crate::machine_format;
+use crate::minimal_format;
+#[cfg(feature = "color")]
+use crate::powerline_format;
+use crate::structs;
+use crate::util;
+
+pub(crate) type ThemeFunction = fn(&structs::ThemeData, &structs::ThemeSymbols) -> String;
+
+pub(crate) const THEMES: &[(&str, ThemeFunction)] = &[
+    #[cfg(feature = "color")]
+    ("ilsore-color", ilsore_format_color::format_ilsore_color),
+    ("ilsore-no-color", ilsore_format::format_ilsore_no_color),
+    #[cfg(feature = "color")]
+    (
+        "ilsore-powerline",
+        powerline_format::format_ilsore_powerline,
+    ),
+    ("porcelain", machine_format::format_porcelain),
+    #[cfg(feature = "json")]
+    ("json", machine_format::format_json),
+    ("vim-airline", integration_format::format_statusline),
+    #[cfg(feature = "json")]
+    ("waybar", integration_format::format_waybar),
+    ("starship", integration_format::format_starship),
+    ("minimal", minimal_format::format_minimal),
+    ("accessible", accessible_format::format_accessible),
+];
+
+/// Themes printing zsh prompt escapes
+const ZSH_THEMES: [&str; 3] = ["ilsore-color", "ilsore-no-color", "ilsore-powerline"];
+
+/// Themes printing a single JSON document
+#[cfg(feature = "json")]
+const JSON_THEMES: [&str; 2] = ["json", "waybar"];
+
+#[derive(Debug)]
+struct Input {
+    branch: Option<String>,
+    detached: bool,
+    ahead_behind: Option<(usize, usize)>,
+    file_status: Option<[bool; 5]>,
+    last_exit_status: u8,
+    jobs: usize,
+    max_width: Option<usize>,
+    branch_max_width: Option<usize>,
+    symbols: usize,
+}
+
+/// Branch names without zsh prompt escapes, they aren't quoted by themes
+fn branch() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        "[^%\\p{C}]{1,40}",
+        "[a-zа-я一-龥/_.-]{200,400}",
+    ]
+}
+
+prop_compose! {
+    fn input()(
+        branch in proptest::option::of(branch()),
+        detached in any::<bool>(),
+        ahead_behind in proptest::option::of((0usize..100_000, 0usize..100_000)),
+        file_status in proptest::option::of(any::<[bool; 5]>()),
+        last_exit_status in any::<u8>(),
+        jobs in 0usize..1000,
+        max_width in proptest::option::of(0usize..200),
+        branch_max_width in proptest::option::of(0usize..60),
+        symbols in 0usize..3,
+    ) -> Input {
+        Input {
+            branch,
+            detached,
+            ahead_behind,
+            file_status,
+            last_exit_status,
+            jobs,
+            max_width,
+            branch_max_width,
+            symbols,
+        }
+    }
+}
+
+fn theme_data(input: &Input) -> structs::ThemeData {
+    let git = input
+        .branch
+        .as_ref()
+        .map(|branch| structs::GitOutputOptions {
+            head_info: Some(structs::GitHeadInfo {
+                reference_short: Some(branch.clone()),
+                reference_name: Some(format!("refs/heads/{branch}")),
+                reference_kind: Some(structs::GitReferenceKind::Symbolic),
+                oid: None,
+                oid_short: Some("1234567".to_string()),
+                detached: input.detached,
+                detached_from: input.detached.then(|| branch.clone()),
+                branch_url: None,
+                signed: None,
+                ticket: None,
+            }),
+            file_status: input.file_status.map(|f| structs::GitFileStatus {
+                conflict: f[0],
+                untracked: f[1],
+                typechange: f[2],
+                unstaged: f[3],
+                staged: f[4],
+                counts: structs::GitFileCounts::default(),
+            }),
+            branch_ahead_behind: input
+                .ahead_behind
+                .map(|(ahead, behind)| structs::GitBranchAheadBehind { ahead, behind }),
+            ..Default::default()
+        });
+
+    structs::ThemeData {
+        last_exit_status: input.last_exit_status,
+        jobs: input.jobs,
+        shell_level: 0,
+        vi_command: false,
+        cmd_duration: None,
+        datetime: structs::DateTime {
+            date: Box::new("2025-04-12"),
+            time: Box::new("12:00:00"),
+            show_date: true,
+        },
+        hostname: Some("host.local".to_string()),
+        username: Some("user".to_string()),
+        python: Vec::new(),
+        git,
+        repo_path: None,
+        max_width: input.max_width,
+        branch_max_width: input.branch_max_width,
+        slow: Vec::new(),
+        layout: None,
+        custom: Vec::new(),
+    }
+}
+
+fn symbols(idx: usize) -> structs::ThemeSymbols {
+    match idx {
+        0 => structs::ThemeSymbols::utf8_power(),
+        1 => structs::ThemeSymbols::utf8(),
+        _ => structs::ThemeSymbols::ascii(),
+    }
+}
+
+/// Every `%{` escape is closed with `%}` before the next one starts
+fn balanced_escapes(text: &str) -> bool {
+    let mut rest = text;
+    while let Some(idx) = rest.find("%{") {
+        let escaped = &rest[idx + 2..];
+        match (escaped.find("%}"), escaped.find("%{")) {
+            (Some(end), Some(next)) if next < end => return false,
+            (Some(end), _) => rest = &escaped[end + 2..],
+            (None, _) => return false,
+        }
+    }
+    !rest.contains("%}")
+}
+
+proptest! {
+    #[test]
+    fn themes_properties(input in input()) {
+        let data = theme_data(&input);
+        let symbols = symbols(input.symbols);
+
+        for &(name, theme) in THEMES {
+            let output = theme(&data, &symbols);
+
+            if ZSH_THEMES.contains(&name) {
+                prop_assert!(balanced_escapes(&output), "{name}: {output:?}");
+            }
+            #[cfg(feature = "json")]
+            if JSON_THEMES.contains(&name) {
+                prop_assert!(
+                    serde_json::from_str::<serde_json::Value>(&output).is_ok(),
+                    "{name}: {output:?}"
+                );
+            }
+            if name.starts_with("ilsore-") {
+                if let Some(max_width) = input.max_width {
+                    let first_line = output.lines().next().unwrap_or_default();
+                    prop_assert!(
+                        util::prompt_width(first_line) <= max_width,
+                        "{name}: {first_line:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truncate_to_width_properties(
+        text in "\\PC{0,80}",
+        max_width in proptest::option::of(0usize..100),
+    ) {
+        let result = util::truncate_to_width(&text, max_width);
+        prop_assert!(util::display_width(&result) <= max_width.unwrap_or(usize::MAX));
+        let kept = result.strip_suffix('…').unwrap_or(&result);
+        prop_assert!(text.starts_with(kept));
+    }
+}
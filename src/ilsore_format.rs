@@ -1,21 +1,57 @@
 use std::borrow::Cow;
 
+use crate::date_time;
 use crate::structs;
+use crate::util;
+use crate::util::ShortHost;
+
+/// Order of the first line segments in `ilsore` themes, custom segments precede git
+/// and vi mode is the closest to the command line
+pub(crate) static DEFAULT_ORDER: &[structs::LayoutItem] = &[
+    structs::LayoutItem::prompt(structs::PromptSegment::DateTime),
+    structs::LayoutItem::prompt(structs::PromptSegment::UserHost),
+    structs::LayoutItem::prompt(structs::PromptSegment::ShellLevel),
+    structs::LayoutItem::prompt(structs::PromptSegment::LastStatus),
+    structs::LayoutItem::prompt(structs::PromptSegment::Jobs),
+    structs::LayoutItem::prompt(structs::PromptSegment::CmdDuration),
+    structs::LayoutItem::prompt(structs::PromptSegment::Python),
+    structs::LayoutItem::Segment(structs::SegmentName::OtherCustom),
+    structs::LayoutItem::prompt(structs::PromptSegment::Git),
+    structs::LayoutItem::prompt(structs::PromptSegment::ViMode),
+];
 
 pub(crate) fn format_ilsore_no_color(
     data: &structs::ThemeData,
     symbols: &structs::ThemeSymbols,
 ) -> String {
-    let date_time = format!("[{} {}]", data.datetime.date, data.datetime.time,);
+    let time = format!("[{}]", data.datetime.time);
+    let date_time = match data.datetime.show_date {
+        true => format!("[{} {}]", data.datetime.date, data.datetime.time),
+        false => time.clone(),
+    };
 
-    let user_host = format!(
-        "{}@{}",
-        data.username.as_deref().unwrap_or_default(),
-        data.hostname.as_deref().unwrap_or_default(),
-    );
-    let python = data.python.as_ref().map(|v| format!("[{}]", v));
+    let username = data.username.as_deref().unwrap_or_default();
+    let hostname = data.hostname.as_deref().unwrap_or(slow_marker(
+        data,
+        structs::SegmentKind::Hostname,
+        symbols,
+    ));
+    let user_host = format!("{}@{}", username, hostname);
+    let user_host_short = format!("{}@{}", username, hostname.short_host());
 
-    let git = data.git.as_ref().map(|v| format_ilsore_git(v, symbols));
+    let python = data
+        .python_text(symbols)
+        .or_else(|| slow_segment(data, structs::SegmentKind::Python, symbols).map(String::from))
+        .map(|v| format!("[{}]", v));
+
+    let git = data
+        .git
+        .as_ref()
+        .map(|v| format_ilsore_git(v, symbols, data.branch_max_width, data.repo_path.as_deref()))
+        .or_else(|| {
+            slow_segment(data, structs::SegmentKind::Git, symbols)
+                .map(|v| format!("(Git: {})", v).into())
+        });
 
     let last_status: Cow<str> = if data.last_exit_status != 0 {
         format!("[{}]", data.last_exit_status).into()
@@ -23,93 +59,368 @@ pub(crate) fn format_ilsore_no_color(
         Cow::Borrowed("")
     };
 
-    format!(
-        "{}{}{}{}{}\n%~>",
-        date_time,
-        user_host,
-        last_status,
-        python.as_deref().unwrap_or_default(),
-        git.as_deref().unwrap_or_default(),
-    )
+    let jobs: Cow<str> = match data.jobs {
+        0 => Cow::Borrowed(""),
+        n => format!("[{}{}]", symbols.jobs, n).into(),
+    };
+
+    let cmd_duration = data
+        .cmd_duration
+        .as_ref()
+        .map(|v| format!("[{}{}]", symbols.cmd_duration, v));
+
+    let shell_level =
+        (data.shell_level > 1).then(|| format!("[{}{}]", symbols.shell_level, data.shell_level));
+
+    let vi_mode = data.vi_command.then(|| format!("[{}]", symbols.vi_command));
+
+    let render = |shown| {
+        let segment = match shown {
+            structs::ShownSegment::Prompt(kind) => match kind {
+                structs::PromptSegment::DateTime => {
+                    util::Segment::new(1, date_time.clone()).with_short(time.clone())
+                }
+                structs::PromptSegment::UserHost => {
+                    util::Segment::new(2, user_host.clone()).with_short(user_host_short.clone())
+                }
+                structs::PromptSegment::Python => util::Segment::new(3, python.clone()?),
+                structs::PromptSegment::Git => util::Segment::new(4, git.clone()?),
+                structs::PromptSegment::Jobs => util::Segment::new(5, jobs.clone()),
+                structs::PromptSegment::CmdDuration => util::Segment::new(3, cmd_duration.clone()?),
+                structs::PromptSegment::LastStatus => util::Segment::new(5, last_status.clone()),
+                structs::PromptSegment::ShellLevel => util::Segment::new(3, shell_level.clone()?),
+                structs::PromptSegment::ViMode => util::Segment::new(5, vi_mode.clone()?),
+            },
+            structs::ShownSegment::Custom(v) => util::Segment::new(3, format!("[{}]", v.text)),
+        };
+        Some(segment).filter(|v| !v.is_empty())
+    };
+
+    let segments = data.arranged(DEFAULT_ORDER, render);
+    let first_line = util::fit_segments(
+        segments.into_iter().map(util::Segment::from).collect(),
+        data.max_width,
+    );
+
+    format!("{}\n%~>", first_line)
 }
 
 #[inline]
 fn format_ilsore_git(
     data: &structs::GitOutputOptions,
     symbols: &structs::ThemeSymbols,
+    branch_max_width: Option<usize>,
+    repo_path: Option<&str>,
 ) -> Cow<'static, str> {
+    if data.untrusted {
+        return format!("(Git: {})", symbols.git_untrusted).into();
+    }
     if data.head_info.is_none() {
-        return Cow::Borrowed("");
+        return match (data.damaged, data.slow) {
+            (true, _) => format!("(Git: {})", symbols.git_damaged).into(),
+            (false, true) => format!("(Git: {})", symbols.slow).into(),
+            (false, false) => Cow::Borrowed(""),
+        };
     }
 
+    let location = format!(
+        "{}{}",
+        format_ilsore_nesting(data, symbols),
+        format_ilsore_location(data.repo_name.as_deref(), repo_path)
+    );
+
+    let state = data
+        .state
+        .as_ref()
+        .map(|v| format!(" {}", v))
+        .unwrap_or_default();
+
+    let diff_stat = data
+        .diff_stat
+        .as_ref()
+        .map(|v| format!(" {}", format_diff_stat(v)))
+        .unwrap_or_default();
+    let remotes = Some(format_remotes(&data.remotes, symbols))
+        .filter(|v| !v.is_empty())
+        .map(|v| format!(" {}", v))
+        .unwrap_or_default();
+    let pull_request = data
+        .pull_request
+        .map(|v| format!(" {}", format_pull_request(v)))
+        .unwrap_or_default();
+    let ci_status = data
+        .ci_status
+        .map(|v| format!(" {}", format_ci_status(v, symbols)))
+        .unwrap_or_default();
+    let today_commits = format_today_commits(data.today_commits)
+        .map(|v| format!(" {}", v))
+        .unwrap_or_default();
+    let base_age = data
+        .base_age_days
+        .map(|v| format!(" {}", format_base_age(v)))
+        .unwrap_or_default();
+    let dirty_age = data
+        .dirty_age_secs
+        .map(|v| format!(" {}", format_dirty_age(v)))
+        .unwrap_or_default();
+    let tag_distance = data
+        .tag_distance
+        .as_ref()
+        .map(|v| format!(" {}", format_tag_distance(v)))
+        .unwrap_or_default();
+    let identity = data
+        .identity
+        .as_ref()
+        .map(|v| format!(" {}", format_identity(v)))
+        .unwrap_or_default();
+
     format!(
-        "(Git: {} {})",
-        format_ilsore_git_head_info(&data.head_info, symbols)
+        "(Git: {}{} {}{}{}{}{}{}{}{}{}{}{})",
+        location,
+        format_ilsore_git_head_info(&data.head_info, symbols, branch_max_width)
             .as_deref()
             .unwrap_or_default(),
-        format_ilsore_git_symbols(
-            &data.head_info,
-            &data.file_status,
-            &data.branch_ahead_behind,
-            symbols
-        )
+        format_ilsore_git_symbols(data, symbols),
+        diff_stat,
+        remotes,
+        pull_request,
+        ci_status,
+        today_commits,
+        base_age,
+        dirty_age,
+        tag_distance,
+        identity,
+        state,
     )
     .into()
 }
 
+/// Marker for a segment skipped by timeout, shared with the color theme
 #[inline]
-fn format_ilsore_git_head_info(
+pub(crate) fn slow_segment(
+    data: &structs::ThemeData,
+    segment: structs::SegmentKind,
+    symbols: &structs::ThemeSymbols,
+) -> Option<&'static str> {
+    data.is_slow(segment).then_some(symbols.slow)
+}
+
+#[inline]
+fn slow_marker(
+    data: &structs::ThemeData,
+    segment: structs::SegmentKind,
+    symbols: &structs::ThemeSymbols,
+) -> &'static str {
+    slow_segment(data, segment, symbols).unwrap_or_default()
+}
+
+/// Formats `+120 -43` of changed lines, shared with other themes
+#[inline]
+pub(crate) fn format_diff_stat(diff_stat: &structs::GitDiffStat) -> String {
+    format!("+{} -{}", diff_stat.insertions, diff_stat.deletions)
+}
+
+/// Formats `#42` of the open pull request, shared with other themes
+#[inline]
+pub(crate) fn format_pull_request(number: u64) -> String {
+    format!("#{number}")
+}
+
+/// Formats `3 today` of commits made today, nothing is shown before the first one,
+/// shared with other themes
+#[inline]
+pub(crate) fn format_today_commits(count: Option<usize>) -> Option<String> {
+    count.filter(|v| *v > 0).map(|v| format!("{v} today"))
+}
+
+/// Formats `dirty 2d` of the oldest uncommitted change, shared with other themes
+#[inline]
+pub(crate) fn format_dirty_age(secs: u64) -> String {
+    format!(
+        "dirty {}",
+        date_time::format_duration(secs.max(1) * 1000, 1)
+    )
+}
+
+/// Formats `v1.2.3+14` of commits after the latest tag, the tag alone if HEAD is tagged,
+/// shared with other themes
+#[inline]
+pub(crate) fn format_tag_distance(distance: &structs::GitTagDistance) -> String {
+    match distance.ahead {
+        0 => distance.tag.clone(),
+        ahead => format!("{}+{ahead}", distance.tag),
+    }
+}
+
+/// Formats `Name <email>` as git does, either part may be missing
+#[inline]
+pub(crate) fn format_identity(identity: &structs::GitIdentity) -> String {
+    match (&identity.name, &identity.email) {
+        (Some(name), Some(email)) => format!("{name} <{email}>"),
+        (Some(name), None) => name.clone(),
+        (None, Some(email)) => format!("<{email}>"),
+        (None, None) => "<no identity>".to_string(),
+    }
+}
+
+/// Formats `base 12d old` of merge base with the tracking branch, shared with other themes
+#[inline]
+pub(crate) fn format_base_age(days: u64) -> String {
+    format!("base {days}d old")
+}
+
+/// Symbol of CI status of HEAD, shared with other themes
+#[inline]
+pub(crate) fn format_ci_status(
+    status: structs::GitCiStatus,
+    symbols: &structs::ThemeSymbols,
+) -> &'static str {
+    match status {
+        structs::GitCiStatus::Passed => symbols.git_ci_passed,
+        structs::GitCiStatus::Failed => symbols.git_ci_failed,
+        structs::GitCiStatus::Running => symbols.git_ci_running,
+    }
+}
+
+/// Formats `origin↑2 upstream↓10` of remotes diverged from their default branch,
+/// shared with other themes
+#[inline]
+pub(crate) fn format_remotes(
+    remotes: &[structs::GitRemoteDivergence],
+    symbols: &structs::ThemeSymbols,
+) -> String {
+    remotes
+        .iter()
+        .filter(|r| r.ahead > 0 || r.behind > 0)
+        .map(|r| {
+            let ahead = match r.ahead {
+                0 => String::new(),
+                n => format!("{}{}", symbols.git_is_ahead, n),
+            };
+            let behind = match r.behind {
+                0 => String::new(),
+                n => format!("{}{}", symbols.git_is_behind, n),
+            };
+            format!("{}{}{}", r.remote, ahead, behind)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Formats `outer-branch⧉ ` prefix of a nested repository, shared with other themes
+#[inline]
+pub(crate) fn format_ilsore_nesting(
+    data: &structs::GitOutputOptions,
+    symbols: &structs::ThemeSymbols,
+) -> String {
+    match (data.nested, &data.outer_branch) {
+        (true, Some(branch)) => format!("{}{} ", branch, symbols.git_nested),
+        (true, None) => format!("{} ", symbols.git_nested),
+        (false, _) => String::new(),
+    }
+}
+
+/// Formats `repo:path ` prefix for the git segment, shared with the color theme
+#[inline]
+pub(crate) fn format_ilsore_location(repo_name: Option<&str>, repo_path: Option<&str>) -> String {
+    match (repo_name, repo_path) {
+        (Some(name), Some(path)) => format!("{}:{} ", name, path),
+        (Some(name), None) => format!("{} ", name),
+        (None, Some(path)) => format!(":{} ", path),
+        (None, None) => String::new(),
+    }
+}
+
+#[inline]
+pub(crate) fn format_ilsore_git_head_info(
     head_info: &Option<structs::GitHeadInfo>,
     symbols: &structs::ThemeSymbols,
+    branch_max_width: Option<usize>,
 ) -> Option<String> {
     head_info.as_ref().and_then(|h| {
-        h.reference_short
-            .as_ref()
-            .map(|v| format!("{} {}", symbols.git_branch, v))
+        h.branch_label()
+            .map(|v| util::truncate_to_width(&v, branch_max_width).into_owned())
+            .map(|v| match &h.branch_url {
+                Some(url) => format!("{} {}", symbols.git_branch, util::hyperlink(url, &v)),
+                None => format!("{} {}", symbols.git_branch, v),
+            })
+            .filter(|_| h.detached_from.is_none())
+            .or(h
+                .detached_from
+                .as_ref()
+                .map(|v| format!("detached from {}", v)))
             .or(h.oid_short.as_ref().map(String::to_string))
     })
 }
 
 #[inline]
-fn format_ilsore_git_symbols(
-    head_info: &Option<structs::GitHeadInfo>,
-    file_status: &Option<structs::GitFileStatus>,
-    branch_ahead_behind: &Option<structs::GitBranchAheadBehind>,
+pub(crate) fn format_ilsore_git_symbols(
+    data: &structs::GitOutputOptions,
     symbols: &structs::ThemeSymbols,
 ) -> String {
+    let head_info = &data.head_info;
+    let file_status = &data.file_status;
+    let branch_ahead_behind = &data.branch_ahead_behind;
+
     format!(
-        "{}{}{}{}{}{}{}{}{}",
+        "{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}",
+        symbol(data.inside_git_dir, symbols.git_inside_git_dir),
+        symbol(data.untrusted, symbols.git_untrusted),
+        symbol(data.damaged, symbols.git_damaged),
+        symbol(data.slow, symbols.slow),
+        symbol(data.reduced, symbols.git_reduced),
+        symbol(data.busy, symbols.git_busy),
+        symbol(data.identity_mismatch, symbols.git_identity_mismatch),
+        symbol(data.protected, symbols.git_protected),
+        symbol(data.large_staged, symbols.git_large_staged),
+        symbol(data.secrets_staged, symbols.git_secrets_staged),
+        symbol(data.read_only, symbols.git_read_only),
+        symbol(data.shallow, symbols.git_shallow),
+        symbol(data.hooks, symbols.git_hooks),
+        symbol(data.from_env, symbols.git_from_env),
+        data.lfs.as_ref().map_or("", |v| match v.unpulled {
+            0 => symbols.git_lfs,
+            _ => symbols.git_lfs_unpulled,
+        }),
+        head_info
+            .as_ref()
+            .and_then(|h| h.signed)
+            .map_or("", |v| match v {
+                true => symbols.git_signed,
+                false => symbols.git_unsigned,
+            }),
+        symbol(data.on_default_branch(), symbols.git_default_branch),
         symbol(
-            head_info.as_ref().map_or(false, |b| b.detached),
+            head_info.as_ref().is_some_and(|b| b.detached),
             symbols.git_branch_detached
         ),
         symbol(branch_ahead_behind.is_none(), symbols.git_has_no_upstream),
         symbol(
-            branch_ahead_behind.as_ref().map_or(false, |b| b.ahead > 0),
+            branch_ahead_behind.as_ref().is_some_and(|b| b.ahead > 0),
             symbols.git_is_ahead
         ),
         symbol(
-            branch_ahead_behind.as_ref().map_or(false, |b| b.behind > 0),
+            branch_ahead_behind.as_ref().is_some_and(|b| b.behind > 0),
             symbols.git_is_behind
         ),
         symbol(
-            file_status.as_ref().map_or(false, |b| b.conflict),
+            file_status.as_ref().is_some_and(|b| b.conflict),
             symbols.git_has_conflict
         ),
         symbol(
-            file_status.as_ref().map_or(false, |b| b.untracked),
+            file_status.as_ref().is_some_and(|b| b.untracked),
             symbols.git_has_untracked
         ),
         symbol(
-            file_status.as_ref().map_or(false, |b| b.typechange),
+            file_status.as_ref().is_some_and(|b| b.typechange),
             symbols.git_has_typechange
         ),
         symbol(
-            file_status.as_ref().map_or(false, |b| b.unstaged),
+            file_status.as_ref().is_some_and(|b| b.unstaged),
             symbols.git_has_unstaged
         ),
         symbol(
-            file_status.as_ref().map_or(false, |b| b.staged),
+            file_status.as_ref().is_some_and(|b| b.staged),
             symbols.git_has_staged
         ),
     )
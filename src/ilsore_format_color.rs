@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
+use crate::ilsore_format;
 use crate::structs;
+use crate::util;
+use crate::util::ShortHost;
 
 static RESET_COLOR: &str = "%{[0m%}";
 
@@ -8,28 +11,45 @@ pub(crate) fn format_ilsore_color(
     data: &structs::ThemeData,
     symbols: &structs::ThemeSymbols,
 ) -> String {
-    let date_time = format!(
-        "[{}{}{RESET_COLOR} {}{}{RESET_COLOR}]",
-        format_color("165"),
-        data.datetime.date,
+    let time = format!(
+        "[{}{}{RESET_COLOR}]",
         format_color("226"),
         data.datetime.time,
     );
+    let date_time = match data.datetime.show_date {
+        true => format!(
+            "[{}{}{RESET_COLOR} {}{}{RESET_COLOR}]",
+            format_color("165"),
+            data.datetime.date,
+            format_color("226"),
+            data.datetime.time,
+        ),
+        false => time.clone(),
+    };
 
-    let user_host = format!(
-        "{}{}{RESET_COLOR}@{}{}{RESET_COLOR}",
-        format_color("214"),
-        data.username.as_deref().unwrap_or_default(),
-        format_color("46"),
-        data.hostname.as_deref().unwrap_or_default(),
+    let hostname = data.hostname.as_deref().unwrap_or(
+        ilsore_format::slow_segment(data, structs::SegmentKind::Hostname, symbols)
+            .unwrap_or_default(),
     );
+    let user_host = format_user_host(data.username.as_deref(), hostname);
+    let user_host_short = format_user_host(data.username.as_deref(), hostname.short_host());
 
     let python = data
-        .python
-        .as_ref()
+        .python_text(symbols)
+        .or_else(|| {
+            ilsore_format::slow_segment(data, structs::SegmentKind::Python, symbols)
+                .map(String::from)
+        })
         .map(|v| format!("[{}{}{RESET_COLOR}]", format_color_bold("42"), v));
 
-    let git = data.git.as_ref().map(|v| format_ilsore_git(v, symbols));
+    let git = data
+        .git
+        .as_ref()
+        .map(|v| format_ilsore_git(v, symbols, data.branch_max_width, data.repo_path.as_deref()))
+        .or_else(|| {
+            ilsore_format::slow_segment(data, structs::SegmentKind::Git, symbols)
+                .map(|v| format!("({}Git: {}{RESET_COLOR})", format_color("magenta"), v).into())
+        });
 
     let last_status: Cow<str> = if data.last_exit_status != 0 {
         format!(
@@ -42,14 +62,113 @@ pub(crate) fn format_ilsore_color(
         Cow::Borrowed("") // same size as String on stack but no heap alloc.
     };
 
+    let jobs: Cow<str> = match data.jobs {
+        0 => Cow::Borrowed(""),
+        n => format!("[{}{}{}{RESET_COLOR}]", format_color("75"), symbols.jobs, n).into(),
+    };
+
+    let cmd_duration = data.cmd_duration.as_ref().map(|v| {
+        format!(
+            "[{}{}{}{RESET_COLOR}]",
+            format_color("180"),
+            symbols.cmd_duration,
+            v
+        )
+    });
+
+    let shell_level = (data.shell_level > 1).then(|| {
+        format!(
+            "[{}{}{}{RESET_COLOR}]",
+            format_color("141"),
+            symbols.shell_level,
+            data.shell_level
+        )
+    });
+
+    let vi_mode = data.vi_command.then(|| {
+        format!(
+            "[{}{}{RESET_COLOR}]",
+            format_color_bold("208"),
+            symbols.vi_command
+        )
+    });
+
+    let render = |shown| {
+        let background = match shown {
+            structs::ShownSegment::Prompt(kind) => {
+                data.layout.as_ref().and_then(|v| v.colors[kind])
+            }
+            structs::ShownSegment::Custom(_) => None,
+        };
+        let segment = match shown {
+            structs::ShownSegment::Prompt(kind) => match kind {
+                structs::PromptSegment::DateTime => {
+                    util::Segment::new(1, date_time.clone()).with_short(time.clone())
+                }
+                structs::PromptSegment::UserHost => {
+                    util::Segment::new(2, user_host.clone()).with_short(user_host_short.clone())
+                }
+                structs::PromptSegment::Python => util::Segment::new(3, python.clone()?),
+                structs::PromptSegment::Git => util::Segment::new(4, git.clone()?),
+                structs::PromptSegment::Jobs => util::Segment::new(5, jobs.clone()),
+                structs::PromptSegment::CmdDuration => util::Segment::new(3, cmd_duration.clone()?),
+                structs::PromptSegment::LastStatus => util::Segment::new(5, last_status.clone()),
+                structs::PromptSegment::ShellLevel => util::Segment::new(3, shell_level.clone()?),
+                structs::PromptSegment::ViMode => util::Segment::new(5, vi_mode.clone()?),
+            },
+            structs::ShownSegment::Custom(v) => {
+                let color = format_color(v.color.unwrap_or("39"));
+                util::Segment::new(3, format!("[{}{}{RESET_COLOR}]", color, v.text))
+            }
+        };
+        let segment = match background {
+            Some(color) => segment.map(|v| on_background(v, color)),
+            None => segment,
+        };
+        Some(segment).filter(|v| !v.is_empty())
+    };
+
+    let segments = data
+        .arranged(ilsore_format::DEFAULT_ORDER, render)
+        .into_iter()
+        .map(|v| {
+            let background = match &v {
+                structs::Arranged::Group(group, _) => group.color,
+                structs::Arranged::Segment(_) => None,
+            };
+            let segment = util::Segment::from(v);
+            match background {
+                Some(color) => segment.map(|v| on_background(v, color)),
+                None => segment,
+            }
+        })
+        .collect();
+    let first_line = util::fit_segments(segments, data.max_width);
+
+    format!("{}\n{}%~{RESET_COLOR}>", first_line, format_color("87"),)
+}
+
+#[inline]
+fn format_user_host(username: Option<&str>, hostname: &str) -> String {
+    format!(
+        "{}{}{RESET_COLOR}@{}{}{RESET_COLOR}",
+        format_color("214"),
+        username.unwrap_or_default(),
+        format_color("46"),
+        hostname,
+    )
+}
+
+/// Draws the text on the background, resets inside of it keep the background
+fn on_background(text: &str, color: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let background = format!("%{{%K{{{color}}}%}}");
+    let text = text.strip_suffix(RESET_COLOR).unwrap_or(text);
     format!(
-        "{}{}{}{}{}\n{}%~{RESET_COLOR}>",
-        date_time,
-        user_host,
-        last_status,
-        python.as_deref().unwrap_or_default(),
-        git.as_deref().unwrap_or_default(),
-        format_color("87"),
+        "{background}{}{RESET_COLOR}",
+        text.replace(RESET_COLOR, &format!("{RESET_COLOR}{background}"))
     )
 }
 
@@ -69,28 +188,127 @@ fn format_color_bold(color: &str) -> String {
 fn format_ilsore_git(
     data: &structs::GitOutputOptions,
     symbols: &structs::ThemeSymbols,
+    branch_max_width: Option<usize>,
+    repo_path: Option<&str>,
 ) -> Cow<'static, str> {
+    if data.untrusted {
+        return format!(
+            "({}Git: {}{}{RESET_COLOR})",
+            format_color("magenta"),
+            format_color_bold("red"),
+            symbols.git_untrusted
+        )
+        .into();
+    }
     if data.head_info.is_none() {
-        return Cow::Borrowed("");
+        return match (data.damaged, data.slow) {
+            (true, _) => format!(
+                "({}Git: {}{}{RESET_COLOR})",
+                format_color("magenta"),
+                format_color_bold("red"),
+                symbols.git_damaged
+            )
+            .into(),
+            (false, true) => format!(
+                "({}Git: {}{RESET_COLOR})",
+                format_color("magenta"),
+                symbols.slow
+            )
+            .into(),
+            (false, false) => Cow::Borrowed(""),
+        };
     }
 
-    let git_info = vec![
+    let location = format!(
+        "{}{}",
+        ilsore_format::format_ilsore_nesting(data, symbols),
+        ilsore_format::format_ilsore_location(data.repo_name.as_deref(), repo_path)
+    );
+
+    let mut git_info = vec![
         data.head_info
             .as_ref()
-            .and_then(|h| format_ilsore_git_branch(h, symbols))
+            .and_then(|h| {
+                format_ilsore_git_branch(h, symbols, branch_max_width, data.on_default_branch())
+            })
             .unwrap_or_default(),
-        format_ilsore_git_symbols(
-            &data.head_info,
-            &data.file_status,
-            &data.branch_ahead_behind,
-            symbols,
-        )
-        .unwrap_or_default(),
+        format_ilsore_git_symbols(data, symbols).unwrap_or_default(),
     ];
+    // changed lines are shown only if they're counted, so no extra space is left
+    if let Some(v) = &data.diff_stat {
+        git_info.push(format!(
+            "{}+{} {}-{}{RESET_COLOR}",
+            format_color("green"),
+            v.insertions,
+            format_color("red"),
+            v.deletions
+        ));
+    }
+    let remotes = ilsore_format::format_remotes(&data.remotes, symbols);
+    if !remotes.is_empty() {
+        git_info.push(format!("{}{remotes}{RESET_COLOR}", format_color("75")));
+    }
+    if let Some(number) = data.pull_request {
+        git_info.push(format!(
+            "{}{}{RESET_COLOR}",
+            format_color("141"),
+            ilsore_format::format_pull_request(number)
+        ));
+    }
+    if let Some(status) = data.ci_status {
+        let color = match status {
+            structs::GitCiStatus::Passed => "green",
+            structs::GitCiStatus::Failed => "red",
+            structs::GitCiStatus::Running => "yellow",
+        };
+        git_info.push(format!(
+            "{}{}{RESET_COLOR}",
+            format_color(color),
+            ilsore_format::format_ci_status(status, symbols)
+        ));
+    }
+    if let Some(v) = ilsore_format::format_today_commits(data.today_commits) {
+        git_info.push(format!("{}{v}{RESET_COLOR}", format_color("180")));
+    }
+    if let Some(days) = data.base_age_days {
+        git_info.push(format!(
+            "{}{}{RESET_COLOR}",
+            format_color("214"),
+            ilsore_format::format_base_age(days)
+        ));
+    }
+    if let Some(secs) = data.dirty_age_secs {
+        git_info.push(format!(
+            "{}{}{RESET_COLOR}",
+            format_color("173"),
+            ilsore_format::format_dirty_age(secs)
+        ));
+    }
+    if let Some(distance) = &data.tag_distance {
+        git_info.push(format!(
+            "{}{}{RESET_COLOR}",
+            format_color("109"),
+            ilsore_format::format_tag_distance(distance)
+        ));
+    }
+    if let Some(identity) = &data.identity {
+        git_info.push(format!(
+            "{}{}{RESET_COLOR}",
+            format_color("110"),
+            ilsore_format::format_identity(identity)
+        ));
+    }
+    git_info.push(
+        data.state
+            .as_ref()
+            .map(|v| format!("{}{}{RESET_COLOR}", format_color_bold("214"), v))
+            .unwrap_or_default(),
+    );
 
     format!(
-        "({}Git: {}{RESET_COLOR})",
+        "({}Git: {}{}{RESET_COLOR})",
         format_color("magenta"),
+        location,
         git_info.join(" ")
     )
     .into()
@@ -100,42 +318,61 @@ fn format_ilsore_git(
 fn format_ilsore_git_branch(
     head_info: &structs::GitHeadInfo,
     symbols: &structs::ThemeSymbols,
+    branch_max_width: Option<usize>,
+    on_default_branch: bool,
 ) -> Option<String> {
     if head_info.reference_short.is_none() && head_info.oid_short.is_none() {
         return None;
     };
-    if head_info.reference_short.is_none() || head_info.detached {
+    if let Some(detached_from) = head_info.detached_from.as_ref() {
+        Some(format!(
+            "{}detached from {}{RESET_COLOR}",
+            format_color_bold("201"),
+            util::truncate_to_width(detached_from, branch_max_width)
+        ))
+    } else if head_info.reference_short.is_none() || head_info.detached {
         Some(format!(
             "{}{}{RESET_COLOR}",
             format_color_bold("201"),
             head_info.oid_short.as_deref().unwrap_or_default()
         ))
     } else {
+        let branch_label = head_info.branch_label().unwrap_or_default();
+        let reference_short = util::truncate_to_width(&branch_label, branch_max_width);
+        let branch: Cow<str> = match &head_info.branch_url {
+            Some(url) => util::hyperlink(url, &reference_short).into(),
+            None => reference_short,
+        };
+        // the default branch is dimmed, so feature branches stand out
+        let color = match on_default_branch {
+            true => format_color("144"),
+            false => format_color_bold("226"),
+        };
         Some(format!(
-            "{}{} {}{RESET_COLOR}",
-            format_color_bold("226"),
-            symbols.git_branch,
-            head_info.reference_short.as_deref().unwrap_or_default()
+            "{color}{} {}{RESET_COLOR}",
+            symbols.git_branch, branch
         ))
     }
 }
 
 #[inline]
 fn format_ilsore_git_symbols(
-    head_info: &Option<structs::GitHeadInfo>,
-    file_status: &Option<structs::GitFileStatus>,
-    branch_ahead_behind: &Option<structs::GitBranchAheadBehind>,
+    data: &structs::GitOutputOptions,
     symbols: &structs::ThemeSymbols,
 ) -> Option<String> {
-    let detached = head_info.as_ref().map_or(false, |b| b.detached);
+    let head_info = &data.head_info;
+    let file_status = &data.file_status;
+    let branch_ahead_behind = &data.branch_ahead_behind;
+
+    let detached = head_info.as_ref().is_some_and(|b| b.detached);
     let no_upstream = branch_ahead_behind.is_none();
-    let is_ahead = branch_ahead_behind.as_ref().map_or(false, |b| b.ahead > 0);
-    let is_behind = branch_ahead_behind.as_ref().map_or(false, |b| b.behind > 0);
-    let has_staged = file_status.as_ref().map_or(false, |b| b.staged);
-    let has_unstaged = file_status.as_ref().map_or(false, |b| b.unstaged);
-    let has_typechange = file_status.as_ref().map_or(false, |b| b.typechange);
-    let has_conflict = file_status.as_ref().map_or(false, |b| b.conflict);
-    let has_untracked = file_status.as_ref().map_or(false, |b| b.untracked);
+    let is_ahead = branch_ahead_behind.as_ref().is_some_and(|b| b.ahead > 0);
+    let is_behind = branch_ahead_behind.as_ref().is_some_and(|b| b.behind > 0);
+    let has_staged = file_status.as_ref().is_some_and(|b| b.staged);
+    let has_unstaged = file_status.as_ref().is_some_and(|b| b.unstaged);
+    let has_typechange = file_status.as_ref().is_some_and(|b| b.typechange);
+    let has_conflict = file_status.as_ref().is_some_and(|b| b.conflict);
+    let has_untracked = file_status.as_ref().is_some_and(|b| b.untracked);
 
     let detached_branch_symbols = vec![match (detached, no_upstream) {
         (true, _) => symbol_bold(true, symbols.git_branch_detached, "26"),
@@ -150,6 +387,31 @@ fn format_ilsore_git_symbols(
     }];
 
     let file_status_symbols = vec![
+        symbol_bold(data.inside_git_dir, symbols.git_inside_git_dir, "yellow"),
+        symbol_bold(data.untrusted, symbols.git_untrusted, "red"),
+        symbol_bold(data.damaged, symbols.git_damaged, "red"),
+        symbol(data.slow, symbols.slow, "yellow"),
+        symbol(data.reduced, symbols.git_reduced, "yellow"),
+        symbol_bold(data.busy, symbols.git_busy, "yellow"),
+        symbol_bold(data.identity_mismatch, symbols.git_identity_mismatch, "red"),
+        symbol_bold(data.protected, symbols.git_protected, "red"),
+        symbol_bold(data.large_staged, symbols.git_large_staged, "yellow"),
+        symbol_bold(data.secrets_staged, symbols.git_secrets_staged, "red"),
+        symbol_bold(data.read_only, symbols.git_read_only, "red"),
+        symbol(data.shallow, symbols.git_shallow, "cyan"),
+        symbol(data.hooks, symbols.git_hooks, "yellow"),
+        symbol(data.from_env, symbols.git_from_env, "cyan"),
+        head_info
+            .as_ref()
+            .and_then(|h| h.signed)
+            .and_then(|v| match v {
+                true => symbol(true, symbols.git_signed, "green"),
+                false => symbol_bold(true, symbols.git_unsigned, "red"),
+            }),
+        data.lfs.as_ref().and_then(|v| match v.unpulled {
+            0 => symbol(true, symbols.git_lfs, "cyan"),
+            _ => symbol_bold(true, symbols.git_lfs_unpulled, "yellow"),
+        }),
         symbol_bold(has_staged, symbols.git_has_staged, "green"),
         symbol_bold(has_unstaged, symbols.git_has_unstaged, "red"),
         symbol_bold(has_typechange, symbols.git_has_typechange, "magenta"),
@@ -157,7 +419,7 @@ fn format_ilsore_git_symbols(
         symbol(has_untracked, symbols.git_has_untracked, "magenta"),
     ];
 
-    let result_data = vec![
+    let result_data = [
         detached_branch_symbols.i_join(),
         file_status_symbols.i_join(),
     ];
@@ -193,6 +455,51 @@ trait Joiner {
 
 impl Joiner for Vec<Option<String>> {
     fn i_join(&self) -> String {
-        return self.iter().filter_map(|p| p.as_deref()).collect::<String>();
+        self.iter().filter_map(|p| p.as_deref()).collect::<String>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::format_ilsore_color;
+    use super::on_background;
+    use super::RESET_COLOR;
+    use crate::demo;
+    use crate::structs;
+
+    #[test]
+    fn on_background_test() {
+        assert_eq!(on_background("", "1"), "");
+        assert_eq!(
+            on_background(&format!("[%{{%F{{226}}%}}12:00{RESET_COLOR}]"), "1"),
+            format!("%{{%K{{1}}%}}[%{{%F{{226}}%}}12:00{RESET_COLOR}%{{%K{{1}}%}}]{RESET_COLOR}")
+        );
+        assert_eq!(
+            on_background(&format!("(%{{%F{{2}}%}}x{RESET_COLOR}){RESET_COLOR}"), "1"),
+            format!("%{{%K{{1}}%}}(%{{%F{{2}}%}}x{RESET_COLOR}%{{%K{{1}}%}}){RESET_COLOR}")
+        );
+    }
+
+    #[test]
+    fn background_test() {
+        let mut colors = enum_map::EnumMap::default();
+        colors[structs::PromptSegment::DateTime] = Some("24");
+        let layout = structs::ThemeLayout {
+            order: None,
+            colors,
+        };
+        let data = demo::theme_data(structs::GitOutputOptions::default(), Some(layout));
+        let symbols = structs::ThemeSymbols::utf8();
+        let output = format_ilsore_color(&data, &symbols);
+        assert!(output.starts_with("%{%K{24}%}[%{%F{165}%}"), "{output:?}");
+        assert_eq!(output.matches("%{%K{").count(), 3, "{output:?}");
+
+        let output = format_ilsore_color(
+            &demo::theme_data(structs::GitOutputOptions::default(), None),
+            &symbols,
+        );
+        assert!(!output.contains("%{%K{"), "{output:?}");
     }
 }
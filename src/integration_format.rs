@@ -0,0 +1,275 @@
+#[cfg(feature = "json")]
+use serde_json::json;
+
+#[cfg(feature = "json")]
+use crate::date_time;
+#[cfg(feature = "json")]
+use crate::ilsore_format;
+use crate::structs;
+
+/// Formats a compact line for editor statuslines.
+///
+/// Fields are always printed in the same order: branch, ahead, behind and file flags,
+/// missing values are replaced with `-`, so the line keeps its shape. Empty outside of repository.
+pub(crate) fn format_statusline(
+    data: &structs::ThemeData,
+    symbols: &structs::ThemeSymbols,
+) -> String {
+    let git = match &data.git {
+        Some(v) => v,
+        None => return String::new(),
+    };
+
+    let branch = git
+        .head_info
+        .as_ref()
+        .and_then(|h| h.reference_short.as_deref().or(h.oid_short.as_deref()))
+        .unwrap_or("-");
+
+    let (ahead, behind) = git
+        .branch_ahead_behind
+        .as_ref()
+        .map_or(("-".to_string(), "-".to_string()), |v| {
+            (v.ahead.to_string(), v.behind.to_string())
+        });
+
+    let flags: String = match &git.file_status {
+        Some(f) => [
+            (f.staged, symbols.git_has_staged),
+            (f.unstaged, symbols.git_has_unstaged),
+            (f.typechange, symbols.git_has_typechange),
+            (f.conflict, symbols.git_has_conflict),
+            (f.untracked, symbols.git_has_untracked),
+        ]
+        .iter()
+        .map(|(present, symbol)| if *present { symbol } else { "-" })
+        .collect(),
+        None => "-".repeat(5),
+    };
+
+    // `%` starts an item in vim statusline
+    format!(
+        "{} {}{} {}{} {}",
+        branch.replace('%', "%%"),
+        symbols.git_is_ahead,
+        ahead,
+        symbols.git_is_behind,
+        behind,
+        flags,
+    )
+}
+
+/// Formats JSON object for waybar `custom` module, `class` is derived from git state
+#[cfg(feature = "json")]
+pub(crate) fn format_waybar(data: &structs::ThemeData, symbols: &structs::ThemeSymbols) -> String {
+    let value = match &data.git {
+        Some(git) => {
+            let branch = git
+                .head_info
+                .as_ref()
+                .and_then(|h| h.reference_short.as_deref().or(h.oid_short.as_deref()))
+                .unwrap_or_default();
+            let ahead_behind = git.branch_ahead_behind.as_ref();
+            let text = format!(
+                "{} {}{}{}{}",
+                symbols.git_branch,
+                branch,
+                symbol(
+                    ahead_behind.is_some_and(|b| b.ahead > 0),
+                    symbols.git_is_ahead
+                ),
+                symbol(
+                    ahead_behind.is_some_and(|b| b.behind > 0),
+                    symbols.git_is_behind
+                ),
+                symbol(is_dirty(git), symbols.git_has_unstaged),
+            );
+            json!({
+                "text": text.trim_start(),
+                "tooltip": waybar_tooltip(git, branch),
+                "class": waybar_classes(git),
+            })
+        }
+        None => json!({
+            "text": "",
+            "class": ["no-repo"],
+        }),
+    };
+    format!("{value}\n")
+}
+
+#[cfg(feature = "json")]
+fn waybar_tooltip(git: &structs::GitOutputOptions, branch: &str) -> String {
+    let mut lines = vec![format!("branch: {branch}")];
+    if let Some(b) = &git.branch_ahead_behind {
+        lines.push(format!("ahead: {}, behind: {}", b.ahead, b.behind));
+    }
+    if let Some(d) = &git.diff_stat {
+        lines.push(format!("lines: +{} -{}", d.insertions, d.deletions));
+    }
+    if let Some(number) = git.pull_request {
+        lines.push(format!("pull request: #{number}"));
+    }
+    if let Some(status) = git.ci_status {
+        lines.push(format!("ci: {status}"));
+    }
+    if let Some(count) = git.today_commits {
+        lines.push(format!("commits today: {count}"));
+    }
+    if let Some(days) = git.base_age_days {
+        lines.push(format!("merge base: {days} days old"));
+    }
+    if let Some(secs) = git.dirty_age_secs {
+        lines.push(format!(
+            "dirty for: {}",
+            date_time::format_duration(secs.max(1) * 1000, 1)
+        ));
+    }
+    if let Some(distance) = &git.tag_distance {
+        lines.push(format!(
+            "tag: {}",
+            ilsore_format::format_tag_distance(distance)
+        ));
+    }
+    if let Some(default) = &git.default_branch {
+        lines.push(format!("default branch: {default}"));
+    }
+    if let Some(identity) = &git.identity {
+        lines.push(format!(
+            "identity: {}",
+            ilsore_format::format_identity(identity)
+        ));
+    }
+    if let Some(f) = &git.file_status {
+        let names: Vec<&str> = [
+            (f.staged, "staged"),
+            (f.unstaged, "unstaged"),
+            (f.typechange, "typechange"),
+            (f.conflict, "conflict"),
+            (f.untracked, "untracked"),
+        ]
+        .iter()
+        .filter_map(|(present, name)| present.then_some(*name))
+        .collect();
+        lines.push(match names.is_empty() {
+            true => "clean".to_string(),
+            false => names.join(", "),
+        });
+    }
+    if let Some(state) = &git.state {
+        lines.push(format!("in progress: {state}"));
+    }
+    lines.join("\n")
+}
+
+/// CSS classes for the bar module, the first one is always `clean` or `dirty`
+#[cfg(feature = "json")]
+fn waybar_classes(git: &structs::GitOutputOptions) -> Vec<String> {
+    let file_status = git.file_status.as_ref();
+    let ahead_behind = git.branch_ahead_behind.as_ref();
+    let mut classes: Vec<String> = [
+        (is_dirty(git), "dirty"),
+        (!is_dirty(git), "clean"),
+        (file_status.is_some_and(|f| f.conflict), "conflict"),
+        (file_status.is_some_and(|f| f.untracked), "untracked"),
+        (ahead_behind.is_some_and(|b| b.ahead > 0), "ahead"),
+        (ahead_behind.is_some_and(|b| b.behind > 0), "behind"),
+        (ahead_behind.is_none(), "no-upstream"),
+        (
+            git.head_info.as_ref().is_some_and(|h| h.detached),
+            "detached",
+        ),
+        (git.busy, "busy"),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, name)| name.to_string())
+    .collect();
+    if let Some(state) = &git.state {
+        // class names can't contain spaces, bisect label does
+        classes.push(
+            state
+                .to_string()
+                .split(' ')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        );
+    }
+    classes
+}
+
+#[cfg(feature = "json")]
+#[inline]
+fn is_dirty(git: &structs::GitOutputOptions) -> bool {
+    git.file_status
+        .as_ref()
+        .is_some_and(|f| f.conflict || f.untracked || f.typechange || f.unstaged || f.staged)
+}
+
+#[inline]
+fn symbol(present: bool, symbol: &'static str) -> &'static str {
+    match present {
+        true => symbol,
+        false => "",
+    }
+}
+
+/// Formats a single line for starship `custom` module without trailing new line.
+///
+/// Control characters are removed from values, so output can't break the prompt.
+pub(crate) fn format_starship(
+    data: &structs::ThemeData,
+    symbols: &structs::ThemeSymbols,
+) -> String {
+    let git = match &data.git {
+        Some(v) => v,
+        None => return String::new(),
+    };
+
+    let branch = git
+        .head_info
+        .as_ref()
+        .and_then(|h| h.reference_short.as_deref().or(h.oid_short.as_deref()))
+        .unwrap_or_default();
+    let ahead_behind = git.branch_ahead_behind.as_ref();
+    let file_status = git.file_status.as_ref();
+
+    let flags = [
+        symbol(
+            ahead_behind.is_some_and(|b| b.ahead > 0),
+            symbols.git_is_ahead,
+        ),
+        symbol(
+            ahead_behind.is_some_and(|b| b.behind > 0),
+            symbols.git_is_behind,
+        ),
+        symbol(
+            file_status.is_some_and(|f| f.conflict),
+            symbols.git_has_conflict,
+        ),
+        symbol(
+            file_status.is_some_and(|f| f.untracked),
+            symbols.git_has_untracked,
+        ),
+        symbol(
+            file_status.is_some_and(|f| f.typechange),
+            symbols.git_has_typechange,
+        ),
+        symbol(
+            file_status.is_some_and(|f| f.unstaged),
+            symbols.git_has_unstaged,
+        ),
+        symbol(
+            file_status.is_some_and(|f| f.staged),
+            symbols.git_has_staged,
+        ),
+    ]
+    .concat();
+
+    let line = match flags.is_empty() {
+        true => branch.to_string(),
+        false => format!("{} {}", branch, flags),
+    };
+    line.chars().filter(|c| !c.is_control()).collect()
+}
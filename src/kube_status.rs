@@ -0,0 +1,62 @@
+//! Kubernetes segment with the current context of kubectl, e.g. `kube prod`.
+//!
+//! The context is read from the first file of `KUBECONFIG` or `~/.kube/config`. The file
+//! isn't parsed as YAML, only the top level `current-context` line is looked up. Home
+//! folders on network mounts make reading slow, so it runs under the time budget of
+//! the segment.
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::context;
+use crate::error::MapLog;
+use crate::structs;
+
+/// Segment of the current context, `None` without a config or a context
+pub(crate) fn segment(ctx: &context::Context) -> Option<structs::CustomSegment> {
+    let content = std::fs::read_to_string(config_path()?).ok_or_log(ctx)?;
+    Some(structs::CustomSegment {
+        name: "kube".to_string(),
+        text: format!("kube {}", current_context(&content)?),
+        color: None,
+    })
+}
+
+/// First file of `KUBECONFIG`, kubectl takes the current context from it too
+fn config_path() -> Option<PathBuf> {
+    match env::var_os("KUBECONFIG").filter(|v| !v.is_empty()) {
+        Some(paths) => env::split_paths(&paths).find(|p| !p.as_os_str().is_empty()),
+        None => Some(PathBuf::from(env::var_os("HOME")?).join(".kube/config")),
+    }
+}
+
+/// Value of the top level `current-context` key, quotes are removed
+fn current_context(content: &str) -> Option<&str> {
+    let value = content
+        .lines()
+        .find_map(|line| line.strip_prefix("current-context:"))?
+        .trim();
+    let value = value.trim_matches(|c| c == '"' || c == '\'');
+    match value.is_empty() {
+        true => None,
+        false => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::current_context;
+
+    #[rstest]
+    #[case("apiVersion: v1\ncurrent-context: prod\nkind: Config\n", Some("prod"))]
+    #[case("current-context: \"kind-dev\"\n", Some("kind-dev"))]
+    #[case("current-context: ''\n", None)]
+    #[case("contexts:\n- name: a\n  current-context: nested\n", None)]
+    #[case("", None)]
+    fn current_context_test(#[case] content: &str, #[case] expected: Option<&str>) {
+        assert_eq!(current_context(content), expected);
+    }
+}
@@ -0,0 +1,242 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::error::Result;
+use crate::git_utils;
+use crate::structs;
+use crate::util;
+
+/// First line of every Git LFS pointer file
+static POINTER_HEADER: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+
+/// Pointer files are guaranteed to be smaller than this size
+const POINTER_MAX_SIZE: u64 = 1024;
+
+/// Line of attribute files setting or unsetting the `filter` attribute
+#[derive(Debug, PartialEq)]
+struct FilterRule {
+    /// Folder of the `.gitattributes` file, patterns match paths below it
+    scope: PathBuf,
+    pattern: String,
+    lfs: bool,
+}
+
+/// Inspects `filter` attribute of indexed files and counts LFS pointers left in the worktree.
+///
+/// Attribute files are read once and their patterns are matched against every path,
+/// asking libgit2 for attributes of each indexed file is too slow in large repositories.
+///
+/// Returns `None` if no indexed file is tracked by LFS.
+pub(crate) fn lfs_status(repo: &git2::Repository) -> Result<Option<structs::GitLfsStatus>> {
+    let workdir = match repo.workdir() {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let index = repo.index()?;
+    let rules = filter_rules(repo, &index)?;
+    if !rules.iter().any(|rule| rule.lfs) {
+        return Ok(None);
+    }
+
+    let mut enabled = false;
+    let mut unpulled = 0;
+    for entry in index.iter() {
+        let path = match std::str::from_utf8(&entry.path) {
+            Ok(v) => Path::new(v),
+            Err(_) => continue,
+        };
+        if !is_lfs(&rules, path) {
+            continue;
+        }
+        enabled = true;
+        if is_pointer_file(&workdir.join(path)) {
+            unpulled += 1;
+        }
+    }
+
+    Ok(enabled.then_some(structs::GitLfsStatus { unpulled }))
+}
+
+/// Rules in the order of precedence, later ones override earlier ones: global attributes
+/// file, indexed `.gitattributes` from the root down, and `info/attributes`
+fn filter_rules(repo: &git2::Repository, index: &git2::Index) -> Result<Vec<FilterRule>> {
+    let mut rules = Vec::new();
+    let global = repo
+        .config()
+        .and_then(|mut c| c.snapshot())
+        .and_then(|c| c.get_path("core.attributesFile"));
+    if let Ok(path) = global {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        rules.extend(parse_rules(Path::new(""), &content));
+    }
+
+    let mut files: Vec<(PathBuf, git2::Oid)> = index
+        .iter()
+        .filter_map(|entry| {
+            let path = Path::new(std::str::from_utf8(&entry.path).ok()?);
+            (path.file_name()? == ".gitattributes").then(|| {
+                (
+                    path.parent().unwrap_or(Path::new("")).to_path_buf(),
+                    entry.id,
+                )
+            })
+        })
+        .collect();
+    files.sort_by_key(|(scope, _)| scope.components().count());
+    for (scope, id) in files {
+        let blob = repo.find_blob(id)?;
+        rules.extend(parse_rules(
+            &scope,
+            &String::from_utf8_lossy(blob.content()),
+        ));
+    }
+
+    let info = git_utils::common_dir(repo).join("info/attributes");
+    let content = fs::read_to_string(info).unwrap_or_default();
+    rules.extend(parse_rules(Path::new(""), &content));
+    Ok(rules)
+}
+
+/// Lines of an attribute file changing `filter`, macros and quoted patterns are skipped
+fn parse_rules(scope: &Path, content: &str) -> Vec<FilterRule> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?;
+            // attributes are given to files only, folder patterns don't set them
+            if pattern.starts_with(['#', '"'])
+                || pattern.starts_with("[attr]")
+                || pattern.ends_with('/')
+            {
+                return None;
+            }
+            // the last one wins when the line sets the attribute more than once
+            let lfs = parts.rev().find_map(|v| match v {
+                "filter=lfs" => Some(true),
+                "-filter" | "!filter" => Some(false),
+                v => v.starts_with("filter=").then_some(false),
+            })?;
+            Some(FilterRule {
+                scope: scope.to_path_buf(),
+                pattern: pattern.to_string(),
+                lfs,
+            })
+        })
+        .collect()
+}
+
+/// Last matching rule decides, patterns without `/` match the file name at any depth
+fn is_lfs(rules: &[FilterRule], path: &Path) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| {
+            let Ok(relative) = path.strip_prefix(&rule.scope) else {
+                return false;
+            };
+            match rule.pattern.contains('/') {
+                true => util::path_match(&rule.pattern, relative),
+                false => path
+                    .file_name()
+                    .is_some_and(|name| util::path_match(&rule.pattern, Path::new(name))),
+            }
+        })
+        .is_some_and(|rule| rule.lfs)
+}
+
+/// Checks if worktree file is still an LFS pointer instead of the real content
+fn is_pointer_file(path: &Path) -> bool {
+    let file = match fs::File::open(path) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    if file
+        .metadata()
+        .map_or(true, |m| m.len() >= POINTER_MAX_SIZE)
+    {
+        return false;
+    }
+    let mut content = Vec::with_capacity(POINTER_HEADER.len());
+    file.take(POINTER_HEADER.len() as u64)
+        .read_to_end(&mut content)
+        .is_ok_and(|_| is_pointer(&content))
+}
+
+#[inline]
+fn is_pointer(content: &[u8]) -> bool {
+    content.starts_with(POINTER_HEADER)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::is_lfs;
+    use super::is_pointer;
+    use super::parse_rules;
+    use super::FilterRule;
+
+    #[rstest]
+    #[case(
+        b"version https://git-lfs.github.com/spec/v1\noid sha256:abc\nsize 12\n",
+        true
+    )]
+    #[case(b"version https://git-lfs.github.com/spec/v1", true)]
+    #[case(b"version https://git-lfs.github.com/spec", false)]
+    #[case(b"\x89PNG\r\n", false)]
+    #[case(b"", false)]
+    fn test_is_pointer(#[case] content: &[u8], #[case] expected: bool) {
+        assert_eq!(is_pointer(content), expected);
+    }
+
+    #[test]
+    fn parse_rules_test() {
+        let content = concat!(
+            "# comment\n",
+            "*.psd filter=lfs diff=lfs merge=lfs -text\n",
+            "*.txt text\n",
+            "assets/ filter=lfs\n",
+            "[attr]big filter=lfs\n",
+            "small.psd -filter\n",
+            "*.bin filter=lfs filter=crypt\n",
+        );
+        let rule = |pattern: &str, lfs| FilterRule {
+            scope: "art".into(),
+            pattern: pattern.to_string(),
+            lfs,
+        };
+        assert_eq!(
+            parse_rules(Path::new("art"), content),
+            [
+                rule("*.psd", true),
+                rule("small.psd", false),
+                rule("*.bin", false)
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("cover.psd", true)]
+    #[case("art/layers/cover.psd", true)]
+    #[case("art/small.psd", false)]
+    #[case("small.psd", true)]
+    #[case("models/mesh.obj", true)]
+    #[case("art/models/mesh.obj", false)]
+    #[case("src/models/mesh.obj", false)]
+    #[case("cover.PSD", false)]
+    #[case("readme.md", false)]
+    fn is_lfs_test(#[case] path: &str, #[case] expected: bool) {
+        let mut rules = parse_rules(
+            Path::new(""),
+            "*.psd filter=lfs\n/models/*.obj filter=lfs\n",
+        );
+        rules.extend(parse_rules(Path::new("art"), "small.psd -filter\n"));
+        assert_eq!(is_lfs(&rules, Path::new(path)), expected);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::schema;
+use crate::structs;
+
+/// Formats theme data as `key value` lines, skipping unknown values
+pub(crate) fn format_porcelain(
+    data: &structs::ThemeData,
+    _symbols: &structs::ThemeSymbols,
+) -> String {
+    let mut lines: Vec<(&str, Option<String>)> = vec![
+        ("schema_version", Some(schema::SCHEMA_VERSION.to_string())),
+        ("last_exit_status", Some(data.last_exit_status.to_string())),
+        ("jobs", Some(data.jobs.to_string())),
+        ("cmd_duration", data.cmd_duration.clone()),
+        ("shell_level", Some(data.shell_level.to_string())),
+        ("vi_command", Some(data.vi_command.to_string())),
+        ("date", Some(data.datetime.date.to_string())),
+        ("time", Some(data.datetime.time.to_string())),
+        ("hostname", data.hostname.clone()),
+        ("username", data.username.clone()),
+        ("python", data.python.last().map(|v| v.name.clone())),
+        (
+            "python_manager",
+            data.python.last().map(|v| v.manager.to_string()),
+        ),
+        (
+            "slow",
+            Some(data.slow.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.join(",")),
+        ),
+    ];
+
+    // stacked environments from the outermost one, `python` is the one in effect
+    for v in &data.python {
+        lines.push(("python_env", Some(v.name.clone())));
+    }
+    for v in &data.custom {
+        lines.push(("custom", Some(format!("{} {}", v.name, v.text))));
+    }
+
+    // exact numbers of files follow flags as `git.count.<category>` lines
+    let mut counts = Vec::new();
+    if let Some(git) = &data.git {
+        if let Some(h) = &git.head_info {
+            lines.extend([
+                ("git.reference_short", h.reference_short.clone()),
+                ("git.reference_name", h.reference_name.clone()),
+                (
+                    "git.reference_kind",
+                    h.reference_kind.map(|v| v.to_string()),
+                ),
+                ("git.oid", h.oid.clone()),
+                ("git.oid_short", h.oid_short.clone()),
+                ("git.detached", Some(h.detached.to_string())),
+                ("git.detached_from", h.detached_from.clone()),
+                ("git.signed", h.signed.map(|v| v.to_string())),
+                ("git.branch_url", h.branch_url.clone()),
+                ("git.ticket", h.ticket.as_ref().map(|t| t.id.clone())),
+                ("git.ticket_name", h.ticket.as_ref().map(|t| t.name.clone())),
+            ]);
+        }
+        if let Some(b) = &git.branch_ahead_behind {
+            lines.extend([
+                ("git.ahead", Some(b.ahead.to_string())),
+                ("git.behind", Some(b.behind.to_string())),
+            ]);
+        }
+        if let Some(f) = &git.file_status {
+            lines.extend([
+                ("git.conflict", Some(f.conflict.to_string())),
+                ("git.untracked", Some(f.untracked.to_string())),
+                ("git.typechange", Some(f.typechange.to_string())),
+                ("git.unstaged", Some(f.unstaged.to_string())),
+                ("git.staged", Some(f.staged.to_string())),
+            ]);
+            counts.extend(f.counts.entries());
+        }
+        lines.push(("git.repo_name", git.repo_name.clone()));
+        lines.push(("git.read_only", Some(git.read_only.to_string())));
+        lines.push(("git.busy", Some(git.busy.to_string())));
+        lines.push(("git.shallow", Some(git.shallow.to_string())));
+        lines.push(("git.hooks", Some(git.hooks.to_string())));
+        lines.push(("git.from_env", Some(git.from_env.to_string())));
+        lines.push(("git.slow", Some(git.slow.to_string())));
+        lines.push(("git.reduced", Some(git.reduced.to_string())));
+        lines.push(("git.inside_git_dir", Some(git.inside_git_dir.to_string())));
+        lines.push(("git.untrusted", Some(git.untrusted.to_string())));
+        lines.push(("git.damaged", Some(git.damaged.to_string())));
+        lines.push(("git.nested", Some(git.nested.to_string())));
+        lines.push(("git.outer_branch", git.outer_branch.clone()));
+        for r in &git.remotes {
+            lines.push((
+                "git.remote",
+                Some(format!("{} {} {}", r.remote, r.ahead, r.behind)),
+            ));
+        }
+        lines.push(("git.pull_request", git.pull_request.map(|v| v.to_string())));
+        lines.push(("git.ci_status", git.ci_status.map(|v| v.to_string())));
+        lines.push((
+            "git.today_commits",
+            git.today_commits.map(|v| v.to_string()),
+        ));
+        lines.push((
+            "git.base_age_days",
+            git.base_age_days.map(|v| v.to_string()),
+        ));
+        lines.push((
+            "git.dirty_age_secs",
+            git.dirty_age_secs.map(|v| v.to_string()),
+        ));
+        if let Some(distance) = &git.tag_distance {
+            lines.extend([
+                ("git.tag", Some(distance.tag.clone())),
+                ("git.tag_ahead", Some(distance.ahead.to_string())),
+            ]);
+        }
+        lines.push(("git.default_branch", git.default_branch.clone()));
+        if let Some(identity) = &git.identity {
+            lines.extend([
+                ("git.user_name", identity.name.clone()),
+                ("git.user_email", identity.email.clone()),
+            ]);
+        }
+        lines.push((
+            "git.identity_mismatch",
+            Some(git.identity_mismatch.to_string()),
+        ));
+        lines.push(("git.protected", Some(git.protected.to_string())));
+        lines.push(("git.large_staged", Some(git.large_staged.to_string())));
+        lines.push(("git.secrets_staged", Some(git.secrets_staged.to_string())));
+        if let Some(d) = &git.diff_stat {
+            lines.extend([
+                ("git.insertions", Some(d.insertions.to_string())),
+                ("git.deletions", Some(d.deletions.to_string())),
+            ]);
+        }
+        lines.push((
+            "git.lfs_unpulled",
+            git.lfs.as_ref().map(|v| v.unpulled.to_string()),
+        ));
+        lines.push(("git.state", git.state.as_ref().map(|v| v.to_string())));
+        lines.push((
+            "git.relative_path",
+            git.relative_path
+                .as_ref()
+                .map(|v| v.to_string_lossy().into_owned()),
+        ));
+    }
+
+    lines
+        .into_iter()
+        .filter_map(|(k, v)| v.map(|v| format!("{k} {}\n", v.replace('\n', " "))))
+        .chain(
+            counts
+                .into_iter()
+                .map(|(name, count)| format!("git.count.{name} {count}\n")),
+        )
+        .collect()
+}
+
+/// Formats theme data as a single JSON object
+#[cfg(feature = "json")]
+pub(crate) fn format_json(data: &structs::ThemeData, _symbols: &structs::ThemeSymbols) -> String {
+    let value = serde_json::to_string(&schema::Prompt::from(data)).unwrap_or_default();
+    format!("{value}\n")
+}
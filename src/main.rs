@@ -1,78 +1,690 @@
-use clap::Parser;
 use error::MapLog;
-use std::borrow::Cow;
-use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
+mod accessible_format;
 mod args;
+// helpers of git collection and its errors stay for themes, demo and recordings
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+mod cache;
+mod collect;
+mod config;
+mod container_status;
+mod context;
+#[cfg(feature = "daemon")]
+mod daemon;
 mod date_time;
+mod demo;
+mod direnv_status;
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
 mod error;
+mod explain;
+#[cfg(feature = "git")]
+mod forge;
+#[cfg(test)]
+mod format_golden;
+#[cfg(test)]
+mod format_properties;
+#[cfg(feature = "git")]
 mod git_utils;
 mod ilsore_format;
+#[cfg(feature = "color")]
 mod ilsore_format_color;
+mod integration_format;
+mod kube_status;
+#[cfg(feature = "git")]
+mod lfs;
+mod machine_format;
+mod minimal_format;
+mod nix_status;
+mod node_status;
+mod output;
+mod package_metadata;
+#[cfg(feature = "color")]
+mod powerline_format;
+#[cfg(feature = "python")]
 mod python_status;
+#[cfg(feature = "json")]
+mod replay;
+#[cfg(feature = "git")]
+mod repo_scale;
+mod schema;
+#[cfg(feature = "scripting")]
+mod scripting;
+#[cfg(feature = "self-update")]
+mod self_update;
+mod shell_init;
+mod stats;
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
 mod structs;
+mod symbols_preview;
+#[cfg(feature = "system")]
+mod system_status;
+mod terminal;
+mod timings;
 mod user_host;
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
 mod util;
 
 fn main() -> error::Result<()> {
+    let started = Instant::now();
     args::init_argument_parser();
-    let args = args::Args::parse();
+    let args = args::Args::parse_explicit();
 
-    error::setup_errors(args.error_output);
-    let theme_data = theme_data(&args);
-    let symbols = args.symbols();
+    let ctx = context::Context::new(args.error_output);
+    error::setup_panic_hook(&ctx);
+    // explanation includes timings of the run
+    timings::setup(args.timings || matches!(args.command, Some(args::Command::Explain)));
 
-    print!("{}", args.theme()(&theme_data, symbols));
+    match &args.command {
+        #[cfg(feature = "daemon")]
+        Some(args::Command::Daemon {
+            socket,
+            cache_ttl_ms,
+            idle_timeout_s,
+            action,
+        }) => {
+            let socket = socket.clone().unwrap_or_else(daemon::default_socket_path);
+            return match action {
+                None => daemon::serve(
+                    &socket,
+                    Duration::from_millis(*cache_ttl_ms),
+                    (*idle_timeout_s > 0).then(|| Duration::from_secs(*idle_timeout_s)),
+                    &ctx,
+                ),
+                Some(args::DaemonAction::Status) => daemon::status_command(&socket),
+                Some(args::DaemonAction::Stop) => daemon::stop_command(&socket),
+            };
+        }
+        Some(args::Command::Init { shell, two_phase }) => {
+            let binary = std::env::current_exe()?;
+            print!("{}", shell_init::snippet(*shell, *two_phase, &binary)?);
+            return Ok(());
+        }
+        Some(args::Command::Install {
+            shell,
+            rc,
+            two_phase,
+        }) => {
+            let binary = std::env::current_exe()?.canonicalize()?;
+            let rc = rc
+                .clone()
+                .or_else(|| shell_init::rc_path(*shell))
+                .ok_or("home folder is unknown, set rc file with --rc")?;
+            shell_init::install(*shell, *two_phase, &binary, &rc)?;
+            println!(
+                "Installed into {}, restart the shell to apply",
+                rc.display()
+            );
+            return Ok(());
+        }
+        Some(args::Command::PackageMetadata { dir, binary }) => {
+            for path in package_metadata::write(dir, binary)? {
+                println!("{}", path.display());
+            }
+            return Ok(());
+        }
+        // symbols and layout of the user theme are shown, so configuration is loaded first
+        Some(args::Command::Symbols { .. } | args::Command::Demo | args::Command::Explain) => {}
+        #[cfg(feature = "json")]
+        Some(args::Command::Record { .. } | args::Command::Render { .. }) => {}
+        Some(args::Command::Stats) => {
+            let path = stats::log_path().ok_or("cache directory is unknown")?;
+            print!("{}", stats::report(&path)?);
+            return Ok(());
+        }
+        #[cfg(feature = "self-update")]
+        Some(args::Command::SelfUpdate { check }) => {
+            return self_update::self_update(*check);
+        }
+        #[cfg(all(feature = "json", feature = "git"))]
+        Some(args::Command::Prcache {
+            action: args::CacheAction::Refresh,
+        }) => {
+            let count = forge::refresh_pull_requests(&std::env::current_dir()?)?;
+            println!("Cached {count} open pull requests");
+            return Ok(());
+        }
+        #[cfg(all(feature = "json", feature = "git"))]
+        Some(args::Command::Cicache {
+            action: args::CacheAction::Refresh,
+        }) => {
+            match forge::refresh_ci_status(&std::env::current_dir()?)? {
+                (oid, Some(status)) => println!("Cached CI status of {oid}: {status}"),
+                (oid, None) => println!("No CI runs of {oid}"),
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
+    // broken configuration shouldn't break the prompt, built-in theme is used instead
+    let config = config::load(args.config.as_deref())
+        .map_err(|err| eprintln!("{}: {err}", env!("CARGO_BIN_NAME")))
+        .unwrap_or_default();
+    let profile = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| config.profile(&cwd));
+    let mut args = match profile {
+        Some(p) if !p.args.is_empty() => args::Args::parse_with_profile(&p.args),
+        _ => args,
+    };
+    args.file_options = config.options.clone();
+
+    // segments left behind by timeout keep borrowing arguments until exit
+    let args: &'static args::Args = Box::leak(Box::new(args));
+    let user_theme = config
+        .user_theme(
+            args.user_theme
+                .as_deref()
+                .or(profile.and_then(|p| p.theme.as_deref())),
+        )
+        .map_err(|err| eprintln!("{}: {err}", env!("CARGO_BIN_NAME")))
+        .unwrap_or_default();
+
+    let symbols = args.symbols(user_theme);
+    if let Some(args::Command::Symbols { theme }) = &args.command {
+        let symbols = theme.map_or(symbols, args::ThemeSymbolsNames::symbols);
+        print!("{}", symbols_preview::table(&symbols));
+        return Ok(());
+    }
+    let theme = args.theme(user_theme);
+    if let Some(args::Command::Demo) = &args.command {
+        let layout = user_theme.and_then(|t| t.layout.as_ref());
+        print!("{}", demo::render(theme, &symbols, layout));
+        return Ok(());
+    }
+    if args.explain_options {
+        let git = SYSTEM_PROVIDERS.git.ok_or(NO_GIT_SUPPORT)?;
+        let trace = git.option_trace(&git_info_options(args, &ctx))?;
+        print!("{}", explain::format_options(&trace));
+        return Ok(());
+    }
+    #[cfg(feature = "json")]
+    if let Some(args::Command::Render { from }) = &args.command {
+        let theme_data = replay::theme_data(&std::fs::read_to_string(from)?)?;
+        print!("{}", theme(&theme_data, &symbols));
+        return Ok(());
+    }
+    #[cfg(feature = "json")]
+    if let Some(args::Command::Record { out }) = &args.command {
+        let theme_data = prompt_data(
+            args,
+            &ctx,
+            &config,
+            user_theme,
+            args.fast,
+            &SYSTEM_PROVIDERS,
+        );
+        let git_options = git_info_options(args, &ctx);
+        let options = SYSTEM_PROVIDERS
+            .git
+            .and_then(|git| git.option_trace(&git_options).ok());
+        let raw = SYSTEM_PROVIDERS
+            .git
+            .filter(|_| !args.disable_git)
+            .and_then(|git| git.raw_data(&git_options).ok());
+        std::fs::write(
+            out,
+            replay::record(&theme_data, options.as_ref(), raw.as_ref())?,
+        )?;
+        return Ok(());
+    }
+    if let Some(args::Command::Explain) = &args.command {
+        let theme_data = prompt_data(
+            args,
+            &ctx,
+            &config,
+            user_theme,
+            args.fast,
+            &SYSTEM_PROVIDERS,
+        );
+        let prompt = timings::measure("render", || theme(&theme_data, &symbols));
+        let options = SYSTEM_PROVIDERS
+            .git
+            .and_then(|git| git.option_trace(&git_info_options(args, &ctx)).ok())
+            .map(|trace| explain::format_options(&trace));
+        print!(
+            "{}",
+            explain::report(
+                &prompt,
+                &theme_data,
+                &symbols,
+                options.as_deref(),
+                &timings::report()
+            )
+        );
+        return Ok(());
+    }
+    let output = args.output();
+    // Ctrl-C of a slow prompt prints what is collected so far instead of nothing, the rest
+    // of commands keep default handling
+    collect::install_signal_handlers();
+
+    // the first phase is a cheap prompt shown while the full one is collected
+    if args.two_phase && !args.fast {
+        let theme_data = prompt_data(args, &ctx, &config, user_theme, true, &SYSTEM_PROVIDERS);
+        output.write(&format!(
+            "{}{}{}",
+            hash_line(args, &theme_data),
+            theme(&theme_data, &symbols),
+            output::PHASE_END
+        ))?;
+    }
+
+    let theme_data = prompt_data(
+        args,
+        &ctx,
+        &config,
+        user_theme,
+        args.fast,
+        &SYSTEM_PROVIDERS,
+    );
+    let prompt = timings::measure("render", || theme(&theme_data, &symbols));
+    let prompt = format!("{}{prompt}", hash_line(args, &theme_data));
+    match args.two_phase {
+        true => output.write(&format!("{prompt}{}", output::PHASE_END))?,
+        false => output.write(&prompt)?,
+    }
+    if args.timings {
+        eprint!("{}", timings::report());
+    }
+    // recorded after the prompt is written, the shell still waits for it to exit
+    if args.stats {
+        stats::append(args, &git_info_options(args, &ctx), started.elapsed()).ok_or_log(&ctx);
+    }
 
     Ok(())
 }
 
-fn theme_data(args: &args::Args) -> structs::ThemeData {
-    let mut mut_hostname: Option<String> = None;
-    let mut git_info: Option<structs::GitOutputOptions> = None;
+/// Line with content hash preceding the prompt with `--print-hash`, empty otherwise
+fn hash_line(args: &args::Args, theme_data: &structs::ThemeData) -> String {
+    match args.print_hash {
+        true => format!("{}\n", output::content_hash(theme_data)),
+        false => String::new(),
+    }
+}
+
+/// Sources of time, user and host names and git information, fixed ones make output
+/// deterministic
+struct Providers {
+    clock: &'static dyn date_time::Clock,
+    host: &'static dyn user_host::HostInfoProvider,
+    /// Git segment is never shown without it
+    git: Option<&'static dyn structs::GitInfoProvider>,
+}
 
-    let fast_hostname = args
-        .static_hostname
-        .as_ref()
-        .map(Cow::from)
-        .or_else(|| std::env::var("HOST").map(Cow::from).ok_or_log()) // zsh and tcsh
-        .or_else(|| std::env::var("HOSTNAME").map(Cow::from).ok_or_log()) // bash
-        .or_else(|| std::env::var("COMPUTERNAME").map(Cow::from).ok_or_log()); // windows
+/// Providers backed by the operating system
+static SYSTEM_PROVIDERS: Providers = Providers {
+    clock: &date_time::SystemClock,
+    host: &user_host::SystemHostInfo,
+    #[cfg(feature = "git")]
+    git: Some(&git_utils::RepoInfo),
+    #[cfg(not(feature = "git"))]
+    git: None,
+};
 
-    let git_info_options = structs::GetGitInfoOptions {
+const NO_GIT_SUPPORT: &str = "git support isn't built, build with `git` feature";
+
+/// Theme data with user theme layout and custom segments, exits if theme has nothing to show
+fn prompt_data(
+    args: &'static args::Args,
+    ctx: &context::Context,
+    config: &config::Config,
+    user_theme: Option<&config::UserTheme>,
+    quick: bool,
+    providers: &'static Providers,
+) -> structs::ThemeData {
+    let mut theme_data = match theme_data(args, ctx, user_theme, quick, providers) {
+        Some(v) => v,
+        None => std::process::exit(1),
+    };
+    theme_data.layout = user_theme.and_then(|t| t.layout.clone());
+
+    let env = config.env.iter().filter_map(config::EnvSegment::segment);
+    theme_data.custom.extend(env);
+    #[cfg(feature = "system")]
+    {
+        let system = system_status::segments(args.battery, args.load_average);
+        theme_data.custom.splice(0..0, system);
+    }
+    if args.direnv {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let direnv = direnv_status::segment(&cwd, quick, ctx);
+        theme_data.custom.splice(0..0, direnv);
+    }
+    if args.nix_shell {
+        theme_data.custom.splice(0..0, nix_status::segment());
+    }
+    if args.container {
+        theme_data.custom.splice(0..0, container_status::segment());
+    }
+    #[cfg(feature = "scripting")]
+    {
+        let scripted = scripting::run(&config.scripts, &theme_data, ctx);
+        theme_data.custom.extend(scripted);
+    }
+    #[cfg(not(feature = "scripting"))]
+    if !config.scripts.is_empty() && !quick {
+        eprintln!(
+            "{}: scripts are ignored, build with `scripting` feature to run them",
+            env!("CARGO_BIN_NAME")
+        );
+    }
+    theme_data
+}
+
+/// Git options of command line and configuration file, git configuration is applied later
+fn git_info_options(
+    args: &'static args::Args,
+    ctx: &context::Context,
+) -> structs::GetGitInfoOptions<'static> {
+    structs::GetGitInfoOptions {
         start_folder: &args.git_start_folder,
         reference_name: args.git_reference.as_deref().unwrap_or("HEAD"),
+        #[cfg(feature = "git")]
+        discovery_across_filesystem: git_utils::discovery_across_filesystem(
+            args.git_discovery_across_filesystem,
+        ),
+        #[cfg(not(feature = "git"))]
+        discovery_across_filesystem: args.git_discovery_across_filesystem,
+        logical_path: args.git_logical_path,
+        use_git_env: !args.git_ignore_env,
         include_submodules: args.git_include_submodules,
         include_untracked: !args.git_exclude_untracked,
+        detect_renames: args.git_detect_renames,
         refresh_status: args.git_refresh_status,
         include_ahead_behind: !args.git_exclude_ahead_behind,
+        ahead_behind_during_operation: args.git_ahead_behind_during_operation,
+        adaptive: args.git_adaptive,
+        cache_upstream: args.git_cache_upstream,
+        include_lfs: args.git_lfs,
+        include_signature: args.git_signature,
+        include_hooks: args.git_hooks,
+        expected_email: args.git_expected_email.as_deref(),
+        include_ticket: args.git_ticket,
+        ticket_pattern: &args.git_ticket_pattern,
+        include_protected: args.git_protected,
+        protected_branches: &args.git_protected_branches,
+        include_large_files: args.git_large_files,
+        large_file_mb: args.git_large_file_mb,
+        include_secrets: args.git_secrets,
+        secret_patterns: &args.git_secret_patterns,
+        secret_scan_ms: args.git_secret_scan_ms,
+        lock_retry_ms: args.git_lock_retry_ms,
         include_workdir_stats: !args.git_exclude_workdir_stats,
-    };
+        cwd_scope: args.git_cwd_scope,
+        include_relative_path: args.git_relative_path,
+        include_repo_name: args.git_repo_name,
+        include_hyperlink: args.git_hyperlink,
+        include_detached_containing: args.git_detached_containing,
+        containing_walk_limit: args.git_containing_walk_limit,
+        short_oid_len: args.short_oid_len,
+        short_oid_unique: args.short_oid_unique,
+        include_diff_stat: args.git_diff_stat,
+        diff_stat_max_files: args.git_diff_stat_max_files,
+        include_remotes: args.git_remotes,
+        include_nested: args.git_nested,
+        include_outer_branch: args.git_outer_branch,
+        include_pull_request: args.git_pull_request,
+        include_ci_status: args.git_ci_status,
+        include_today_commits: args.git_today_commits,
+        include_base_age: args.git_base_age,
+        base_age_min_days: args.git_base_age_min_days,
+        include_dirty_age: args.git_dirty_age,
+        include_tag_distance: args.git_tag_distance,
+        tag_walk_limit: args.git_tag_walk_limit,
+        include_identity: args.git_identity,
+        include_default_branch: args.git_default_branch,
+        repo_config: !args.no_repo_config,
+        option_layers: args.option_layers(),
+        timeout: args.timeout_ms.map(Duration::from_millis),
+        cancel: collect::CancelToken::interrupted(),
+        context: ctx.clone(),
+    }
+}
 
-    if fast_hostname.is_none() || !args.disable_git {
-        thread::scope(|s| {
-            s.spawn(|| {
-                if fast_hostname.is_none() {
-                    mut_hostname = user_host::hostname();
-                }
-            });
+/// Extra time for git segment to return partial information after its own budget
+const GIT_GRACE: Duration = Duration::from_millis(50);
 
-            s.spawn(|| {
-                if !args.disable_git {
-                    git_info = git_utils::process_current_dir(&git_info_options).ok_or_log();
-                }
-            });
-        });
+/// Collects data for the theme, `None` if theme has nothing to show.
+///
+/// Quick data skips slow hostname lookup and reads git branch from HEAD file only.
+fn theme_data(
+    args: &'static args::Args,
+    ctx: &context::Context,
+    user_theme: Option<&config::UserTheme>,
+    quick: bool,
+    providers: &'static Providers,
+) -> Option<structs::ThemeData> {
+    let timeout = args.timeout_ms.map(Duration::from_millis);
+
+    let fast_hostname = args
+        .static_hostname
+        .clone()
+        .or_else(|| providers.host.fast_hostname(ctx));
+
+    let git_info_options = git_info_options(args, ctx);
+    let cancel = git_info_options.cancel;
+
+    let inside_repo = || {
+        providers
+            .git
+            .is_some_and(|git| git.is_inside_repo(&git_info_options))
+    };
+    if args.hides_outside_repo(user_theme) && !inside_repo() {
+        return None;
     }
 
-    let hostname: Option<String> = fast_hostname.map(|s| s.to_string()).or(mut_hostname);
+    let hostname_pending = (fast_hostname.is_none() && !quick).then(|| {
+        let ctx = ctx.clone();
+        collect::spawn(move || timings::measure("hostname", || providers.host.hostname(&ctx)))
+    });
+    #[cfg(feature = "python")]
+    let python_pending = {
+        let ctx = ctx.clone();
+        Some(collect::spawn(move || {
+            Some(python_status::python_info(&ctx))
+        }))
+    };
+    #[cfg(not(feature = "python"))]
+    let python_pending: Option<collect::Pending<Vec<structs::PythonEnv>>> = None;
+    let node_pending = args.node.then(|| {
+        let ctx = ctx.clone();
+        let cwd = std::env::current_dir().unwrap_or_default();
+        collect::spawn(move || timings::measure("node", || node_status::segment(&cwd, quick, &ctx)))
+    });
+    let kube_pending = args.kube.then(|| {
+        let ctx = ctx.clone();
+        collect::spawn(move || timings::measure("kube", || kube_status::segment(&ctx)))
+    });
+    let git = providers.git.filter(|_| !args.disable_git);
+    let git_pending = git.map(|git| {
+        collect::spawn(move || match quick {
+            true => timings::measure("git.fast", || git.head_from_file(&git_info_options)),
+            false => timings::measure("git", || {
+                git.status(&git_info_options)
+                    .ok_or_log(&git_info_options.context)
+            }),
+        })
+    });
 
-    structs::ThemeData {
+    let mut slow = Vec::new();
+    let hostname = match hostname_pending {
+        Some(pending) => collected(
+            &mut slow,
+            structs::SegmentKind::Hostname,
+            pending.wait(timeout, cancel),
+        ),
+        None => fast_hostname,
+    };
+    let python = python_pending
+        .and_then(|pending| {
+            collected(
+                &mut slow,
+                structs::SegmentKind::Python,
+                pending.wait(timeout, cancel),
+            )
+        })
+        .unwrap_or_default();
+    let custom = [
+        (structs::SegmentKind::Node, node_pending),
+        (structs::SegmentKind::Kube, kube_pending),
+    ]
+    .into_iter()
+    .filter_map(|(kind, pending)| collected(&mut slow, kind, pending?.wait(timeout, cancel)))
+    .collect();
+    let git_info = git_pending.and_then(|pending| {
+        collected(
+            &mut slow,
+            structs::SegmentKind::Git,
+            pending.wait(timeout.map(|t| t + GIT_GRACE), cancel),
+        )
+    });
+
+    let repo_path = git_info
+        .as_ref()
+        .and_then(|g| g.relative_path.as_deref())
+        .map(|p| util::truncate_path(p, args.git_relative_path_depth));
+
+    Some(structs::ThemeData {
         last_exit_status: args.last_exit_status,
-        datetime: date_time::date_time(),
+        jobs: args.jobs,
+        shell_level: args.shlvl,
+        vi_command: args.keymap == Some(args::Keymap::Vicmd),
+        cmd_duration: args
+            .cmd_duration_ms
+            .filter(|v| *v >= args.cmd_duration_threshold_ms)
+            .map(|v| date_time::format_duration(v, args.cmd_duration_precision)),
+        datetime: date_time::date_time(args, providers.clock, ctx),
         hostname,
-        username: user_host::username(),
-        python: python_status::python_info(),
+        username: providers.host.username(ctx),
+        python,
         git: git_info,
+        repo_path,
+        max_width: args.max_width.or_else(terminal::width),
+        branch_max_width: args.branch_max_width,
+        slow,
+        layout: None,
+        custom,
+    })
+}
+
+/// Unwraps collected segment value remembering if it was too slow
+fn collected<T>(
+    slow: &mut Vec<structs::SegmentKind>,
+    kind: structs::SegmentKind,
+    value: collect::Collected<T>,
+) -> Option<T> {
+    if value.is_slow() {
+        slow.push(kind);
+    }
+    value.value()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use clap::Parser;
+
+    use crate::args;
+    use crate::context;
+    use crate::date_time;
+    use crate::ilsore_format;
+    use crate::structs;
+    use crate::user_host;
+
+    struct FixedHostInfo;
+
+    impl user_host::HostInfoProvider for FixedHostInfo {
+        fn fast_hostname(&self, _ctx: &context::Context) -> Option<String> {
+            None
+        }
+
+        fn hostname(&self, _ctx: &context::Context) -> Option<String> {
+            Some("fixed-host".to_string())
+        }
+
+        fn username(&self, _ctx: &context::Context) -> Option<String> {
+            Some("fixed-user".to_string())
+        }
+    }
+
+    static FIXED_PROVIDERS: super::Providers = super::Providers {
+        clock: &date_time::SystemClock,
+        host: &FixedHostInfo,
+        git: None,
+    };
+
+    struct PanickingHostInfo;
+
+    impl user_host::HostInfoProvider for PanickingHostInfo {
+        fn fast_hostname(&self, _ctx: &context::Context) -> Option<String> {
+            None
+        }
+
+        fn hostname(&self, _ctx: &context::Context) -> Option<String> {
+            panic!("hostname lookup failed")
+        }
+
+        fn username(&self, _ctx: &context::Context) -> Option<String> {
+            Some("fixed-user".to_string())
+        }
+    }
+
+    static PANICKING_PROVIDERS: super::Providers = super::Providers {
+        clock: &date_time::SystemClock,
+        host: &PanickingHostInfo,
+        git: None,
+    };
+
+    fn args(argv: &[&str]) -> &'static args::Args {
+        Box::leak(Box::new(args::Args::parse_from(argv)))
+    }
+
+    #[test]
+    fn theme_data_host_info_test() {
+        let data = super::theme_data(
+            args(&["ilsore-format"]),
+            &context::Context::silent(),
+            None,
+            false,
+            &FIXED_PROVIDERS,
+        )
+        .expect("theme has something to show");
+        let output = ilsore_format::format_ilsore_no_color(&data, &structs::ThemeSymbols::utf8());
+        assert!(output.contains("fixed-user@fixed-host"), "{output}");
+    }
+
+    #[test]
+    fn theme_data_collector_panic_test() {
+        // panics of every thread reach the hook, the previous one still prints them
+        static REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            REPORTED.lock().expect("reports").push(info.to_string());
+            previous(info);
+        }));
+
+        let data = super::theme_data(
+            args(&["ilsore-format"]),
+            &context::Context::silent(),
+            None,
+            false,
+            &PANICKING_PROVIDERS,
+        )
+        .expect("theme has something to show");
+        assert_eq!(data.hostname, None);
+        let output = ilsore_format::format_ilsore_no_color(&data, &structs::ThemeSymbols::utf8());
+        assert!(output.contains("fixed-user@"), "{output}");
+        assert!(output.ends_with("%~>"), "{output}");
+        let reported = REPORTED.lock().expect("reports");
+        assert!(
+            reported
+                .iter()
+                .any(|v| v.contains("hostname lookup failed")),
+            "{reported:?}"
+        );
     }
 }
@@ -0,0 +1,46 @@
+use crate::structs;
+use crate::util;
+
+pub(crate) fn format_minimal(data: &structs::ThemeData, symbols: &structs::ThemeSymbols) -> String {
+    let git = data
+        .git
+        .as_ref()
+        .map(|v| format_minimal_git(v, symbols, data.branch_max_width))
+        .or_else(|| {
+            data.is_slow(structs::SegmentKind::Git)
+                .then(|| symbols.slow.to_string())
+        });
+
+    format!("{}>", git.as_deref().unwrap_or_default())
+}
+
+#[inline]
+fn format_minimal_git(
+    data: &structs::GitOutputOptions,
+    symbols: &structs::ThemeSymbols,
+    branch_max_width: Option<usize>,
+) -> String {
+    if data.untrusted {
+        return symbols.git_untrusted.to_string();
+    }
+    let branch = data
+        .head_info
+        .as_ref()
+        .and_then(|h| h.reference_short.as_ref().or(h.oid_short.as_ref()))
+        .map(|v| util::truncate_to_width(v, branch_max_width))
+        .unwrap_or_default();
+
+    let dirty = data
+        .file_status
+        .as_ref()
+        .is_some_and(|f| f.conflict || f.untracked || f.typechange || f.unstaged || f.staged);
+
+    if data.damaged {
+        return format!("{}{}", branch, symbols.git_damaged);
+    }
+
+    match dirty {
+        true => format!("{}{}", branch, symbols.git_has_unstaged),
+        false => branch.to_string(),
+    }
+}
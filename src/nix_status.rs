@@ -0,0 +1,67 @@
+//! Nix shell segment, shown inside of `nix-shell`, `nix develop` and devenv shells
+//! including ones loaded by direnv, with the name of the shell.
+
+use std::env;
+
+use crate::structs;
+use crate::util::LastPart;
+
+/// Name nixpkgs gives a shell made by `mkShell` without a name
+const DEFAULT_SHELL_NAME: &str = "nix-shell";
+
+/// Segment of the current environment
+pub(crate) fn segment() -> Option<structs::CustomSegment> {
+    Some(structs::CustomSegment {
+        name: "nix".to_string(),
+        text: shell_text(|name| env::var(name).ok().filter(|v| !v.is_empty()))?,
+        color: None,
+    })
+}
+
+/// Kind of the shell followed by its name, e.g. `nix pure proj` or `devenv proj`.
+///
+/// devenv doesn't name its shells, the name is its project folder. Nix shells have
+/// the name of their derivation in `name`, direnv sets `DIRENV_IN_NIX` for flakes
+/// dev shells it loads, which aren't marked by `IN_NIX_SHELL`.
+fn shell_text(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(root) = var("DEVENV_ROOT") {
+        return Some(format!("devenv {}", root.last_part()));
+    }
+    let kind = match var("IN_NIX_SHELL").as_deref() {
+        Some("pure") => "nix pure",
+        Some(_) => "nix",
+        None => var("DIRENV_IN_NIX").map(|_| "nix")?,
+    };
+    match var("name").filter(|v| v != DEFAULT_SHELL_NAME) {
+        Some(name) => Some(format!("{kind} {name}")),
+        None => Some(kind.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::shell_text;
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&[("name", "proj")], None)]
+    #[case(&[("IN_NIX_SHELL", "impure"), ("name", "nix-shell")], Some("nix"))]
+    #[case(&[("IN_NIX_SHELL", "impure"), ("name", "proj-dev")], Some("nix proj-dev"))]
+    #[case(&[("IN_NIX_SHELL", "pure"), ("name", "proj")], Some("nix pure proj"))]
+    #[case(&[("DIRENV_IN_NIX", "1"), ("name", "proj")], Some("nix proj"))]
+    #[case(
+        &[("DEVENV_ROOT", "/home/me/proj"), ("IN_NIX_SHELL", "impure"), ("name", "devenv-shell")],
+        Some("devenv proj")
+    )]
+    fn shell_text_test(#[case] vars: &[(&str, &str)], #[case] expected: Option<&str>) {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        };
+        assert_eq!(shell_text(var).as_deref(), expected);
+    }
+}
@@ -0,0 +1,67 @@
+//! Node.js segment, shown in a folder of a node project with the version of `node`.
+//!
+//! A project is found by `package.json` in the folder or its parents. The version is
+//! asked from `node --version`, which takes a while with version managers' shims, so
+//! it runs under the time budget of the segment.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::context;
+use crate::error::MapLog;
+use crate::structs;
+
+/// Segment of the folder, `quick` doesn't run `node` and shows nothing
+pub(crate) fn segment(
+    cwd: &Path,
+    quick: bool,
+    ctx: &context::Context,
+) -> Option<structs::CustomSegment> {
+    let in_project = || {
+        cwd.ancestors()
+            .any(|dir| dir.join("package.json").is_file())
+    };
+    if quick || !in_project() {
+        return None;
+    }
+    let output = Command::new("node")
+        .arg("--version")
+        .output()
+        .ok_or_log(ctx)?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(structs::CustomSegment {
+        name: "node".to_string(),
+        text: format!("node {}", version(&stdout)?),
+        color: None,
+    })
+}
+
+/// Version of `node --version` output without `v` prefix
+fn version(output: &str) -> Option<&str> {
+    let version = output.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    match version.is_empty() {
+        true => None,
+        false => Some(version),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::version;
+
+    #[rstest]
+    #[case("v20.11.1\n", Some("20.11.1"))]
+    #[case("18.0.0", Some("18.0.0"))]
+    #[case("\n", None)]
+    #[case("v", None)]
+    fn version_test(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(version(output), expected);
+    }
+}
@@ -0,0 +1,88 @@
+//! Destination of the rendered prompt, stdout unless `--output` or `--fd` is given.
+//!
+//! Prompt is the only thing written to the destination, and it's written at once,
+//! so readers of a fifo or a file never get a partial prompt.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::cache;
+use crate::error::Result;
+use crate::structs;
+
+/// Ends every prompt of `--two-phase` output, ASCII record separator never appears in a prompt
+pub(crate) const PHASE_END: char = '\x1e';
+
+pub(crate) enum Output<'a> {
+    Stdout,
+    File(&'a Path),
+    Fd(i32),
+}
+
+impl Output<'_> {
+    pub(crate) fn write(&self, prompt: &str) -> Result<()> {
+        match self {
+            Output::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(prompt.as_bytes())?;
+                stdout.flush()?;
+            }
+            Output::File(path) => write_file(path, prompt)?,
+            Output::Fd(fd) => write_fd(*fd, prompt)?,
+        }
+        Ok(())
+    }
+}
+
+/// Short hash of what the prompt shows, so shell frameworks can skip redrawing the same prompt.
+///
+/// It's computed over theme data rather than the rendered string, so colors and prompt
+/// escapes don't change it. Time changes on every run and is left out as well.
+pub(crate) fn content_hash(data: &structs::ThemeData) -> String {
+    cache::key((
+        (data.last_exit_status, data.jobs, &data.cmd_duration),
+        (data.datetime.date.to_string(), data.datetime.show_date),
+        (&data.hostname, &data.username, &data.python),
+        (&data.git, &data.repo_path),
+        (data.max_width, data.branch_max_width),
+        (&data.slow, &data.custom),
+    ))
+}
+
+/// Regular files are replaced atomically, anything else like a fifo is written directly
+fn write_file(path: &Path, prompt: &str) -> Result<()> {
+    let is_regular = fs::metadata(path).map_or(true, |m| m.is_file());
+    if !is_regular {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .write_all(prompt.as_bytes())?;
+        return Ok(());
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(format!(".{}", std::process::id()));
+    let tmp_path = Path::new(&tmp_name);
+    fs::write(tmp_path, prompt)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_fd(fd: i32, prompt: &str) -> Result<()> {
+    use std::os::fd::FromRawFd;
+
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(format!("file descriptor {fd} is not open").into());
+    }
+    // descriptor is owned by the parent process and is closed on exit anyway
+    let mut file = std::mem::ManuallyDrop::new(unsafe { fs::File::from_raw_fd(fd) });
+    file.write_all(prompt.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_fd(_fd: i32, _prompt: &str) -> Result<()> {
+    Err("writing to a file descriptor requires unix".into())
+}
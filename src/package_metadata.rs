@@ -0,0 +1,60 @@
+//! Auxiliary files for distribution packages, written by hidden `package-metadata` subcommand.
+//!
+//! Files are laid out as packagers install them:
+//! `completions/` for every supported shell, `man/` with the manual page
+//! and `init/` with prompt snippets calling the installed binary.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::CommandFactory;
+use clap::ValueEnum;
+
+use crate::args;
+use crate::error::Result;
+use crate::shell_init;
+
+/// Writes completions, man page and init snippets into the folder, returns written files
+pub(crate) fn write(dir: &Path, binary: &Path) -> Result<Vec<PathBuf>> {
+    let mut command = args::Args::command();
+    let name = command.get_name().to_string();
+    let mut written = Vec::new();
+
+    let completions = dir.join("completions");
+    fs::create_dir_all(&completions)?;
+    for shell in [
+        clap_complete::Shell::Bash,
+        clap_complete::Shell::Zsh,
+        clap_complete::Shell::Fish,
+    ] {
+        written.push(clap_complete::generate_to(
+            shell,
+            &mut command,
+            &name,
+            &completions,
+        )?);
+    }
+
+    let man = dir.join("man");
+    fs::create_dir_all(&man)?;
+    let page = man.join(format!("{name}.1"));
+    let mut buffer = Vec::new();
+    clap_mangen::Man::new(command).render(&mut buffer)?;
+    fs::write(&page, buffer)?;
+    written.push(page);
+
+    let init = dir.join("init");
+    fs::create_dir_all(&init)?;
+    for shell in shell_init::Shell::value_variants() {
+        let shell_name = shell
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        let path = init.join(format!("{name}.{shell_name}"));
+        fs::write(&path, shell_init::snippet(*shell, false, binary)?)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
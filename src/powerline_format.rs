@@ -0,0 +1,474 @@
+use std::borrow::Cow;
+
+use crate::ilsore_format;
+use crate::structs;
+use crate::util;
+use crate::util::ShortHost;
+
+static RESET_COLOR: &str = "%{%f%k%b%}";
+
+/// Text color on top of segment backgrounds
+static TEXT_COLOR: &str = "16";
+
+/// Background of segments produced by user scripts
+static CUSTOM_COLOR: &str = "39";
+
+/// Every prompt segment followed by custom ones
+static DEFAULT_ORDER: &[structs::LayoutItem] = &[
+    structs::LayoutItem::prompt(structs::PromptSegment::DateTime),
+    structs::LayoutItem::prompt(structs::PromptSegment::UserHost),
+    structs::LayoutItem::prompt(structs::PromptSegment::Python),
+    structs::LayoutItem::prompt(structs::PromptSegment::Git),
+    structs::LayoutItem::prompt(structs::PromptSegment::Jobs),
+    structs::LayoutItem::prompt(structs::PromptSegment::CmdDuration),
+    structs::LayoutItem::prompt(structs::PromptSegment::LastStatus),
+    structs::LayoutItem::prompt(structs::PromptSegment::ShellLevel),
+    structs::LayoutItem::prompt(structs::PromptSegment::ViMode),
+];
+
+/// Colored part of the powerline prompt
+struct Segment {
+    text: String,
+    /// Text used when the line is too wide
+    short: Option<String>,
+    /// Segments with lower priority are shortened and dropped first
+    priority: u8,
+    color: &'static str,
+    /// Background color, separators take it to draw transitions
+    background: Option<&'static str>,
+}
+
+impl Segment {
+    fn new(text: String, priority: u8, background: &'static str) -> Self {
+        Segment {
+            text,
+            short: None,
+            priority,
+            color: TEXT_COLOR,
+            background: Some(background),
+        }
+    }
+
+    /// Color of separators and caps drawn on the segment edge
+    fn edge_color(&self) -> &'static str {
+        self.background.unwrap_or(self.color)
+    }
+}
+
+pub(crate) fn format_ilsore_powerline(
+    data: &structs::ThemeData,
+    symbols: &structs::ThemeSymbols,
+) -> String {
+    let render = |shown| match shown {
+        structs::ShownSegment::Prompt(kind) => {
+            let text = segment_text(kind, data, symbols)?;
+            let color = data
+                .layout
+                .as_ref()
+                .and_then(|v| v.colors[kind])
+                .unwrap_or(default_color(kind));
+            Some(Segment {
+                short: short_text(kind, data, symbols),
+                ..Segment::new(text, priority(kind), color)
+            })
+        }
+        structs::ShownSegment::Custom(v) => Some(Segment::new(
+            v.text.clone(),
+            3,
+            v.color.unwrap_or(CUSTOM_COLOR),
+        )),
+    };
+
+    let segments: Vec<Segment> = data
+        .arranged(DEFAULT_ORDER, render)
+        .into_iter()
+        .map(|v| match v {
+            structs::Arranged::Segment(segment) => segment,
+            structs::Arranged::Group(group, segments) => {
+                let texts: Vec<_> = segments.iter().map(|v| v.text.as_str()).collect();
+                let text = format!(
+                    "{}{}{}",
+                    group.prefix,
+                    texts.join(group.separator),
+                    group.suffix
+                );
+                Segment {
+                    text,
+                    short: None,
+                    priority: segments
+                        .iter()
+                        .map(|v| v.priority)
+                        .max()
+                        .unwrap_or_default(),
+                    color: TEXT_COLOR,
+                    background: group.color.or(segments[0].background),
+                }
+            }
+        })
+        .collect();
+
+    let segments = fit(segments, data.max_width, symbols);
+    format!("{}\n%~>", join(&segments, symbols))
+}
+
+/// Shortens and then drops segments with the lowest priority until the line fits into
+/// the max width, like `util::fit_segments` does, separators and caps are counted too
+fn fit(
+    mut segments: Vec<Segment>,
+    max_width: Option<usize>,
+    symbols: &structs::ThemeSymbols,
+) -> Vec<Segment> {
+    let Some(max_width) = max_width else {
+        return segments;
+    };
+    let fits = |segments: &[Segment]| util::prompt_width(&join(segments, symbols)) <= max_width;
+
+    let mut order: Vec<usize> = (0..segments.len()).collect();
+    order.sort_by_key(|&idx| segments[idx].priority);
+    for &idx in &order {
+        if fits(&segments) {
+            break;
+        }
+        if let Some(short) = segments[idx].short.take() {
+            segments[idx].text = short;
+        }
+    }
+
+    while !fits(&segments) {
+        let lowest = segments
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, v)| v.priority)
+            .map(|(idx, _)| idx);
+        match lowest {
+            Some(idx) => segments.remove(idx),
+            None => break,
+        };
+    }
+    segments
+}
+
+fn segment_text(
+    kind: structs::PromptSegment,
+    data: &structs::ThemeData,
+    symbols: &structs::ThemeSymbols,
+) -> Option<String> {
+    match kind {
+        structs::PromptSegment::DateTime => Some(match data.datetime.show_date {
+            true => format!("{} {}", data.datetime.date, data.datetime.time),
+            false => data.datetime.time.to_string(),
+        }),
+        structs::PromptSegment::UserHost => Some(format!(
+            "{}@{}",
+            data.username.as_deref().unwrap_or_default(),
+            hostname(data, symbols)
+        )),
+        structs::PromptSegment::Python => data.python_text(symbols).or_else(|| {
+            ilsore_format::slow_segment(data, structs::SegmentKind::Python, symbols)
+                .map(String::from)
+        }),
+        structs::PromptSegment::Git => match &data.git {
+            Some(v) if v.head_info.is_some() || v.untrusted || v.damaged => Some(format_git(
+                v,
+                symbols,
+                data.branch_max_width,
+                data.repo_path.as_deref(),
+            )),
+            Some(v) if v.slow => Some(symbols.slow.to_string()),
+            _ => ilsore_format::slow_segment(data, structs::SegmentKind::Git, symbols)
+                .map(String::from),
+        },
+        structs::PromptSegment::Jobs => {
+            (data.jobs != 0).then(|| format!("{}{}", symbols.jobs, data.jobs))
+        }
+        structs::PromptSegment::CmdDuration => data
+            .cmd_duration
+            .as_ref()
+            .map(|v| format!("{}{}", symbols.cmd_duration, v)),
+        structs::PromptSegment::LastStatus => {
+            (data.last_exit_status != 0).then(|| data.last_exit_status.to_string())
+        }
+        structs::PromptSegment::ShellLevel => {
+            (data.shell_level > 1).then(|| format!("{}{}", symbols.shell_level, data.shell_level))
+        }
+        structs::PromptSegment::ViMode => data.vi_command.then(|| symbols.vi_command.to_string()),
+    }
+}
+
+/// Shorter text of the segment for a narrow terminal, `None` if it has no such one
+fn short_text(
+    kind: structs::PromptSegment,
+    data: &structs::ThemeData,
+    symbols: &structs::ThemeSymbols,
+) -> Option<String> {
+    match kind {
+        structs::PromptSegment::DateTime => Some(data.datetime.time.to_string()),
+        structs::PromptSegment::UserHost => Some(format!(
+            "{}@{}",
+            data.username.as_deref().unwrap_or_default(),
+            hostname(data, symbols).short_host()
+        )),
+        _ => None,
+    }
+}
+
+/// Host name or the slow marker when looking it up took too long
+fn hostname<'a>(data: &'a structs::ThemeData, symbols: &structs::ThemeSymbols) -> &'a str {
+    data.hostname.as_deref().unwrap_or(
+        ilsore_format::slow_segment(data, structs::SegmentKind::Hostname, symbols)
+            .unwrap_or_default(),
+    )
+}
+
+/// Priority of the segment when the line is too wide, the same as in `ilsore` themes
+#[inline]
+fn priority(kind: structs::PromptSegment) -> u8 {
+    match kind {
+        structs::PromptSegment::DateTime => 1,
+        structs::PromptSegment::UserHost => 2,
+        structs::PromptSegment::Python
+        | structs::PromptSegment::CmdDuration
+        | structs::PromptSegment::ShellLevel => 3,
+        structs::PromptSegment::Git => 4,
+        structs::PromptSegment::Jobs
+        | structs::PromptSegment::LastStatus
+        | structs::PromptSegment::ViMode => 5,
+    }
+}
+
+#[inline]
+fn default_color(kind: structs::PromptSegment) -> &'static str {
+    match kind {
+        structs::PromptSegment::DateTime => "165",
+        structs::PromptSegment::UserHost => "214",
+        structs::PromptSegment::Python => "42",
+        structs::PromptSegment::Git => "226",
+        structs::PromptSegment::Jobs => "75",
+        structs::PromptSegment::CmdDuration => "180",
+        structs::PromptSegment::LastStatus => "196",
+        structs::PromptSegment::ShellLevel => "141",
+        structs::PromptSegment::ViMode => "208",
+    }
+}
+
+#[inline]
+fn format_git(
+    data: &structs::GitOutputOptions,
+    symbols: &structs::ThemeSymbols,
+    branch_max_width: Option<usize>,
+    repo_path: Option<&str>,
+) -> String {
+    let parts: [Cow<str>; 13] = [
+        ilsore_format::format_ilsore_nesting(data, symbols)
+            .trim_end()
+            .to_string()
+            .into(),
+        ilsore_format::format_ilsore_location(data.repo_name.as_deref(), repo_path)
+            .trim_end()
+            .to_string()
+            .into(),
+        ilsore_format::format_ilsore_git_head_info(&data.head_info, symbols, branch_max_width)
+            .unwrap_or_default()
+            .into(),
+        ilsore_format::format_ilsore_git_symbols(data, symbols).into(),
+        data.diff_stat
+            .as_ref()
+            .map(ilsore_format::format_diff_stat)
+            .unwrap_or_default()
+            .into(),
+        ilsore_format::format_remotes(&data.remotes, symbols).into(),
+        data.pull_request
+            .map(ilsore_format::format_pull_request)
+            .unwrap_or_default()
+            .into(),
+        data.ci_status
+            .map(|v| ilsore_format::format_ci_status(v, symbols))
+            .unwrap_or_default()
+            .into(),
+        ilsore_format::format_today_commits(data.today_commits)
+            .unwrap_or_default()
+            .into(),
+        data.base_age_days
+            .map(ilsore_format::format_base_age)
+            .unwrap_or_default()
+            .into(),
+        data.dirty_age_secs
+            .map(ilsore_format::format_dirty_age)
+            .unwrap_or_default()
+            .into(),
+        data.tag_distance
+            .as_ref()
+            .map(ilsore_format::format_tag_distance)
+            .unwrap_or_default()
+            .into(),
+        data.identity
+            .as_ref()
+            .map(ilsore_format::format_identity)
+            .unwrap_or_default()
+            .into(),
+    ];
+    parts
+        .iter()
+        .filter(|v| !v.is_empty())
+        .map(|v| v.as_ref())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Joins segments with separators, segments of the same color are divided by a thin one.
+///
+/// Separator between different backgrounds takes color of the previous segment's background
+/// and is drawn on the next segment's background.
+fn join(segments: &[Segment], symbols: &structs::ThemeSymbols) -> String {
+    let mut result = String::new();
+    if let Some(first) = segments.first() {
+        result.push_str(&format_style(first.edge_color(), None));
+        result.push_str(symbols.cap_left);
+    }
+    for (idx, segment) in segments.iter().enumerate() {
+        if let Some(previous) = idx.checked_sub(1).map(|i| &segments[i]) {
+            let same = previous.background == segment.background
+                && (previous.background.is_some() || previous.color == segment.color);
+            match same {
+                true => {
+                    result.push_str(&format_style(previous.color, previous.background));
+                    result.push_str(symbols.separator_thin);
+                }
+                false => {
+                    result.push_str(&format_style(previous.edge_color(), segment.background));
+                    result.push_str(symbols.separator);
+                }
+            }
+        }
+        result.push_str(&format_style(segment.color, segment.background));
+        result.push_str(&format!(" {} ", segment.text));
+    }
+    if let Some(last) = segments.last() {
+        result.push_str(&format_style(last.edge_color(), None));
+        result.push_str(symbols.cap_right);
+    }
+    result.push_str(RESET_COLOR);
+    result
+}
+
+#[inline]
+fn format_style(color: &str, background: Option<&str>) -> String {
+    match background {
+        Some(background) => format!("%{{%F{{{color}}}%K{{{background}}}%}}"),
+        None => format!("%{{%F{{{color}}}%k%}}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::fit;
+    use super::format_ilsore_powerline;
+    use super::join;
+    use super::Segment;
+    use crate::demo;
+    use crate::structs;
+    use crate::structs::ThemeSymbols;
+
+    fn segment(text: &str, color: &'static str, background: Option<&'static str>) -> Segment {
+        Segment {
+            text: text.to_string(),
+            short: None,
+            priority: 0,
+            color,
+            background,
+        }
+    }
+
+    #[test]
+    fn join_test() {
+        let symbols = ThemeSymbols {
+            cap_left: "(",
+            cap_right: ")",
+            ..ThemeSymbols::ascii()
+        };
+        assert_eq!(join(&[], &symbols), "%{%f%k%b%}");
+        assert_eq!(
+            join(
+                &[
+                    segment("a", "1", None),
+                    segment("b", "1", None),
+                    segment("c", "2", None)
+                ],
+                &symbols
+            ),
+            concat!(
+                "%{%F{1}%k%}(%{%F{1}%k%} a ",
+                "%{%F{1}%k%}|%{%F{1}%k%} b ",
+                "%{%F{1}%k%}>%{%F{2}%k%} c ",
+                "%{%F{2}%k%})%{%f%k%b%}",
+            )
+        );
+        assert_eq!(
+            join(
+                &[
+                    segment("a", "0", Some("1")),
+                    segment("b", "0", Some("1")),
+                    segment("c", "0", Some("2"))
+                ],
+                &symbols
+            ),
+            concat!(
+                "%{%F{1}%k%}(%{%F{0}%K{1}%} a ",
+                "%{%F{0}%K{1}%}|%{%F{0}%K{1}%} b ",
+                "%{%F{1}%K{2}%}>%{%F{0}%K{2}%} c ",
+                "%{%F{2}%k%})%{%f%k%b%}",
+            )
+        );
+    }
+
+    #[test]
+    fn fit_test() {
+        let symbols = ThemeSymbols {
+            cap_left: "(",
+            cap_right: ")",
+            ..ThemeSymbols::ascii()
+        };
+        let segments = || {
+            vec![
+                Segment {
+                    short: Some("time".to_string()),
+                    priority: 1,
+                    ..segment("date time", "0", Some("1"))
+                },
+                Segment {
+                    priority: 2,
+                    ..segment("user@host", "0", Some("2"))
+                },
+                Segment {
+                    priority: 4,
+                    ..segment("git", "0", Some("3"))
+                },
+            ]
+        };
+        let texts = |max_width| {
+            fit(segments(), max_width, &symbols)
+                .into_iter()
+                .map(|v| v.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(texts(None), ["date time", "user@host", "git"]);
+        assert_eq!(texts(Some(31)), ["date time", "user@host", "git"]);
+        assert_eq!(texts(Some(26)), ["time", "user@host", "git"]);
+        assert_eq!(texts(Some(19)), ["user@host", "git"]);
+        assert_eq!(texts(Some(4)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn slow_test() {
+        let mut data = demo::theme_data(structs::GitOutputOptions::default(), None);
+        data.hostname = None;
+        data.git = None;
+        data.slow = vec![structs::SegmentKind::Hostname, structs::SegmentKind::Git];
+        let output = format_ilsore_powerline(&data, &ThemeSymbols::utf8());
+        assert!(output.contains(" user@⌛ "), "{output:?}");
+        assert!(output.contains(" ⌛ "), "{output:?}");
+    }
+}
@@ -1,9 +1,178 @@
+use crate::context;
 use crate::error::MapLog;
+use crate::structs;
 use crate::util::LastPart;
 use std::env;
+use std::path::Path;
 
-pub fn python_info() -> Option<String> {
-    env::var("VIRTUAL_ENV")
-        .ok_or_log()
-        .map(|v| v.as_str().last_two_parts().to_string())
+/// Active python environments, from the outermost one to the one in effect.
+///
+/// Conda environments go first as a virtualenv is usually created on top of a conda
+/// python, e.g. `base` and `proj/.venv`. Environment of a uv project is shown inside
+/// of the project even if it isn't activated, as `uv run` doesn't need that.
+pub fn python_info(ctx: &context::Context) -> Vec<structs::PythonEnv> {
+    let mut envs: Vec<_> = conda_envs(|name| env::var(name).ok())
+        .into_iter()
+        .map(|name| structs::PythonEnv {
+            name,
+            manager: structs::PythonManager::Conda,
+        })
+        .collect();
+    let pipenv_active = env::var("PIPENV_ACTIVE").is_ok_and(|v| v == "1");
+    let virtual_env = env::var("VIRTUAL_ENV")
+        .ok_or_log(ctx)
+        .map(|v| virtual_env(&v, pipenv_active))
+        .or_else(|| uv_project(&env::current_dir().ok()?));
+    envs.extend(virtual_env);
+    envs
+}
+
+/// Activated virtual environment, recognized by the files of its project or by pipenv
+/// environment variable when it lives outside of the project
+fn virtual_env(path: &str, pipenv_active: bool) -> structs::PythonEnv {
+    let venv = Path::new(path);
+    let project = venv
+        .parent()
+        .filter(|_| venv.file_name().is_some_and(|v| v == ".venv"));
+    let project_name = || {
+        project
+            .and_then(Path::file_name)
+            .map(|v| v.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    match project {
+        Some(dir) if dir.join("uv.lock").is_file() => structs::PythonEnv {
+            name: project_name(),
+            manager: structs::PythonManager::Uv,
+        },
+        Some(dir) if dir.join("Pipfile").is_file() => structs::PythonEnv {
+            name: project_name(),
+            manager: structs::PythonManager::Pipenv,
+        },
+        _ if pipenv_active => structs::PythonEnv {
+            name: pipenv_name(path.last_part()).to_string(),
+            manager: structs::PythonManager::Pipenv,
+        },
+        _ => structs::PythonEnv {
+            name: path.last_two_parts().to_string(),
+            manager: structs::PythonManager::Virtualenv,
+        },
+    }
+}
+
+/// Environment of uv project the folder is in, `.venv` next to `uv.lock`
+fn uv_project(cwd: &Path) -> Option<structs::PythonEnv> {
+    let dir = cwd
+        .ancestors()
+        .find(|dir| dir.join("uv.lock").is_file() && dir.join(".venv").is_dir())?;
+    Some(structs::PythonEnv {
+        name: dir.file_name()?.to_string_lossy().to_string(),
+        manager: structs::PythonManager::Uv,
+    })
+}
+
+/// Project name of a pipenv environment folder, pipenv adds a dash and 8 characters of
+/// base64 encoded hash of the project path to it
+fn pipenv_name(folder: &str) -> &str {
+    let is_hash = |v: &str| {
+        v.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    match folder.char_indices().rev().nth(8) {
+        Some((idx, '-')) if idx > 0 && is_hash(&folder[idx + 1..]) => &folder[..idx],
+        _ => folder,
+    }
+}
+
+/// Conda environments including ones stacked with `conda activate --stack`.
+///
+/// Conda keeps prefix of the previous environment as `CONDA_PREFIX_<level>` and marks
+/// a stacked activation with `CONDA_STACKED_<level>`, the level is `CONDA_SHLVL`.
+fn conda_envs(var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let Some(current) = var("CONDA_DEFAULT_ENV").filter(|v| !v.is_empty()) else {
+        return Vec::new();
+    };
+    let mut envs = vec![current];
+    let mut level: usize = var("CONDA_SHLVL")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default();
+    while level > 1 && var(&format!("CONDA_STACKED_{level}")).is_some_and(|v| v == "true") {
+        level -= 1;
+        match var(&format!("CONDA_PREFIX_{level}")) {
+            Some(prefix) => envs.push(conda_env_name(&prefix).to_string()),
+            None => break,
+        }
+    }
+    envs.reverse();
+    envs
+}
+
+/// Name of conda environment by its prefix, environments other than the base one
+/// live in `envs` folder of the installation
+fn conda_env_name(prefix: &str) -> &str {
+    let prefix = prefix.trim_end_matches(['/', '\\']);
+    match prefix.rsplit_once(['/', '\\']) {
+        Some((parent, name)) if parent.ends_with("envs") => name,
+        _ => "base",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::conda_env_name;
+    use super::conda_envs;
+    use super::pipenv_name;
+
+    #[rstest]
+    #[case(&[], &[])]
+    #[case(&[("CONDA_DEFAULT_ENV", "base"), ("CONDA_SHLVL", "1")], &["base"])]
+    // activation without stacking replaces the previous environment
+    #[case(
+        &[
+            ("CONDA_DEFAULT_ENV", "proj"),
+            ("CONDA_SHLVL", "2"),
+            ("CONDA_PREFIX_1", "/opt/conda"),
+        ],
+        &["proj"]
+    )]
+    #[case(
+        &[
+            ("CONDA_DEFAULT_ENV", "ml"),
+            ("CONDA_SHLVL", "3"),
+            ("CONDA_PREFIX_1", "/opt/conda"),
+            ("CONDA_PREFIX_2", "/opt/conda/envs/tools"),
+            ("CONDA_STACKED_2", "true"),
+            ("CONDA_STACKED_3", "true"),
+        ],
+        &["base", "tools", "ml"]
+    )]
+    fn conda_envs_test(#[case] vars: &[(&str, &str)], #[case] expected: &[&str]) {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        };
+        assert_eq!(conda_envs(var), expected);
+    }
+
+    #[rstest]
+    #[case("/opt/conda", "base")]
+    #[case("/opt/conda/envs/ml/", "ml")]
+    #[case(r"C:\Users\me\miniconda3\envs\ml", "ml")]
+    fn conda_env_name_test(#[case] prefix: &str, #[case] expected: &str) {
+        assert_eq!(conda_env_name(prefix), expected);
+    }
+
+    #[rstest]
+    #[case("proj-AbC_12xY", "proj")]
+    #[case("my-proj-AbC_12xY", "my-proj")]
+    #[case("proj-Ab-C_12x", "proj")]
+    #[case("my-proj", "my-proj")]
+    #[case("-AbC_12xY", "-AbC_12xY")]
+    fn pipenv_name_test(#[case] folder: &str, #[case] expected: &str) {
+        assert_eq!(pipenv_name(folder), expected);
+    }
 }
@@ -0,0 +1,422 @@
+//! Recordings for bug reports. `record` subcommand writes collected data of the prompt,
+//! effective git options and raw data of the repository, `render` subcommand renders the
+//! prompt from such a file without access to the repository.
+//!
+//! A recording is the document of `json` theme with `options`, `raw` and widths added.
+//! Host, user, identity, branch url, expected email and texts of custom segments are
+//! replaced, branch names and paths are kept as they're often the reason of a bug.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::schema;
+use crate::structs;
+use crate::structs::OptionTrace;
+
+/// Recording file, only the prompt data and widths are read back
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Recording<'a> {
+    #[serde(flatten)]
+    prompt: schema::Prompt<'a>,
+    /// Effective git options by their names
+    options: BTreeMap<Cow<'a, str>, RecordedOption<'a>>,
+    raw: Option<Raw<'a>>,
+    repo_path: Option<Cow<'a, str>>,
+    max_width: Option<usize>,
+    branch_max_width: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct RecordedOption<'a> {
+    value: Cow<'a, str>,
+    layer: String,
+}
+
+/// [`structs::GitRawData`] as pairs of strings
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Raw<'a> {
+    refs: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    status: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    config: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+/// Recording of the prompt data with personal values replaced
+pub(crate) fn record(
+    data: &structs::ThemeData,
+    options: Option<&OptionTrace>,
+    raw: Option<&structs::GitRawData>,
+) -> Result<String> {
+    let recording = Recording {
+        prompt: schema::Prompt::from(data),
+        options: options
+            .into_iter()
+            .flatten()
+            .map(|(name, value, layer)| {
+                let value = match *name {
+                    "expected-email" if !value.is_empty() => "<redacted>",
+                    _ => value.as_str(),
+                };
+                let option = RecordedOption {
+                    value: Cow::from(value),
+                    layer: layer.to_string(),
+                };
+                (Cow::from(*name), option)
+            })
+            .collect(),
+        raw: raw.map(|raw| Raw {
+            refs: pairs(&raw.refs),
+            status: pairs(&raw.status),
+            config: pairs(&raw.config),
+        }),
+        repo_path: data.repo_path.as_deref().map(Cow::from),
+        max_width: data.max_width,
+        branch_max_width: data.branch_max_width,
+    };
+    let mut value =
+        serde_json::to_value(recording).map_err(|err| format!("data isn't serializable: {err}"))?;
+    sanitize(&mut value);
+    let recording = serde_json::to_string_pretty(&value)
+        .map_err(|err| format!("data isn't serializable: {err}"))?;
+    Ok(format!("{recording}\n"))
+}
+
+fn pairs(values: &[(String, String)]) -> Vec<(Cow<'_, str>, Cow<'_, str>)> {
+    values
+        .iter()
+        .map(|(k, v)| (Cow::from(k), Cow::from(v)))
+        .collect()
+}
+
+fn sanitize(value: &mut Value) {
+    let replaced = [
+        ("/hostname", json!("host")),
+        ("/username", json!("user")),
+        ("/git/head_info/branch_url", Value::Null),
+        ("/git/identity/name", json!("user")),
+        ("/git/identity/email", json!("user@example.com")),
+    ];
+    for (pointer, replacement) in replaced {
+        if let Some(v) = value.pointer_mut(pointer).filter(|v| !v.is_null()) {
+            *v = replacement;
+        }
+    }
+    // custom segments show environment variables and output of scripts
+    if let Some(custom) = value.pointer_mut("/custom").and_then(Value::as_object_mut) {
+        custom
+            .values_mut()
+            .for_each(|text| *text = json!("<redacted>"));
+    }
+}
+
+/// Prompt data from a recording, unknown fields are ignored and missing ones are empty
+pub(crate) fn theme_data(recording: &str) -> Result<structs::ThemeData> {
+    let recording: Recording =
+        serde_json::from_str(recording).map_err(|err| format!("invalid recording: {err}"))?;
+    let prompt = recording.prompt;
+    if prompt.schema_version != schema::SCHEMA_VERSION {
+        return Err(format!(
+            "recording has schema version {}, expected {}",
+            prompt.schema_version,
+            schema::SCHEMA_VERSION
+        )
+        .into());
+    }
+
+    Ok(structs::ThemeData {
+        last_exit_status: prompt.last_exit_status,
+        jobs: prompt.jobs,
+        shell_level: prompt.shell_level,
+        vi_command: prompt.vi_command,
+        cmd_duration: prompt.cmd_duration.map(Cow::into_owned),
+        datetime: structs::DateTime {
+            date: Box::new(prompt.date),
+            time: Box::new(prompt.time),
+            show_date: true,
+        },
+        hostname: prompt.hostname.map(Cow::into_owned),
+        username: prompt.username.map(Cow::into_owned),
+        python: python(
+            prompt.python_envs,
+            prompt.python,
+            prompt.python_manager.as_deref(),
+        ),
+        git: prompt.git.map(git),
+        repo_path: recording.repo_path.map(Cow::into_owned),
+        max_width: recording.max_width,
+        branch_max_width: recording.branch_max_width,
+        slow: prompt.slow.iter().filter_map(|v| segment_kind(v)).collect(),
+        layout: None,
+        custom: prompt
+            .custom
+            .into_iter()
+            .map(|(name, text)| structs::CustomSegment {
+                name: name.into_owned(),
+                text: text.into_owned(),
+                color: None,
+            })
+            .collect(),
+    })
+}
+
+/// Stacked environments, managers of outer ones aren't recorded and taken as conda.
+/// Without `python` feature every manager is read as virtualenv
+fn python(
+    envs: Vec<Cow<str>>,
+    python: Option<Cow<str>>,
+    manager: Option<&str>,
+) -> Vec<structs::PythonEnv> {
+    let mut names: Vec<_> = envs.into_iter().map(Cow::into_owned).collect();
+    if names.is_empty() {
+        names.extend(python.map(Cow::into_owned));
+    }
+    let manager = manager
+        .and_then(python_manager)
+        .unwrap_or(structs::PythonManager::Virtualenv);
+    let count = names.len();
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(idx, name)| structs::PythonEnv {
+            name,
+            manager: match idx + 1 == count {
+                true => manager,
+                #[cfg(feature = "python")]
+                false => structs::PythonManager::Conda,
+                #[cfg(not(feature = "python"))]
+                false => structs::PythonManager::Virtualenv,
+            },
+        })
+        .collect()
+}
+
+fn python_manager(value: &str) -> Option<structs::PythonManager> {
+    match value {
+        #[cfg(feature = "python")]
+        "conda" => Some(structs::PythonManager::Conda),
+        "virtualenv" => Some(structs::PythonManager::Virtualenv),
+        #[cfg(feature = "python")]
+        "uv" => Some(structs::PythonManager::Uv),
+        #[cfg(feature = "python")]
+        "pipenv" => Some(structs::PythonManager::Pipenv),
+        _ => None,
+    }
+}
+
+fn git(git: schema::Git) -> structs::GitOutputOptions {
+    structs::GitOutputOptions {
+        head_info: git.head_info.map(|h| structs::GitHeadInfo {
+            reference_short: h.reference_short.map(Cow::into_owned),
+            reference_name: h.reference_name.map(Cow::into_owned),
+            reference_kind: h.reference_kind.as_deref().and_then(|v| match v {
+                "symbolic" => Some(structs::GitReferenceKind::Symbolic),
+                "direct" => Some(structs::GitReferenceKind::Direct),
+                _ => None,
+            }),
+            oid: h.oid.map(Cow::into_owned),
+            oid_short: h.oid_short.map(Cow::into_owned),
+            detached: h.detached,
+            detached_from: h.detached_from.map(Cow::into_owned),
+            branch_url: h.branch_url.map(Cow::into_owned),
+            signed: h.signed,
+            ticket: h.ticket.map(|t| structs::GitTicket {
+                id: t.id.into_owned(),
+                name: t.name.into_owned(),
+            }),
+        }),
+        file_status: git.file_status.map(|f| structs::GitFileStatus {
+            conflict: f.conflict,
+            untracked: f.untracked,
+            typechange: f.typechange,
+            unstaged: f.unstaged,
+            staged: f.staged,
+            counts: file_counts(&f.counts),
+        }),
+        branch_ahead_behind: git
+            .branch_ahead_behind
+            .map(|b| structs::GitBranchAheadBehind {
+                ahead: b.ahead,
+                behind: b.behind,
+            }),
+        state: git.state.as_deref().and_then(repo_state),
+        relative_path: git.relative_path.map(|v| v.into_owned().into()),
+        repo_name: git.repo_name.map(Cow::into_owned),
+        read_only: git.read_only,
+        busy: git.busy,
+        shallow: git.shallow,
+        lfs: git.lfs.map(|v| structs::GitLfsStatus {
+            unpulled: v.unpulled,
+        }),
+        identity_mismatch: git.identity_mismatch,
+        protected: git.protected,
+        large_staged: git.large_staged,
+        secrets_staged: git.secrets_staged,
+        hooks: git.hooks,
+        from_env: git.from_env,
+        slow: git.slow,
+        reduced: git.reduced,
+        inside_git_dir: git.inside_git_dir,
+        untrusted: git.untrusted,
+        damaged: git.damaged,
+        diff_stat: git.diff_stat.map(|d| structs::GitDiffStat {
+            insertions: d.insertions,
+            deletions: d.deletions,
+        }),
+        nested: git.nested,
+        outer_branch: git.outer_branch.map(Cow::into_owned),
+        remotes: git
+            .remotes
+            .into_iter()
+            .map(|r| structs::GitRemoteDivergence {
+                remote: r.remote.into_owned(),
+                ahead: r.ahead,
+                behind: r.behind,
+            })
+            .collect(),
+        pull_request: git.pull_request,
+        ci_status: git.ci_status.as_deref().and_then(|v| match v {
+            "passed" => Some(structs::GitCiStatus::Passed),
+            "failed" => Some(structs::GitCiStatus::Failed),
+            "running" => Some(structs::GitCiStatus::Running),
+            _ => None,
+        }),
+        today_commits: git.today_commits,
+        base_age_days: git.base_age_days,
+        dirty_age_secs: git.dirty_age_secs,
+        tag_distance: git.tag_distance.map(|v| structs::GitTagDistance {
+            tag: v.tag.into_owned(),
+            ahead: v.ahead,
+        }),
+        identity: git.identity.map(|v| structs::GitIdentity {
+            name: v.name.map(Cow::into_owned),
+            email: v.email.map(Cow::into_owned),
+        }),
+        default_branch: git.default_branch.map(Cow::into_owned),
+    }
+}
+
+fn file_counts(counts: &BTreeMap<Cow<str>, usize>) -> structs::GitFileCounts {
+    let count = |name| counts.get(name).copied().unwrap_or_default();
+    structs::GitFileCounts {
+        staged_new: count("staged_new"),
+        staged_modified: count("staged_modified"),
+        staged_deleted: count("staged_deleted"),
+        staged_renamed: count("staged_renamed"),
+        staged_typechange: count("staged_typechange"),
+        wt_new: count("wt_new"),
+        wt_modified: count("wt_modified"),
+        wt_deleted: count("wt_deleted"),
+        wt_renamed: count("wt_renamed"),
+        wt_typechange: count("wt_typechange"),
+        conflicted: count("conflicted"),
+    }
+}
+
+/// State from its display form like `bisect ~3 steps`
+fn repo_state(value: &str) -> Option<structs::GitRepoState> {
+    let state = match value {
+        "merge" => structs::GitRepoState::Merge,
+        "revert" => structs::GitRepoState::Revert,
+        "cherry-pick" => structs::GitRepoState::CherryPick,
+        "bisect" => structs::GitRepoState::Bisect { steps: None },
+        "rebase" => structs::GitRepoState::Rebase,
+        "am" => structs::GitRepoState::ApplyMailbox,
+        _ => {
+            let steps = value.strip_prefix("bisect ~")?.strip_suffix(" steps")?;
+            structs::GitRepoState::Bisect {
+                steps: Some(steps.parse().ok()?),
+            }
+        }
+    };
+    Some(state)
+}
+
+fn segment_kind(value: &str) -> Option<structs::SegmentKind> {
+    match value {
+        "hostname" => Some(structs::SegmentKind::Hostname),
+        "python" => Some(structs::SegmentKind::Python),
+        "git" => Some(structs::SegmentKind::Git),
+        "node" => Some(structs::SegmentKind::Node),
+        "kube" => Some(structs::SegmentKind::Kube),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::record;
+    use super::repo_state;
+    use super::theme_data;
+    use crate::demo;
+    use crate::machine_format;
+    use crate::structs;
+
+    #[test]
+    fn round_trip_test() {
+        let symbols = structs::ThemeSymbols::ascii();
+        for (name, git) in demo::scenarios() {
+            let data = demo::theme_data(git, None);
+            let recording = record(&data, None, None).expect("recorded");
+            let replayed = theme_data(&recording).expect("replayed");
+            assert_eq!(
+                machine_format::format_porcelain(&replayed, &symbols),
+                machine_format::format_porcelain(&data, &symbols),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn sanitize_test() {
+        let mut git = demo::git(
+            demo::head("main"),
+            demo::scenarios().remove(0).1.file_status.expect("status"),
+            (0, 0),
+            None,
+        );
+        git.identity = Some(structs::GitIdentity {
+            name: Some("Jane Doe".to_string()),
+            email: Some("jane@example.com".to_string()),
+        });
+        let mut data = demo::theme_data(git, None);
+        data.hostname = Some("laptop.corp".to_string());
+        data.custom = vec![structs::CustomSegment {
+            name: "aws".to_string(),
+            text: "prod-account".to_string(),
+            color: None,
+        }];
+
+        let recording = record(&data, None, None).expect("recorded");
+        assert!(!recording.contains("Jane"), "{recording}");
+        assert!(!recording.contains("laptop"), "{recording}");
+        assert!(!recording.contains("prod-account"), "{recording}");
+        assert!(recording.contains("user@example.com"), "{recording}");
+
+        let replayed = theme_data(&recording).expect("replayed");
+        assert_eq!(replayed.custom[0].name, "aws");
+        assert_eq!(replayed.custom[0].text, "<redacted>");
+    }
+
+    #[rstest]
+    #[case("merge", Some("merge"))]
+    #[case("bisect", Some("bisect"))]
+    #[case("bisect ~3 steps", Some("bisect ~3 steps"))]
+    #[case("bisect ~x steps", None)]
+    fn repo_state_test(#[case] value: &str, #[case] expected: Option<&str>) {
+        assert_eq!(
+            repo_state(value).map(|v| v.to_string()).as_deref(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Size of a repository for `--git-adaptive`, expensive parts of status are skipped
+//! in big repositories and the prompt is marked as reduced.
+//!
+//! Size is measured on the first visit and cached for a week. Measuring reads only
+//! the index header, reference names and sizes of pack files.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::cache;
+use crate::context;
+use crate::error::MapLog;
+
+static SCALE_CACHE: &str = "repo_scale";
+
+/// Measured size is trusted for this long
+const SCALE_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Untracked files are searched through the whole worktree, it's slow next to a big index
+const UNTRACKED_MAX_INDEX_ENTRIES: usize = 100_000;
+
+/// Ahead/behind walks history, which is slow in a big object database
+const AHEAD_BEHIND_MAX_PACK_BYTES: u64 = 4 << 30;
+
+/// Many references usually come with long history as well
+const AHEAD_BEHIND_MAX_REFS: usize = 50_000;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct RepoScale {
+    pub index_entries: usize,
+    /// Loose and packed references
+    pub refs: usize,
+    /// Total size of pack files
+    pub pack_bytes: u64,
+}
+
+impl RepoScale {
+    pub(crate) fn skips_untracked(&self) -> bool {
+        self.index_entries > UNTRACKED_MAX_INDEX_ENTRIES
+    }
+
+    pub(crate) fn skips_ahead_behind(&self) -> bool {
+        self.pack_bytes > AHEAD_BEHIND_MAX_PACK_BYTES || self.refs > AHEAD_BEHIND_MAX_REFS
+    }
+}
+
+/// Size of the repository from the cache, measured if it's unknown or outdated
+pub(crate) fn repo_scale(git_dir: &Path, common_dir: &Path, ctx: &context::Context) -> RepoScale {
+    let key = cache::key(common_dir);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    if let Some(scale) = cache::get(SCALE_CACHE, &key).and_then(|v| parse_scale(&v, now)) {
+        return scale;
+    }
+
+    let scale = RepoScale {
+        index_entries: index_entries(&git_dir.join("index")).unwrap_or_default(),
+        refs: loose_refs(&common_dir.join("refs")) + packed_refs(common_dir),
+        pack_bytes: pack_bytes(&common_dir.join("objects/pack")),
+    };
+    cache::set(SCALE_CACHE, &key, &format_scale(now, &scale)).ok_or_log(ctx);
+    scale
+}
+
+fn format_scale(measured_at: u64, scale: &RepoScale) -> String {
+    format!(
+        "{measured_at}\t{}\t{}\t{}",
+        scale.index_entries, scale.refs, scale.pack_bytes
+    )
+}
+
+/// Cached size if it isn't outdated
+fn parse_scale(value: &str, now: u64) -> Option<RepoScale> {
+    let mut fields = value.split('\t');
+    let measured_at: u64 = fields.next()?.parse().ok()?;
+    if now.saturating_sub(measured_at) > SCALE_TTL_SECS {
+        return None;
+    }
+    Some(RepoScale {
+        index_entries: fields.next()?.parse().ok()?,
+        refs: fields.next()?.parse().ok()?,
+        pack_bytes: fields.next()?.parse().ok()?,
+    })
+}
+
+/// Number of entries from the index header, the rest of the index isn't read
+pub(crate) fn index_entries(path: &Path) -> Option<usize> {
+    let mut header = [0; 12];
+    fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+    parse_index_header(&header)
+}
+
+/// `DIRC` signature, version and number of entries, all big-endian
+fn parse_index_header(header: &[u8; 12]) -> Option<usize> {
+    if &header[0..4] != b"DIRC" {
+        return None;
+    }
+    let count = u32::from_be_bytes(header[8..12].try_into().ok()?);
+    Some(count as usize)
+}
+
+fn loose_refs(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => loose_refs(&entry.path()),
+            Ok(_) => 1,
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn packed_refs(common_dir: &Path) -> usize {
+    fs::read_to_string(common_dir.join("packed-refs")).map_or(0, |content| {
+        content
+            .lines()
+            .filter(|line| !line.starts_with(['#', '^']))
+            .count()
+    })
+}
+
+fn pack_bytes(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|v| v == "pack"))
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::format_scale;
+    use super::parse_index_header;
+    use super::parse_scale;
+    use super::RepoScale;
+    use super::SCALE_TTL_SECS;
+
+    #[rstest]
+    #[case(*b"DIRC\0\0\0\x02\0\0\x01\x00", Some(256))]
+    #[case(*b"DIRC\0\0\0\x04\0\0\0\0", Some(0))]
+    #[case(*b"XXXX\0\0\0\x02\0\0\x01\x00", None)]
+    fn parse_index_header_test(#[case] header: [u8; 12], #[case] expected: Option<usize>) {
+        assert_eq!(parse_index_header(&header), expected);
+    }
+
+    #[rstest]
+    #[case(1000, Some(10))]
+    #[case(1000 + SCALE_TTL_SECS, Some(10))]
+    #[case(1001 + SCALE_TTL_SECS, None)]
+    fn parse_scale_test(#[case] now: u64, #[case] expected: Option<usize>) {
+        let scale = RepoScale {
+            index_entries: 10,
+            refs: 3,
+            pack_bytes: 4096,
+        };
+        let parsed = parse_scale(&format_scale(1000, &scale), now);
+        assert_eq!(parsed.as_ref().map(|v| v.index_entries), expected);
+        if expected.is_some() {
+            assert_eq!(parsed, Some(scale));
+        }
+    }
+
+    #[rstest]
+    #[case(100_000, 0, 0, false, false)]
+    #[case(100_001, 0, 0, true, false)]
+    #[case(0, 50_001, 0, false, true)]
+    #[case(0, 0, 5 << 30, false, true)]
+    fn thresholds_test(
+        #[case] index_entries: usize,
+        #[case] refs: usize,
+        #[case] pack_bytes: u64,
+        #[case] untracked: bool,
+        #[case] ahead_behind: bool,
+    ) {
+        let scale = RepoScale {
+            index_entries,
+            refs,
+            pack_bytes,
+        };
+        assert_eq!(scale.skips_untracked(), untracked);
+        assert_eq!(scale.skips_ahead_behind(), ahead_behind);
+    }
+}
@@ -0,0 +1,404 @@
+//! Machine readable output of `json` and `porcelain` themes and the daemon protocol.
+//!
+//! Both themes start with `schema_version`, the daemon reports it in `version` response.
+//! Compatibility rules within one schema version:
+//!
+//! - fields are only added, existing ones are never renamed, removed or change their type
+//! - unknown values are `null` in JSON and missing lines in porcelain output
+//! - consumers ignore fields and lines they don't know
+//!
+//! Anything else increments [`SCHEMA_VERSION`].
+
+#[cfg(feature = "json")]
+use std::borrow::Cow;
+#[cfg(feature = "json")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "json")]
+use serde::Deserialize;
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+#[cfg(feature = "json")]
+use crate::structs;
+
+/// Version of machine readable output, incremented on incompatible changes only
+pub(crate) const SCHEMA_VERSION: u64 = 1;
+
+/// Object printed by `json` theme
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Prompt<'a> {
+    pub schema_version: u64,
+    pub last_exit_status: u8,
+    pub jobs: usize,
+    pub cmd_duration: Option<Cow<'a, str>>,
+    /// Nesting level of the shell, zero if unknown
+    pub shell_level: usize,
+    pub vi_command: bool,
+    pub date: String,
+    pub time: String,
+    pub hostname: Option<Cow<'a, str>>,
+    pub username: Option<Cow<'a, str>>,
+    /// Python environment in effect
+    pub python: Option<Cow<'a, str>>,
+    /// Tool managing the environment in effect, e.g. `uv`
+    pub python_manager: Option<String>,
+    /// Stacked python environments from the outermost one
+    pub python_envs: Vec<Cow<'a, str>>,
+    pub git: Option<Git<'a>>,
+    pub slow: Vec<String>,
+    /// Texts of custom segments by their names
+    pub custom: BTreeMap<Cow<'a, str>, Cow<'a, str>>,
+}
+
+/// Git information, `result` of daemon `status` request
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Git<'a> {
+    pub head_info: Option<HeadInfo<'a>>,
+    pub branch_ahead_behind: Option<AheadBehind>,
+    pub file_status: Option<FileStatus>,
+    pub repo_name: Option<Cow<'a, str>>,
+    pub read_only: bool,
+    pub busy: bool,
+    pub shallow: bool,
+    pub identity_mismatch: bool,
+    pub protected: bool,
+    pub large_staged: bool,
+    pub secrets_staged: bool,
+    pub hooks: bool,
+    pub from_env: bool,
+    pub slow: bool,
+    pub reduced: bool,
+    pub inside_git_dir: bool,
+    pub untrusted: bool,
+    pub damaged: bool,
+    pub nested: bool,
+    pub outer_branch: Option<Cow<'a, str>>,
+    pub remotes: Vec<Remote<'a>>,
+    pub pull_request: Option<u64>,
+    pub ci_status: Option<String>,
+    pub today_commits: Option<usize>,
+    pub base_age_days: Option<u64>,
+    pub dirty_age_secs: Option<u64>,
+    pub tag_distance: Option<TagDistance<'a>>,
+    pub identity: Option<Identity<'a>>,
+    pub default_branch: Option<Cow<'a, str>>,
+    pub lfs: Option<Lfs>,
+    pub diff_stat: Option<DiffStat>,
+    pub state: Option<String>,
+    pub relative_path: Option<Cow<'a, str>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct HeadInfo<'a> {
+    pub reference_short: Option<Cow<'a, str>>,
+    pub reference_name: Option<Cow<'a, str>>,
+    pub reference_kind: Option<String>,
+    pub oid: Option<Cow<'a, str>>,
+    pub oid_short: Option<Cow<'a, str>>,
+    pub detached: bool,
+    pub detached_from: Option<Cow<'a, str>>,
+    pub signed: Option<bool>,
+    pub branch_url: Option<Cow<'a, str>>,
+    pub ticket: Option<Ticket<'a>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Ticket<'a> {
+    pub id: Cow<'a, str>,
+    pub name: Cow<'a, str>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct AheadBehind {
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct FileStatus {
+    pub conflict: bool,
+    pub untracked: bool,
+    pub typechange: bool,
+    pub unstaged: bool,
+    pub staged: bool,
+    /// Number of files by category names of [`structs::GitFileCounts::entries`]
+    pub counts: BTreeMap<Cow<'static, str>, usize>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Remote<'a> {
+    pub remote: Cow<'a, str>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct TagDistance<'a> {
+    pub tag: Cow<'a, str>,
+    pub ahead: usize,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Identity<'a> {
+    pub name: Option<Cow<'a, str>>,
+    pub email: Option<Cow<'a, str>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Lfs {
+    pub unpulled: usize,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct DiffStat {
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[cfg(feature = "json")]
+impl<'a> From<&'a structs::ThemeData> for Prompt<'a> {
+    fn from(data: &'a structs::ThemeData) -> Self {
+        Prompt {
+            schema_version: SCHEMA_VERSION,
+            last_exit_status: data.last_exit_status,
+            jobs: data.jobs,
+            cmd_duration: data.cmd_duration.as_deref().map(Cow::from),
+            shell_level: data.shell_level,
+            vi_command: data.vi_command,
+            date: data.datetime.date.to_string(),
+            time: data.datetime.time.to_string(),
+            hostname: data.hostname.as_deref().map(Cow::from),
+            username: data.username.as_deref().map(Cow::from),
+            python: data.python.last().map(|v| Cow::from(&v.name)),
+            python_manager: data.python.last().map(|v| v.manager.to_string()),
+            python_envs: data.python.iter().map(|v| Cow::from(&v.name)).collect(),
+            git: data.git.as_ref().map(Git::from),
+            slow: data.slow.iter().map(|v| v.to_string()).collect(),
+            custom: data
+                .custom
+                .iter()
+                .map(|v| (Cow::from(&v.name), Cow::from(&v.text)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'a> From<&'a structs::GitOutputOptions> for Git<'a> {
+    fn from(git: &'a structs::GitOutputOptions) -> Self {
+        Git {
+            head_info: git.head_info.as_ref().map(|h| HeadInfo {
+                reference_short: h.reference_short.as_deref().map(Cow::from),
+                reference_name: h.reference_name.as_deref().map(Cow::from),
+                reference_kind: h.reference_kind.map(|v| v.to_string()),
+                oid: h.oid.as_deref().map(Cow::from),
+                oid_short: h.oid_short.as_deref().map(Cow::from),
+                detached: h.detached,
+                detached_from: h.detached_from.as_deref().map(Cow::from),
+                signed: h.signed,
+                branch_url: h.branch_url.as_deref().map(Cow::from),
+                ticket: h.ticket.as_ref().map(|t| Ticket {
+                    id: Cow::from(&t.id),
+                    name: Cow::from(&t.name),
+                }),
+            }),
+            branch_ahead_behind: git.branch_ahead_behind.as_ref().map(|b| AheadBehind {
+                ahead: b.ahead,
+                behind: b.behind,
+            }),
+            file_status: git.file_status.as_ref().map(|f| FileStatus {
+                conflict: f.conflict,
+                untracked: f.untracked,
+                typechange: f.typechange,
+                unstaged: f.unstaged,
+                staged: f.staged,
+                counts: f
+                    .counts
+                    .entries()
+                    .into_iter()
+                    .map(|(name, count)| (Cow::from(name), count))
+                    .collect(),
+            }),
+            repo_name: git.repo_name.as_deref().map(Cow::from),
+            read_only: git.read_only,
+            busy: git.busy,
+            shallow: git.shallow,
+            identity_mismatch: git.identity_mismatch,
+            protected: git.protected,
+            large_staged: git.large_staged,
+            secrets_staged: git.secrets_staged,
+            hooks: git.hooks,
+            from_env: git.from_env,
+            slow: git.slow,
+            reduced: git.reduced,
+            inside_git_dir: git.inside_git_dir,
+            untrusted: git.untrusted,
+            damaged: git.damaged,
+            nested: git.nested,
+            outer_branch: git.outer_branch.as_deref().map(Cow::from),
+            remotes: git
+                .remotes
+                .iter()
+                .map(|r| Remote {
+                    remote: Cow::from(&r.remote),
+                    ahead: r.ahead,
+                    behind: r.behind,
+                })
+                .collect(),
+            pull_request: git.pull_request,
+            ci_status: git.ci_status.map(|v| v.to_string()),
+            today_commits: git.today_commits,
+            base_age_days: git.base_age_days,
+            dirty_age_secs: git.dirty_age_secs,
+            tag_distance: git.tag_distance.as_ref().map(|v| TagDistance {
+                tag: Cow::from(&v.tag),
+                ahead: v.ahead,
+            }),
+            identity: git.identity.as_ref().map(|v| Identity {
+                name: v.name.as_deref().map(Cow::from),
+                email: v.email.as_deref().map(Cow::from),
+            }),
+            default_branch: git.default_branch.as_deref().map(Cow::from),
+            lfs: git.lfs.as_ref().map(|v| Lfs {
+                unpulled: v.unpulled,
+            }),
+            diff_stat: git.diff_stat.map(|d| DiffStat {
+                insertions: d.insertions,
+                deletions: d.deletions,
+            }),
+            state: git.state.as_ref().map(|v| v.to_string()),
+            relative_path: git.relative_path.as_ref().map(|v| v.to_string_lossy()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::Git;
+    use super::Prompt;
+    use crate::structs;
+
+    /// Names of fields are part of the schema, renaming one requires a new version
+    #[test]
+    fn field_names_test() {
+        let data = structs::ThemeData {
+            last_exit_status: 0,
+            jobs: 0,
+            shell_level: 0,
+            vi_command: false,
+            cmd_duration: None,
+            datetime: structs::DateTime {
+                date: Box::new("2025-04-12"),
+                time: Box::new("12:00:00"),
+                show_date: true,
+            },
+            hostname: None,
+            username: None,
+            python: Vec::new(),
+            git: Some(structs::GitOutputOptions::default()),
+            repo_path: None,
+            max_width: None,
+            branch_max_width: None,
+            slow: Vec::new(),
+            layout: None,
+            custom: Vec::new(),
+        };
+        let value = serde_json::to_value(Prompt::from(&data)).expect("serializable");
+        let keys = |v: &serde_json::Value| {
+            v.as_object()
+                .expect("object")
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(
+            keys(&value),
+            [
+                "cmd_duration",
+                "custom",
+                "date",
+                "git",
+                "hostname",
+                "jobs",
+                "last_exit_status",
+                "python",
+                "python_envs",
+                "python_manager",
+                "schema_version",
+                "shell_level",
+                "slow",
+                "time",
+                "username",
+                "vi_command",
+            ]
+        );
+        assert_eq!(
+            keys(
+                &serde_json::to_value(Git::from(&structs::GitOutputOptions::default()))
+                    .expect("serializable")
+            ),
+            [
+                "base_age_days",
+                "branch_ahead_behind",
+                "busy",
+                "ci_status",
+                "damaged",
+                "default_branch",
+                "diff_stat",
+                "dirty_age_secs",
+                "file_status",
+                "from_env",
+                "head_info",
+                "hooks",
+                "identity",
+                "identity_mismatch",
+                "inside_git_dir",
+                "large_staged",
+                "lfs",
+                "nested",
+                "outer_branch",
+                "protected",
+                "pull_request",
+                "read_only",
+                "reduced",
+                "relative_path",
+                "remotes",
+                "repo_name",
+                "secrets_staged",
+                "shallow",
+                "slow",
+                "state",
+                "tag_distance",
+                "today_commits",
+                "untrusted",
+            ]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Custom segments defined by Rhai scripts from the configuration file.
+//!
+//! Script gets theme data as variables: `branch`, `ticket`, `oid`, `detached`, `dirty`,
+//! `repo_name`, `python`, `python_manager`, `hostname`, `username`, `last_exit_status`
+//! and `jobs`, missing values are empty strings.
+//! `env(name)` reads an environment variable. Value of the last expression is the segment text,
+//! empty text hides the segment.
+//!
+//! ```toml
+//! [scripts]
+//! user = 'if env("SSH_CONNECTION") != "" { username } else { "" }'
+//! ```
+
+use rhai::Dynamic;
+use rhai::Engine;
+use rhai::Scope;
+
+use crate::context;
+use crate::error::MapLog;
+use crate::structs;
+
+/// Scripts can't take more operations than this, so a broken one doesn't hang the prompt
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Runs scripts returning non-empty segments in the same order
+pub(crate) fn run(
+    scripts: &[(String, String)],
+    data: &structs::ThemeData,
+    ctx: &context::Context,
+) -> Vec<structs::CustomSegment> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.register_fn("env", |name: &str| std::env::var(name).unwrap_or_default());
+
+    let scope = scope(data);
+    scripts
+        .iter()
+        .filter_map(|(name, script)| {
+            let result = engine
+                .eval_with_scope::<Dynamic>(&mut scope.clone(), script)
+                .map_err(|err| format!("script '{name}': {err}"))
+                .map_err(crate::error::Error::from)
+                .ok_or_log(ctx)?;
+            let text = match result.is_unit() {
+                true => String::new(),
+                false => result.to_string(),
+            };
+            (!text.is_empty()).then(|| structs::CustomSegment {
+                name: name.to_string(),
+                text,
+                color: None,
+            })
+        })
+        .collect()
+}
+
+fn scope(data: &structs::ThemeData) -> Scope<'static> {
+    let head_info = data.git.as_ref().and_then(|g| g.head_info.as_ref());
+    let text = |v: Option<&String>| v.cloned().unwrap_or_default();
+
+    let mut scope = Scope::new();
+    scope.push_constant(
+        "branch",
+        text(head_info.and_then(|h| h.reference_short.as_ref())),
+    );
+    scope.push_constant(
+        "ticket",
+        text(head_info.and_then(|h| h.ticket.as_ref()).map(|t| &t.id)),
+    );
+    scope.push_constant("oid", text(head_info.and_then(|h| h.oid.as_ref())));
+    scope.push_constant("detached", head_info.is_some_and(|h| h.detached));
+    scope.push_constant(
+        "dirty",
+        data.git
+            .as_ref()
+            .and_then(|g| g.file_status.as_ref())
+            .is_some_and(|f| f.conflict || f.untracked || f.typechange || f.unstaged || f.staged),
+    );
+    scope.push_constant(
+        "repo_name",
+        text(data.git.as_ref().and_then(|g| g.repo_name.as_ref())),
+    );
+    let python = data.python.last();
+    scope.push_constant("python", text(python.map(|v| &v.name)));
+    scope.push_constant(
+        "python_manager",
+        python.map_or(String::new(), |v| v.manager.to_string()),
+    );
+    scope.push_constant("hostname", text(data.hostname.as_ref()));
+    scope.push_constant("username", text(data.username.as_ref()));
+    scope.push_constant("last_exit_status", data.last_exit_status as i64);
+    scope.push_constant("jobs", data.jobs as i64);
+    scope
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::run;
+    use crate::context;
+    use crate::structs;
+
+    fn theme_data() -> structs::ThemeData {
+        structs::ThemeData {
+            last_exit_status: 2,
+            jobs: 0,
+            shell_level: 0,
+            vi_command: false,
+            cmd_duration: None,
+            datetime: structs::DateTime {
+                date: Box::new(""),
+                time: Box::new(""),
+                show_date: true,
+            },
+            hostname: Some("host.local".to_string()),
+            username: None,
+            python: Vec::new(),
+            git: None,
+            repo_path: None,
+            max_width: None,
+            branch_max_width: None,
+            slow: Vec::new(),
+            layout: None,
+            custom: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn run_test() {
+        let scripts = [
+            ("host", r#"hostname.split(".")[0]"#),
+            (
+                "status",
+                "if last_exit_status > 1 { `exit ${last_exit_status}` }",
+            ),
+            ("empty", "username"),
+            ("broken", "("),
+            ("endless", "loop {}"),
+        ]
+        .map(|(n, s)| (n.to_string(), s.to_string()));
+
+        let segments: Vec<(String, String)> =
+            run(&scripts, &theme_data(), &context::Context::silent())
+                .into_iter()
+                .map(|v| (v.name, v.text))
+                .collect();
+        assert_eq!(
+            segments,
+            [
+                ("host".to_string(), "host".to_string()),
+                ("status".to_string(), "exit 2".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Replaces the running binary with the latest GitHub release, `self-update` subcommand.
+//!
+//! Release assets are named `ilsore-format-<arch>-<os>`, every one comes with
+//! `.sha256` checksum file and optional `.minisig` signature. Checksum is always verified,
+//! signature is required when a minisign public key is set at build time
+//! with `ILSORE_FORMAT_UPDATE_KEY` environment variable.
+
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use sha2::Digest;
+
+use crate::error::Result;
+
+/// GitHub repository with releases
+const REPOSITORY: &str = "eirnym-learn/git-status";
+
+/// Minisign public key of release signatures
+const PUBLIC_KEY: Option<&str> = option_env!("ILSORE_FORMAT_UPDATE_KEY");
+
+/// Release assets are much smaller, anything bigger is rejected
+const MAX_ASSET_SIZE: u64 = 64 * 1024 * 1024;
+
+struct Release {
+    version: String,
+    /// Asset name and download url
+    assets: Vec<(String, String)>,
+}
+
+impl Release {
+    fn asset_url(&self, name: &str) -> Option<&str> {
+        self.assets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, url)| url.as_str())
+    }
+}
+
+/// Checks the latest release and installs it if it's newer, only reports it with `check_only`
+pub(crate) fn self_update(check_only: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release = latest_release()?;
+    if !is_newer(&release.version, current) {
+        println!("{} is up to date", current);
+        return Ok(());
+    }
+    if check_only {
+        println!(
+            "{} is available, current version is {}",
+            release.version, current
+        );
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let binary_url = release
+        .asset_url(&name)
+        .ok_or_else(|| format!("release {} has no binary {name}", release.version))?;
+    let checksum_url = release
+        .asset_url(&format!("{name}.sha256"))
+        .ok_or_else(|| format!("release {} has no checksum for {name}", release.version))?;
+
+    let binary = download(binary_url)?;
+    let checksum = String::from_utf8_lossy(&download(checksum_url)?).to_string();
+    let expected = parse_checksum(&checksum, &name)
+        .ok_or_else(|| format!("checksum of {name} is not found"))?;
+    if sha256_hex(&binary) != expected {
+        return Err(format!("checksum of {name} doesn't match").into());
+    }
+    if let Some(public_key) = PUBLIC_KEY {
+        let signature_url = release
+            .asset_url(&format!("{name}.minisig"))
+            .ok_or_else(|| format!("release {} has no signature for {name}", release.version))?;
+        let signature = String::from_utf8_lossy(&download(signature_url)?).to_string();
+        verify_signature(&binary, &signature, public_key)?;
+    }
+
+    let exe = env::current_exe()?.canonicalize()?;
+    replace_binary(&exe, &binary)?;
+    println!("Updated {} to {}", exe.display(), release.version);
+    Ok(())
+}
+
+fn latest_release() -> Result<Release> {
+    let url = format!("https://api.github.com/repos/{REPOSITORY}/releases/latest");
+    let body = download(&url)?;
+    let value: serde_json::Value =
+        serde_json::from_slice(&body).map_err(|err| format!("invalid release: {err}"))?;
+    let version = value["tag_name"]
+        .as_str()
+        .ok_or("release has no tag")?
+        .trim_start_matches('v')
+        .to_string();
+    let assets = value["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| {
+            let name = v["name"].as_str()?;
+            let url = v["browser_download_url"].as_str()?;
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect();
+    Ok(Release { version, assets })
+}
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .set(
+            "User-Agent",
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        )
+        .call()
+        .map_err(|err| format!("{url}: {err}"))?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_ASSET_SIZE)
+        .read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Release asset of the binary for the current platform
+fn asset_name() -> String {
+    format!(
+        "{}-{}-{}",
+        env!("CARGO_BIN_NAME"),
+        env::consts::ARCH,
+        env::consts::OS
+    )
+}
+
+/// Flag if release version is newer than the current one, versions are compared numerically
+fn is_newer(release: &str, current: &str) -> bool {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split(['.', '-', '+'])
+            .map_while(|p| p.parse().ok())
+            .collect()
+    };
+    parts(release) > parts(current)
+}
+
+/// Checksum of the asset in `sha256sum` format, a file with a single hash is accepted too
+fn parse_checksum(text: &str, name: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        match parts.next().map(|n| n.trim_start_matches('*')) {
+            Some(n) if n != name => None,
+            _ => Some(hash.to_ascii_lowercase()),
+        }
+    })
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    sha2::Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn verify_signature(data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key)
+        .map_err(|err| format!("invalid public key: {err}"))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|err| format!("invalid signature: {err}"))?;
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|err| format!("signature doesn't match: {err}"))?;
+    Ok(())
+}
+
+/// Writes new binary next to the current one and renames it over, so it's replaced at once
+fn replace_binary(exe: &Path, binary: &[u8]) -> Result<()> {
+    let mut tmp_name = exe.as_os_str().to_owned();
+    tmp_name.push(format!(".{}", std::process::id()));
+    let tmp_path = Path::new(&tmp_name);
+    fs::write(tmp_path, binary)?;
+    fs::set_permissions(tmp_path, fs::metadata(exe)?.permissions())?;
+    fs::rename(tmp_path, exe)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::is_newer;
+    use super::parse_checksum;
+    use super::sha256_hex;
+
+    #[rstest]
+    #[case("1.0.1", "1.0.0", true)]
+    #[case("1.10.0", "1.9.3", true)]
+    #[case("2.0.0", "1.99.99", true)]
+    #[case("1.0.0", "1.0.0", false)]
+    #[case("0.9.0", "1.0.0", false)]
+    #[case("1.0.0-rc.1", "1.0.0", false)]
+    fn is_newer_test(#[case] release: &str, #[case] current: &str, #[case] expected: bool) {
+        assert_eq!(is_newer(release, current), expected);
+    }
+
+    #[rstest]
+    #[case("ABC  ilsore-format-x86_64-linux\n", Some("abc"))]
+    #[case("abc *ilsore-format-x86_64-linux", Some("abc"))]
+    #[case("def  other\nabc  ilsore-format-x86_64-linux", Some("abc"))]
+    #[case("abc\n", Some("abc"))]
+    #[case("def  other\n", None)]
+    #[case("", None)]
+    fn parse_checksum_test(#[case] text: &str, #[case] expected: Option<&str>) {
+        assert_eq!(
+            parse_checksum(text, "ilsore-format-x86_64-linux").as_deref(),
+            expected
+        );
+    }
+
+    #[test]
+    fn sha256_hex_test() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
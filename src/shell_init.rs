@@ -0,0 +1,261 @@
+//! Shell snippets calling the binary before every prompt, printed by `init` subcommand.
+//!
+//! Prompts are formatted for zsh, so bash and fish use `ilsore-no-color` theme
+//! with the current folder substituted by the snippet. Two-phase snippet for zsh
+//! shows the quick prompt at once and replaces it when the full one is ready.
+//!
+//! `install` subcommand writes a block loading the snippet into the shell rc file,
+//! the block of a previous install is replaced, so it can be run again after upgrade.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::error::Result;
+
+/// Shells with init snippets
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+const ZSH: &str = r#"autoload -Uz add-zsh-hook add-zle-hook-widget
+typeset -g _ilsore_format_status=0
+_ilsore_format_precmd() {
+    local exit_status=$?
+    _ilsore_format_status=$exit_status
+    PROMPT="$(BIN --last-exit-status $exit_status --jobs ${#jobstates} --shlvl $SHLVL)"
+}
+add-zsh-hook precmd _ilsore_format_precmd
+KEYMAP_HOOK"#;
+
+/// Redraws zsh prompt when vi mode changes, the line starts in insert mode
+const ZSH_KEYMAP: &str = r#"_ilsore_format_keymap() {
+    local keymap=viins
+    [[ $KEYMAP == vicmd ]] && keymap=vicmd
+    PROMPT="$(BIN --last-exit-status $_ilsore_format_status --jobs ${#jobstates} --shlvl $SHLVL --keymap $keymap)"
+    zle reset-prompt
+}
+add-zle-hook-widget keymap-select _ilsore_format_keymap
+"#;
+
+const ZSH_TWO_PHASE: &str = r#"autoload -Uz add-zsh-hook add-zle-hook-widget
+typeset -g _ilsore_format_status=0
+typeset -g _ilsore_format_fd
+_ilsore_format_close() {
+    [[ -n $_ilsore_format_fd ]] || return
+    zle -F $_ilsore_format_fd 2>/dev/null
+    exec {_ilsore_format_fd}<&-
+    _ilsore_format_fd=
+}
+_ilsore_format_refresh() {
+    local prompt
+    if IFS= read -r -d $'\x1e' -u $1 prompt; then
+        PROMPT=$prompt
+        zle reset-prompt
+    fi
+    _ilsore_format_close
+}
+_ilsore_format_precmd() {
+    local exit_status=$?
+    _ilsore_format_status=$exit_status
+    _ilsore_format_close
+    exec {_ilsore_format_fd}< <(BIN --two-phase --last-exit-status $exit_status --jobs ${#jobstates} --shlvl $SHLVL)
+    local prompt
+    IFS= read -r -d $'\x1e' -u $_ilsore_format_fd prompt
+    PROMPT=$prompt
+    zle -F $_ilsore_format_fd _ilsore_format_refresh
+}
+add-zsh-hook precmd _ilsore_format_precmd
+KEYMAP_HOOK"#;
+
+const BASH: &str = r#"_ilsore_format_prompt() {
+    local exit_status=$?
+    local jobs
+    jobs=$(jobs -p | wc -l)
+    PS1="$(BIN --last-exit-status $exit_status --jobs ${jobs// /} --shlvl $SHLVL --theme-name ilsore-no-color)"
+    PS1="${PS1//%~/\\w} "
+}
+PROMPT_COMMAND="_ilsore_format_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+"#;
+
+const FISH: &str = r#"function fish_prompt
+    set -l exit_status $status
+    set -l keymap viins
+    if test "$fish_key_bindings" = fish_vi_key_bindings; and test "$fish_bind_mode" = default
+        set keymap vicmd
+    end
+    set -l prompt (BIN --last-exit-status $exit_status --jobs (count (jobs -p)) --shlvl $SHLVL --keymap $keymap --theme-name ilsore-no-color)
+    string replace -- '%~' (prompt_pwd) $prompt
+    echo -n ' '
+end
+"#;
+
+/// Snippet for the shell calling binary at the path
+pub(crate) fn snippet(shell: Shell, two_phase: bool, binary: &Path) -> Result<String> {
+    let binary = binary.to_string_lossy();
+    let (template, binary) = match (shell, two_phase) {
+        (Shell::Zsh, false) => (ZSH, quote(&binary, "'\\''")),
+        (Shell::Zsh, true) => (ZSH_TWO_PHASE, quote(&binary, "'\\''")),
+        (Shell::Bash, false) => (BASH, quote(&binary, "'\\''")),
+        (Shell::Fish, false) => (FISH, quote(&binary, "\\'")),
+        (_, true) => return Err("two-phase prompt is supported for zsh only".into()),
+    };
+    Ok(template
+        .replace("KEYMAP_HOOK", ZSH_KEYMAP)
+        .replace("BIN", &binary))
+}
+
+/// Single quotes text, `escaped` is used for single quotes inside of it
+fn quote(text: &str, escaped: &str) -> String {
+    format!("'{}'", text.replace('\'', escaped))
+}
+
+/// First line of the block written by `install`
+const BLOCK_START: &str = "# >>> ilsore-format >>>";
+
+/// Last line of the block written by `install`
+const BLOCK_END: &str = "# <<< ilsore-format <<<";
+
+/// Default rc file of the shell, `None` if home folder is unknown
+pub(crate) fn rc_path(shell: Shell) -> Option<PathBuf> {
+    let home = PathBuf::from(env::var_os("HOME")?);
+    let path = match shell {
+        Shell::Zsh => env::var_os("ZDOTDIR")
+            .map_or(home, PathBuf::from)
+            .join(".zshrc"),
+        Shell::Bash => home.join(".bashrc"),
+        Shell::Fish => env::var_os("XDG_CONFIG_HOME")
+            .map_or_else(|| home.join(".config"), PathBuf::from)
+            .join("fish/config.fish"),
+    };
+    Some(path)
+}
+
+/// Adds a block loading the snippet of the binary into the rc file
+pub(crate) fn install(shell: Shell, two_phase: bool, binary: &Path, rc: &Path) -> Result<()> {
+    // unsupported combinations fail before the rc file is touched
+    snippet(shell, two_phase, binary)?;
+
+    let block = install_block(shell, two_phase, binary);
+    let content = match fs::read_to_string(rc) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(parent) = rc.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(rc, with_block(&content, &block))?;
+    Ok(())
+}
+
+/// Block evaluating output of `init` subcommand, so the snippet is updated with the binary
+fn install_block(shell: Shell, two_phase: bool, binary: &Path) -> String {
+    let binary = binary.to_string_lossy();
+    let flags = match two_phase {
+        true => " --two-phase",
+        false => "",
+    };
+    let line = match shell {
+        Shell::Zsh => format!("eval \"$({} init zsh{flags})\"", quote(&binary, "'\\''")),
+        Shell::Bash => format!("eval \"$({} init bash{flags})\"", quote(&binary, "'\\''")),
+        Shell::Fish => format!("{} init fish{flags} | source", quote(&binary, "\\'")),
+    };
+    format!("{BLOCK_START}\n{line}\n{BLOCK_END}\n")
+}
+
+/// Replaces the block in the content or appends it to the end
+fn with_block(content: &str, block: &str) -> String {
+    let range = content.find(BLOCK_START).and_then(|start| {
+        let end = content[start..].find(BLOCK_END)? + start + BLOCK_END.len();
+        // trailing new line belongs to the block
+        let end = match content[end..].starts_with('\n') {
+            true => end + 1,
+            false => end,
+        };
+        Some(start..end)
+    });
+    match range {
+        Some(range) => format!(
+            "{}{block}{}",
+            &content[..range.start],
+            &content[range.end..]
+        ),
+        None if content.is_empty() || content.ends_with('\n') => format!("{content}{block}"),
+        None => format!("{content}\n{block}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use rstest::rstest;
+
+    use super::install_block;
+    use super::snippet;
+    use super::with_block;
+    use super::Shell;
+
+    #[rstest]
+    #[case(
+        Shell::Zsh,
+        "'/opt/it'\\''s/ilsore-format' --last-exit-status $exit_status --jobs ${#jobstates} --shlvl $SHLVL"
+    )]
+    #[case(
+        Shell::Zsh,
+        "'/opt/it'\\''s/ilsore-format' --last-exit-status $_ilsore_format_status --jobs ${#jobstates} --shlvl $SHLVL --keymap $keymap"
+    )]
+    #[case(
+        Shell::Bash,
+        "'/opt/it'\\''s/ilsore-format' --last-exit-status $exit_status --jobs ${jobs// /} --shlvl $SHLVL"
+    )]
+    #[case(
+        Shell::Fish,
+        "('/opt/it\\'s/ilsore-format' --last-exit-status $exit_status --jobs (count (jobs -p)) --shlvl $SHLVL --keymap $keymap"
+    )]
+    fn snippet_test(#[case] shell: Shell, #[case] expected: &str) {
+        let snippet = snippet(shell, false, Path::new("/opt/it's/ilsore-format")).expect("snippet");
+        assert!(snippet.contains(expected), "{snippet}");
+    }
+
+    #[test]
+    fn two_phase_snippet_test() {
+        let path = Path::new("/bin/ilsore-format");
+        let zsh = snippet(Shell::Zsh, true, path).expect("zsh snippet");
+        assert!(zsh.contains("'/bin/ilsore-format' --two-phase"), "{zsh}");
+        assert!(zsh.contains("keymap-select _ilsore_format_keymap"), "{zsh}");
+        // phases are read up to the separator, it's escaped to keep the snippet printable
+        let separator = format!("$'\\x{:02x}'", u32::from(crate::output::PHASE_END));
+        assert_eq!(zsh.matches(&separator).count(), 2, "{zsh}");
+        assert!(!zsh.contains(crate::output::PHASE_END), "{zsh}");
+        assert!(snippet(Shell::Bash, true, path).is_err());
+    }
+
+    #[rstest]
+    #[case(Shell::Zsh, "eval \"$('/opt/it'\\''s/ilsore-format' init zsh)\"")]
+    #[case(Shell::Bash, "eval \"$('/opt/it'\\''s/ilsore-format' init bash)\"")]
+    #[case(Shell::Fish, "'/opt/it\\'s/ilsore-format' init fish | source")]
+    fn install_block_test(#[case] shell: Shell, #[case] expected: &str) {
+        let block = install_block(shell, false, Path::new("/opt/it's/ilsore-format"));
+        assert!(block.contains(expected), "{block}");
+    }
+
+    #[rstest]
+    #[case("", "B\n")]
+    #[case("alias a=b", "alias a=b\nB\n")]
+    #[case("alias a=b\n", "alias a=b\nB\n")]
+    #[case(
+        "a\n# >>> ilsore-format >>>\nold\n# <<< ilsore-format <<<\nb\n",
+        "a\nB\nb\n"
+    )]
+    #[case("# >>> ilsore-format >>>\nold\n# <<< ilsore-format <<<", "B\n")]
+    fn with_block_test(#[case] content: &str, #[case] expected: &str) {
+        assert_eq!(with_block(content, "B\n"), expected);
+    }
+}
@@ -0,0 +1,255 @@
+//! Local log of prompt latency per repository, appended with `--stats`
+//! and summarized by `stats` subcommand. Nothing leaves the machine.
+//!
+//! Every line is `<unix time>\t<repository>\t<latency us>\t<index entries>\t<untracked>`,
+//! the last two help to decide if untracked scanning or other options are worth disabling.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path;
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::args;
+use crate::cache;
+use crate::error::Result;
+#[cfg(feature = "git")]
+use crate::git_utils;
+#[cfg(feature = "git")]
+use crate::repo_scale;
+use crate::structs;
+
+/// Statistics log, `stats.tsv` in the cache directory
+pub(crate) fn log_path() -> Option<path::PathBuf> {
+    cache::cache_dir().map(|v| v.join("stats.tsv"))
+}
+
+#[derive(Debug, PartialEq)]
+struct Record {
+    repo: String,
+    latency_us: u64,
+    index_entries: Option<usize>,
+    untracked: bool,
+}
+
+/// Appends the run to the log, runs outside of repositories or without git segment aren't
+/// recorded
+pub(crate) fn append(
+    args: &args::Args,
+    options: &structs::GetGitInfoOptions,
+    latency: Duration,
+) -> Result<()> {
+    if args.disable_git {
+        return Ok(());
+    }
+    let Some((root, index_entries)) = repository(options)? else {
+        return Ok(());
+    };
+    let record = Record {
+        repo: root.to_string_lossy().to_string(),
+        latency_us: latency.as_micros() as u64,
+        index_entries,
+        untracked: !args.git_exclude_untracked,
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let path = log_path().ok_or("cache directory is unknown")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // a single short write to a file in append mode doesn't interleave with concurrent prompts
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(format_record(timestamp, &record).as_bytes())?;
+    Ok(())
+}
+
+/// Root of the repository of the run with number of entries of its index, the index header
+/// is read only as the shell waits for the process to exit
+#[cfg(feature = "git")]
+fn repository(
+    options: &structs::GetGitInfoOptions,
+) -> Result<Option<(path::PathBuf, Option<usize>)>> {
+    let Some(root) = git_utils::git_subfolder(options)? else {
+        return Ok(None);
+    };
+    let root = root.canonicalize()?;
+    let index_entries = repo_scale::index_entries(&git_utils::git_dir(&root).join("index"));
+    Ok(Some((root, index_entries)))
+}
+
+/// Repositories aren't known without git support, so nothing is recorded
+#[cfg(not(feature = "git"))]
+fn repository(
+    _options: &structs::GetGitInfoOptions,
+) -> Result<Option<(path::PathBuf, Option<usize>)>> {
+    Ok(None)
+}
+
+fn format_record(timestamp: u64, record: &Record) -> String {
+    format!(
+        "{timestamp}\t{}\t{}\t{}\t{}\n",
+        record.repo.replace(['\t', '\n', '\r'], " "),
+        record.latency_us,
+        record
+            .index_entries
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
+        record.untracked as u8,
+    )
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let mut fields = line.split('\t').skip(1);
+    let repo = fields.next()?.to_string();
+    let latency_us = fields.next()?.parse().ok()?;
+    let index_entries = fields.next()?.parse().ok();
+    let untracked = fields.next()? == "1";
+    Some(Record {
+        repo,
+        latency_us,
+        index_entries,
+        untracked,
+    })
+}
+
+/// Summary of the log with p50 and p95 latency per repository, the slowest first
+pub(crate) fn report(path: &Path) -> Result<String> {
+    let content = match fs::read_to_string(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(format!(
+                "No statistics in {}, run prompt with --stats to record them\n",
+                path.display()
+            ))
+        }
+        result => result?,
+    };
+    Ok(format_report(content.lines().filter_map(parse_record)))
+}
+
+fn format_report(records: impl Iterator<Item = Record>) -> String {
+    let mut repos: BTreeMap<String, Vec<Record>> = BTreeMap::new();
+    for record in records {
+        repos.entry(record.repo.clone()).or_default().push(record);
+    }
+
+    let mut rows: Vec<_> = repos
+        .into_iter()
+        .map(|(repo, records)| {
+            let mut latencies: Vec<u64> = records.iter().map(|r| r.latency_us).collect();
+            latencies.sort_unstable();
+            // the latest run tells the current size and options
+            let last = records.last();
+            (
+                repo,
+                latencies.len(),
+                percentile(&latencies, 50),
+                percentile(&latencies, 95),
+                last.and_then(|r| r.index_entries),
+                last.is_some_and(|r| r.untracked),
+            )
+        })
+        .collect();
+    rows.sort_by(|a, b| b.3.cmp(&a.3).then_with(|| a.0.cmp(&b.0)));
+
+    let mut result = format!(
+        "{:>6} {:>9} {:>9} {:>8} {:>9}  {}\n",
+        "runs", "p50", "p95", "files", "untracked", "repository"
+    );
+    for (repo, runs, p50, p95, files, untracked) in rows {
+        result.push_str(&format!(
+            "{runs:>6} {:>7.1}ms {:>7.1}ms {:>8} {:>9}  {repo}\n",
+            p50 as f64 / 1000.0,
+            p95 as f64 / 1000.0,
+            files.map(|v| v.to_string()).unwrap_or_else(|| "-".into()),
+            if untracked { "yes" } else { "no" },
+        ));
+    }
+    result
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::format_record;
+    use super::format_report;
+    use super::parse_record;
+    use super::percentile;
+    use super::Record;
+
+    #[rstest]
+    #[case(&[], 50, 0)]
+    #[case(&[7], 95, 7)]
+    #[case(&[1, 2, 3, 4], 50, 2)]
+    #[case(&[1, 2, 3, 4], 95, 4)]
+    #[case(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20], 95, 19)]
+    fn percentile_test(#[case] sorted: &[u64], #[case] percent: usize, #[case] expected: u64) {
+        assert_eq!(percentile(sorted, percent), expected);
+    }
+
+    #[rstest]
+    #[case(Some(120), true)]
+    #[case(None, false)]
+    fn record_round_trip_test(#[case] index_entries: Option<usize>, #[case] untracked: bool) {
+        let record = Record {
+            repo: "/src/repo".to_string(),
+            latency_us: 1500,
+            index_entries,
+            untracked,
+        };
+        let line = format_record(1700000000, &record);
+        assert_eq!(parse_record(line.trim_end()), Some(record));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("1700000000\t/src/repo")]
+    #[case("1700000000\t/src/repo\tslow\t1\t1")]
+    fn parse_record_invalid_test(#[case] line: &str) {
+        assert_eq!(parse_record(line), None);
+    }
+
+    #[test]
+    fn format_report_test() {
+        let record = |repo: &str, latency_us| Record {
+            repo: repo.to_string(),
+            latency_us,
+            index_entries: Some(10),
+            untracked: repo == "/b",
+        };
+        assert_eq!(
+            format_report(
+                [
+                    record("/a", 1000),
+                    record("/b", 20000),
+                    record("/a", 3000),
+                    record("/b", 40000),
+                ]
+                .into_iter()
+            ),
+            concat!(
+                "  runs       p50       p95    files untracked  repository\n",
+                "     2    20.0ms    40.0ms       10       yes  /b\n",
+                "     2     1.0ms     3.0ms       10        no  /a\n",
+            )
+        );
+    }
+}
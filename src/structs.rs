@@ -1,4 +1,44 @@
+use std::borrow::Cow;
 use std::path;
+use std::time::Duration;
+
+use crate::collect;
+use crate::context;
+use crate::error::Result;
+
+/// Values of git options by their configuration names, later values win over earlier ones
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct OptionLayers<'a> {
+    /// `[options]` table of the configuration file, below git configuration
+    pub file: &'a [(String, String)],
+    /// Explicit command line arguments and `--set`, above environment variables
+    pub cli: &'a [(String, String)],
+}
+
+/// Source of an effective option value, from the lowest precedence to the highest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OptionLayer {
+    Default,
+    ConfigFile,
+    GitConfig,
+    Environment,
+    CommandLine,
+}
+
+impl std::fmt::Display for OptionLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionLayer::Default => f.write_str("default"),
+            OptionLayer::ConfigFile => f.write_str("config file"),
+            OptionLayer::GitConfig => f.write_str("git config"),
+            OptionLayer::Environment => f.write_str("environment"),
+            OptionLayer::CommandLine => f.write_str("command line"),
+        }
+    }
+}
+
+/// Effective values of options with their layers in order of resolution
+pub(crate) type OptionTrace = Vec<(&'static str, String, OptionLayer)>;
 
 /// Options for git status reporter
 #[derive(Debug)]
@@ -9,37 +49,453 @@ pub(crate) struct GetGitInfoOptions<'a> {
     /// Reference name to ask information for
     pub reference_name: &'a str,
 
+    /// Flag if `.git` should be searched above the filesystem of the start folder
+    pub discovery_across_filesystem: bool,
+
+    /// Flag if current folder should be taken from `$PWD` keeping symlinks it's reached through
+    pub logical_path: bool,
+
+    /// Flag if repository and index given by git to hooks in `GIT_DIR` and `GIT_INDEX_FILE`
+    /// should be used
+    pub use_git_env: bool,
+
     /// Flag if git status should include submodules information
     pub include_submodules: bool,
 
     /// Flag if git status should include untracked files
     pub include_untracked: bool,
 
+    /// Flag if git status should detect renames, it costs time
+    pub detect_renames: bool,
+
     /// Flag if git status should do soft refresh
     pub refresh_status: bool,
 
     /// Flag if git status should include ahead/behind information
     pub include_ahead_behind: bool,
 
+    /// Flag if untracked files and ahead/behind should be skipped in big repositories
+    pub adaptive: bool,
+
+    /// Flag if ahead/behind should be computed while an operation is in progress
+    pub ahead_behind_during_operation: bool,
+
+    /// Flag if tracking branch resolution should be cached on disk
+    pub cache_upstream: bool,
+
+    /// Flag if Git LFS usage and un-pulled pointers should be reported
+    pub include_lfs: bool,
+
+    /// Flag if HEAD commit should be checked for a signature
+    pub include_signature: bool,
+
+    /// Flag if custom hooks path or active local hooks should be reported
+    pub include_hooks: bool,
+
+    /// Pattern `user.email` is expected to match, `ilsore-format.expected-email` overrides it
+    pub expected_email: Option<&'a str>,
+
+    /// Flag if ticket id should be extracted from branch name
+    pub include_ticket: bool,
+
+    /// Regular expression matching ticket id, `ilsore-format.ticket-pattern` overrides it
+    pub ticket_pattern: &'a str,
+
+    /// Flag if branch matching a protected pattern should be marked
+    pub include_protected: bool,
+
+    /// Comma separated patterns of protected branches, `*` matches within a path component
+    pub protected_branches: &'a str,
+
+    /// Flag if staged files bigger than `large_file_mb` should be marked
+    pub include_large_files: bool,
+
+    /// Size of a staged file in megabytes which is worth a warning
+    pub large_file_mb: usize,
+
+    /// Flag if staged files looking like secrets should be marked
+    pub include_secrets: bool,
+
+    /// Comma separated patterns of secret file names, a pattern with `/` matches the whole path
+    pub secret_patterns: &'a str,
+
+    /// Time budget to search staged contents for private keys, zero checks names only
+    pub secret_scan_ms: usize,
+
+    /// Delay before checking index lock once again, zero disables retry
+    pub lock_retry_ms: usize,
+
     /// Flag if git status should include workdir check
     pub include_workdir_stats: bool,
+
+    /// Flag if git status should count only files under the start folder
+    pub cwd_scope: bool,
+
+    /// Flag if path of the start folder relative to repository root should be included
+    pub include_relative_path: bool,
+
+    /// Flag if repository name should be included
+    pub include_repo_name: bool,
+
+    /// Flag if branch should be linked to the remote branch web page
+    pub include_hyperlink: bool,
+
+    /// Flag if local branch containing detached HEAD should be searched
+    pub include_detached_containing: bool,
+
+    /// Maximum number of commits to walk per branch looking for detached HEAD
+    pub containing_walk_limit: usize,
+
+    /// Number of characters to show for commit id
+    pub short_oid_len: usize,
+
+    /// Flag if commit id should be extended to be unambiguous
+    pub short_oid_unique: bool,
+
+    /// Flag if inserted and deleted lines of the worktree against HEAD should be counted
+    pub include_diff_stat: bool,
+
+    /// Maximum number of changed files to count lines of
+    pub diff_stat_max_files: usize,
+
+    /// Flag if divergence from default branch of every remote should be computed
+    pub include_remotes: bool,
+
+    /// Flag if repository nested in another one should be marked
+    pub include_nested: bool,
+
+    /// Flag if branch of the outer repository should be shown for a nested one
+    pub include_outer_branch: bool,
+
+    /// Flag if cached open pull request of the branch should be shown
+    pub include_pull_request: bool,
+
+    /// Flag if cached CI status of HEAD should be shown
+    pub include_ci_status: bool,
+
+    /// Flag if commits of the author made today on HEAD should be counted
+    pub include_today_commits: bool,
+
+    /// Flag if age of merge base with the tracking branch should be shown
+    pub include_base_age: bool,
+
+    /// Merge base younger than this number of days isn't shown
+    pub base_age_min_days: usize,
+
+    /// Flag if age of the oldest uncommitted change should be shown
+    pub include_dirty_age: bool,
+
+    /// Flag if number of commits after the latest reachable tag should be shown
+    pub include_tag_distance: bool,
+
+    /// Maximum number of commits to walk from HEAD looking for a tag
+    pub tag_walk_limit: usize,
+
+    /// Flag if effective `user.name` and `user.email` should be shown
+    pub include_identity: bool,
+
+    /// Flag if default branch of the remote should be resolved to mark it
+    pub include_default_branch: bool,
+
+    /// Flag if `ilsore-format.*` options of the repository configuration are applied
+    pub repo_config: bool,
+
+    /// Option values of layers other than git configuration and environment
+    pub option_layers: OptionLayers<'a>,
+
+    /// Time budget for HEAD and worktree status each, `None` waits until they finish
+    pub timeout: Option<Duration>,
+
+    /// Stops waiting for HEAD and worktree status, e.g. on Ctrl-C
+    pub cancel: collect::CancelToken,
+
+    /// How errors are reported
+    pub context: context::Context,
+}
+
+/// Source of git information, missing if git support isn't built
+pub(crate) trait GitInfoProvider: Sync {
+    /// Cheap check if start folder is inside of a repository
+    fn is_inside_repo(&self, options: &GetGitInfoOptions) -> bool;
+
+    /// Branch or commit id read from HEAD file only
+    fn head_from_file(&self, options: &GetGitInfoOptions) -> Option<GitOutputOptions>;
+
+    /// Full status of the repository in start folder
+    fn status(&self, options: &GetGitInfoOptions) -> Result<GitOutputOptions>;
+
+    /// Effective git options of the repository in start folder with their layers
+    fn option_trace(&self, options: &GetGitInfoOptions) -> Result<OptionTrace>;
+
+    /// References, status entries and configuration git information is made of
+    #[cfg(feature = "json")]
+    fn raw_data(&self, options: &GetGitInfoOptions) -> Result<GitRawData>;
+}
+
+/// Data read from the repository before it's turned into [`GitOutputOptions`], recorded
+/// for bug reports
+#[cfg(feature = "json")]
+#[derive(Debug, Default)]
+pub(crate) struct GitRawData {
+    /// Reference names with their targets, HEAD first, symbolic ones as `ref: <name>`
+    pub refs: Vec<(String, String)>,
+    /// Status entries as two letter codes like `git status --short` with their paths
+    pub status: Vec<(String, String)>,
+    /// Configuration values the prompt reads, in the order of their files
+    pub config: Vec<(String, String)>,
 }
 
 /// Data to be passed to theme processor
 pub(crate) struct ThemeData {
     pub last_exit_status: u8,
+    /// Number of background jobs of the shell
+    pub jobs: usize,
+    /// Humanized duration of the last command if it took long enough
+    pub cmd_duration: Option<String>,
+    /// Nesting level of the shell, `SHLVL`, zero if unknown
+    pub shell_level: usize,
+    /// Line editor is in vi command mode
+    pub vi_command: bool,
     pub datetime: DateTime,
     pub hostname: Option<String>,
     pub username: Option<String>,
-    pub python: Option<String>,
+    /// Active python environments, from the outermost one to the one in effect
+    pub python: Vec<PythonEnv>,
     pub git: Option<GitOutputOptions>,
+    /// Truncated path of the current folder relative to repository root
+    pub repo_path: Option<String>,
+    pub max_width: Option<usize>,
+    pub branch_max_width: Option<usize>,
+    /// Segments skipped because they didn't finish in time
+    pub slow: Vec<SegmentKind>,
+    /// Order and colors of prompt segments from user theme
+    pub layout: Option<ThemeLayout>,
+    /// Custom segments from environment variables and user scripts
+    pub custom: Vec<CustomSegment>,
 }
 
-#[derive(Debug)]
+/// Segment defined in the configuration file
+#[derive(Debug, PartialEq, Hash)]
+pub(crate) struct CustomSegment {
+    pub name: String,
+    pub text: String,
+    /// Color for themes with colors, theme default if not set
+    pub color: Option<&'static str>,
+}
+
+/// Segments of the first prompt line
+#[derive(Debug, Clone, Copy, PartialEq, enum_map::Enum)]
+pub(crate) enum PromptSegment {
+    DateTime,
+    UserHost,
+    Python,
+    Git,
+    Jobs,
+    CmdDuration,
+    LastStatus,
+    ShellLevel,
+    ViMode,
+}
+
+impl PromptSegment {
+    /// Segments compiled into the binary
+    pub(crate) const ALL: &'static [PromptSegment] = &[
+        PromptSegment::DateTime,
+        PromptSegment::UserHost,
+        PromptSegment::Python,
+        PromptSegment::Git,
+        PromptSegment::Jobs,
+        PromptSegment::CmdDuration,
+        PromptSegment::LastStatus,
+        PromptSegment::ShellLevel,
+        PromptSegment::ViMode,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            PromptSegment::DateTime => "date_time",
+            PromptSegment::UserHost => "user_host",
+            PromptSegment::Python => "python",
+            PromptSegment::Git => "git",
+            PromptSegment::Jobs => "jobs",
+            PromptSegment::CmdDuration => "cmd_duration",
+            PromptSegment::LastStatus => "last_status",
+            PromptSegment::ShellLevel => "shell_level",
+            PromptSegment::ViMode => "vi_mode",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|v| v.name() == name)
+    }
+}
+
+/// Segment order and colors overridden by user theme
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "color"), allow(dead_code))]
+pub(crate) struct ThemeLayout {
+    /// Segments and groups to show, theme's own order if not set. Missing prompt
+    /// segments are hidden, missing custom ones follow the rest unless the order
+    /// has `custom` in it
+    pub order: Option<Vec<LayoutItem>>,
+    pub colors: enum_map::EnumMap<PromptSegment, Option<&'static str>>,
+}
+
+/// Segment of the layout by its name in the configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SegmentName {
+    Prompt(PromptSegment),
+    Custom(String),
+    /// Custom segments the layout doesn't name, `custom`
+    OtherCustom,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LayoutItem {
+    Segment(SegmentName),
+    Group(SegmentGroup),
+}
+
+impl LayoutItem {
+    pub(crate) const fn prompt(kind: PromptSegment) -> Self {
+        LayoutItem::Segment(SegmentName::Prompt(kind))
+    }
+
+    /// Segments of the item, the one segment or members of the group
+    pub(crate) fn names(&self) -> &[SegmentName] {
+        match self {
+            LayoutItem::Segment(name) => std::slice::from_ref(name),
+            LayoutItem::Group(group) => &group.segments,
+        }
+    }
+}
+
+/// Segments shown together between prefix and suffix, joined by the separator instead
+/// of theme's own one. The group is hidden with all of its segments
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "color"), allow(dead_code))]
+pub(crate) struct SegmentGroup {
+    pub segments: Vec<SegmentName>,
+    pub separator: &'static str,
+    pub prefix: &'static str,
+    pub suffix: &'static str,
+    /// Color for themes with colors, color of the first shown segment if not set
+    pub color: Option<&'static str>,
+}
+
+/// Segment to render for the layout
+#[derive(Clone, Copy)]
+pub(crate) enum ShownSegment<'a> {
+    Prompt(PromptSegment),
+    Custom(&'a CustomSegment),
+}
+
+/// Rendered segments in the layout order
+pub(crate) enum Arranged<'a, T> {
+    Segment(T),
+    Group(&'a SegmentGroup, Vec<T>),
+}
+
+impl ThemeData {
+    pub(crate) fn is_slow(&self, segment: SegmentKind) -> bool {
+        self.slow.contains(&segment)
+    }
+
+    /// Segments rendered by the theme in order of the user layout, in the default order
+    /// without it. Segments rendered as `None` are skipped and groups without any
+    /// rendered segment are dropped
+    pub(crate) fn arranged<'a, T>(
+        &'a self,
+        default: &'a [LayoutItem],
+        mut render: impl FnMut(ShownSegment<'a>) -> Option<T>,
+    ) -> Vec<Arranged<'a, T>> {
+        let order = self
+            .layout
+            .as_ref()
+            .and_then(|v| v.order.as_deref())
+            .unwrap_or(default);
+        let named: Vec<&SegmentName> = order.iter().flat_map(LayoutItem::names).collect();
+        let is_named = |custom: &CustomSegment| {
+            named
+                .iter()
+                .any(|v| matches!(v, SegmentName::Custom(name) if *name == custom.name))
+        };
+        let mut expand = |name: &SegmentName| -> Vec<T> {
+            match name {
+                SegmentName::Prompt(kind) => {
+                    render(ShownSegment::Prompt(*kind)).into_iter().collect()
+                }
+                SegmentName::Custom(name) => self
+                    .custom
+                    .iter()
+                    .filter(|v| v.name == *name)
+                    .filter_map(|v| render(ShownSegment::Custom(v)))
+                    .collect(),
+                SegmentName::OtherCustom => self
+                    .custom
+                    .iter()
+                    .filter(|v| !is_named(v))
+                    .filter_map(|v| render(ShownSegment::Custom(v)))
+                    .collect(),
+            }
+        };
+
+        let mut arranged = Vec::new();
+        for item in order {
+            match item {
+                LayoutItem::Segment(name) => {
+                    arranged.extend(expand(name).into_iter().map(Arranged::Segment))
+                }
+                LayoutItem::Group(group) => {
+                    let shown: Vec<T> = group.segments.iter().flat_map(&mut expand).collect();
+                    if !shown.is_empty() {
+                        arranged.push(Arranged::Group(group, shown));
+                    }
+                }
+            }
+        }
+        if !named.contains(&&SegmentName::OtherCustom) {
+            let other = expand(&SegmentName::OtherCustom);
+            arranged.extend(other.into_iter().map(Arranged::Segment));
+        }
+        arranged
+    }
+
+    /// Active python environments joined like `base>proj`, `None` without any
+    pub(crate) fn python_text(&self, symbols: &ThemeSymbols) -> Option<String> {
+        let names: Vec<_> = self.python.iter().map(|v| v.name.as_str()).collect();
+        (!names.is_empty()).then(|| names.join(symbols.python_separator))
+    }
+}
+
+/// Segments collected in background
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub(crate) enum SegmentKind {
+    Hostname,
+    Python,
+    Git,
+    Node,
+    Kube,
+}
+
+impl std::fmt::Display for SegmentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SegmentKind::Hostname => f.write_str("hostname"),
+            SegmentKind::Python => f.write_str("python"),
+            SegmentKind::Git => f.write_str("git"),
+            SegmentKind::Node => f.write_str("node"),
+            SegmentKind::Kube => f.write_str("kube"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct ThemeSymbols {
     pub git_branch: &'static str,
     pub git_has_no_upstream: &'static str,
     pub git_branch_detached: &'static str,
+    pub git_default_branch: &'static str,
     pub git_is_ahead: &'static str,
     pub git_is_behind: &'static str,
     pub git_has_conflict: &'static str,
@@ -47,47 +503,464 @@ pub(crate) struct ThemeSymbols {
     pub git_has_typechange: &'static str,
     pub git_has_unstaged: &'static str,
     pub git_has_staged: &'static str,
+    pub git_read_only: &'static str,
+    pub git_busy: &'static str,
+    pub git_shallow: &'static str,
+    pub git_lfs: &'static str,
+    pub git_lfs_unpulled: &'static str,
+    pub git_signed: &'static str,
+    pub git_unsigned: &'static str,
+    pub git_identity_mismatch: &'static str,
+    pub git_protected: &'static str,
+    pub git_large_staged: &'static str,
+    pub git_secrets_staged: &'static str,
+    pub git_hooks: &'static str,
+    pub git_from_env: &'static str,
+    pub git_untrusted: &'static str,
+    pub git_damaged: &'static str,
+    pub git_reduced: &'static str,
+    pub git_inside_git_dir: &'static str,
+    pub git_nested: &'static str,
+    pub git_ci_passed: &'static str,
+    pub git_ci_failed: &'static str,
+    pub git_ci_running: &'static str,
+    pub jobs: &'static str,
+    pub cmd_duration: &'static str,
+    /// Vi command mode of the line editor
+    pub vi_command: &'static str,
+    /// Before the nesting level of the shell
+    pub shell_level: &'static str,
+    pub slow: &'static str,
+    /// Separator between stacked python environments
+    pub python_separator: &'static str,
+    /// Separator between segments of different colors
+    pub separator: &'static str,
+    /// Separator between segments of the same color
+    pub separator_thin: &'static str,
+    pub cap_left: &'static str,
+    pub cap_right: &'static str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Hash)]
 pub(crate) struct GitOutputOptions {
     pub head_info: Option<GitHeadInfo>,
     pub file_status: Option<GitFileStatus>,
     pub branch_ahead_behind: Option<GitBranchAheadBehind>,
+    pub state: Option<GitRepoState>,
+    /// Path of the start folder relative to repository root, `None` for the root itself
+    pub relative_path: Option<path::PathBuf>,
+    /// Repository name from origin url or worktree folder name
+    pub repo_name: Option<String>,
+    /// Worktree or git folder can't be written to
+    pub read_only: bool,
+    /// Another git process holds the index lock, file status is skipped
+    pub busy: bool,
+    /// Shallow or partial clone, history and objects may be incomplete
+    pub shallow: bool,
+    /// Git LFS status, `None` if repository doesn't track files with LFS
+    pub lfs: Option<GitLfsStatus>,
+    /// Committing identity doesn't match expected email pattern
+    pub identity_mismatch: bool,
+    /// Branch matches a protected branch pattern, committing on it directly is unwanted
+    pub protected: bool,
+    /// A staged file is bigger than the configured limit, likely a build artifact
+    pub large_staged: bool,
+    /// A staged file looks like a secret by its name or contents
+    pub secrets_staged: bool,
+    /// Repository has non-default `core.hooksPath` or active local hooks
+    pub hooks: bool,
+    /// Repository and index are given by `GIT_DIR` and `GIT_INDEX_FILE`, e.g. in a hook
+    pub from_env: bool,
+    /// Part of git information didn't finish in time and is skipped
+    pub slow: bool,
+    /// Repository is big, untracked files or ahead/behind are skipped by `--git-adaptive`
+    pub reduced: bool,
+    /// Start folder is inside of `.git`, git refuses status there and it's skipped
+    pub inside_git_dir: bool,
+    /// Repository is owned by another user and isn't listed in `safe.directory`
+    pub untrusted: bool,
+    /// Objects, references or index can't be read
+    pub damaged: bool,
+    /// Lines changed in the worktree against HEAD, `None` if disabled, too big or too slow
+    pub diff_stat: Option<GitDiffStat>,
+    /// Repository is inside of a worktree of another one
+    pub nested: bool,
+    /// Branch or commit id of the closest outer repository
+    pub outer_branch: Option<String>,
+    /// Divergence from default branches of remotes, remotes which didn't finish in time are skipped
+    pub remotes: Vec<GitRemoteDivergence>,
+    /// Open pull request of the branch known at the last `prcache refresh`
+    pub pull_request: Option<u64>,
+    /// CI status of HEAD known at the last `cicache refresh`
+    pub ci_status: Option<GitCiStatus>,
+    /// Commits of the author made today reachable from HEAD, counted within a walk limit
+    pub today_commits: Option<usize>,
+    /// Days since merge base of HEAD and its tracking branch was committed,
+    /// `None` if disabled or younger than the minimum
+    pub base_age_days: Option<u64>,
+    /// Seconds since the oldest uncommitted change, `None` if disabled or clean
+    pub dirty_age_secs: Option<u64>,
+    /// Latest tag reachable from HEAD, `None` if disabled or not found within the walk limit
+    pub tag_distance: Option<GitTagDistance>,
+    /// Effective identity commits are made with, `None` if disabled
+    pub identity: Option<GitIdentity>,
+    /// Default branch of the remote from `<remote>/HEAD`, `None` if disabled or unknown
+    pub default_branch: Option<String>,
+}
+
+/// Nothing is known about the repository, unknown upstream isn't shown as a missing one
+impl Default for GitOutputOptions {
+    fn default() -> Self {
+        GitOutputOptions {
+            head_info: None,
+            file_status: None,
+            branch_ahead_behind: Some(GitBranchAheadBehind {
+                ahead: 0,
+                behind: 0,
+            }),
+            state: None,
+            relative_path: None,
+            repo_name: None,
+            read_only: false,
+            busy: false,
+            shallow: false,
+            lfs: None,
+            identity_mismatch: false,
+            protected: false,
+            large_staged: false,
+            secrets_staged: false,
+            hooks: false,
+            from_env: false,
+            slow: false,
+            reduced: false,
+            inside_git_dir: false,
+            untrusted: false,
+            damaged: false,
+            diff_stat: None,
+            nested: false,
+            outer_branch: None,
+            remotes: Vec::new(),
+            pull_request: None,
+            ci_status: None,
+            today_commits: None,
+            base_age_days: None,
+            dirty_age_secs: None,
+            tag_distance: None,
+            identity: None,
+            default_branch: None,
+        }
+    }
+}
+
+impl GitOutputOptions {
+    /// Flag if HEAD is the default branch of the remote rather than a feature branch
+    pub(crate) fn on_default_branch(&self) -> bool {
+        let branch = self.head_info.as_ref().and_then(|h| h.branch_name());
+        branch.is_some_and(|v| self.default_branch.as_deref() == Some(v))
+    }
 }
 
 pub(crate) struct DateTime {
     pub date: Box<dyn std::fmt::Display>,
     pub time: Box<dyn std::fmt::Display>,
+    /// Date is hidden from the prompt if it's the same as on the previous one
+    pub show_date: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Hash)]
 pub(crate) struct GitHeadInfo {
+    /// Short reference name, non UTF-8 names are converted lossy
     pub reference_short: Option<String>,
+    /// Full reference name or resolved target of a symbolic reference
+    pub reference_name: Option<String>,
+    pub reference_kind: Option<GitReferenceKind>,
+    pub oid: Option<String>,
     pub oid_short: Option<String>,
     pub detached: bool,
+    /// Closest local branch containing detached HEAD
+    pub detached_from: Option<String>,
+    pub branch_url: Option<String>,
+    /// HEAD commit has a signature, `None` if not checked
+    pub signed: Option<bool>,
+    /// Ticket id found in the branch name, `None` if not checked or not found
+    pub ticket: Option<GitTicket>,
 }
 
-#[derive(Debug)]
+impl GitHeadInfo {
+    /// Full branch name like `release/1.0`, `None` for detached HEAD
+    pub(crate) fn branch_name(&self) -> Option<&str> {
+        self.reference_name
+            .as_deref()
+            .filter(|_| !self.detached)?
+            .strip_prefix("refs/heads/")
+    }
+
+    /// Short branch name to show, ticket id goes first when it's extracted
+    pub(crate) fn branch_label(&self) -> Option<Cow<'_, str>> {
+        match &self.ticket {
+            Some(t) if t.name.is_empty() => Some(Cow::Borrowed(&t.id)),
+            Some(t) => Some(Cow::Owned(format!("{} {}", t.id, t.name))),
+            None => self.reference_short.as_deref().map(Cow::Borrowed),
+        }
+    }
+}
+
+/// Ticket id found in branch name
+#[derive(Debug, PartialEq, Hash)]
+pub(crate) struct GitTicket {
+    pub id: String,
+    /// Rest of the branch name without prefix folders and ticket id
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub(crate) enum GitReferenceKind {
+    Symbolic,
+    Direct,
+}
+
+impl std::fmt::Display for GitReferenceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitReferenceKind::Symbolic => f.write_str("symbolic"),
+            GitReferenceKind::Direct => f.write_str("direct"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
 pub(crate) struct GitFileStatus {
     pub conflict: bool,
     pub untracked: bool,
     pub typechange: bool,
     pub unstaged: bool,
     pub staged: bool,
+    /// Exact number of files per category for machine readable themes
+    pub counts: GitFileCounts,
 }
 
-#[derive(Debug)]
+/// Number of files per status, a file is counted in every category it belongs to
+#[derive(Debug, Clone, Default, PartialEq, Hash)]
+pub(crate) struct GitFileCounts {
+    pub staged_new: usize,
+    pub staged_modified: usize,
+    pub staged_deleted: usize,
+    pub staged_renamed: usize,
+    pub staged_typechange: usize,
+    pub wt_new: usize,
+    pub wt_modified: usize,
+    pub wt_deleted: usize,
+    pub wt_renamed: usize,
+    pub wt_typechange: usize,
+    pub conflicted: usize,
+}
+
+impl GitFileCounts {
+    /// Category names with their counts in output order
+    pub(crate) fn entries(&self) -> [(&'static str, usize); 11] {
+        [
+            ("staged_new", self.staged_new),
+            ("staged_modified", self.staged_modified),
+            ("staged_deleted", self.staged_deleted),
+            ("staged_renamed", self.staged_renamed),
+            ("staged_typechange", self.staged_typechange),
+            ("wt_new", self.wt_new),
+            ("wt_modified", self.wt_modified),
+            ("wt_deleted", self.wt_deleted),
+            ("wt_renamed", self.wt_renamed),
+            ("wt_typechange", self.wt_typechange),
+            ("conflicted", self.conflicted),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub(crate) struct GitRemoteDivergence {
+    pub remote: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// Latest tag reachable from HEAD and number of commits HEAD is ahead of it
+#[derive(Debug, Clone, Hash)]
+pub(crate) struct GitTagDistance {
+    pub tag: String,
+    pub ahead: usize,
+}
+
+/// `user.name` and `user.email` of the repository after includes are resolved
+#[derive(Debug, Clone, Hash)]
+pub(crate) struct GitIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub(crate) struct GitDiffStat {
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Hash)]
+pub(crate) struct GitLfsStatus {
+    /// Number of LFS tracked files checked out as pointers
+    pub unpulled: usize,
+}
+
+#[derive(Debug, Hash)]
 pub(crate) struct GitBranchAheadBehind {
     pub ahead: usize,
     pub behind: usize,
 }
+
+/// Python environment with the tool managing it
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub(crate) struct PythonEnv {
+    pub name: String,
+    pub manager: PythonManager,
+}
+
+/// Tool managing a python environment, themes may tell them apart
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub(crate) enum PythonManager {
+    #[cfg(feature = "python")]
+    Conda,
+    /// `venv` or `virtualenv` without a known project manager
+    #[cfg_attr(not(any(feature = "python", feature = "json")), allow(dead_code))]
+    Virtualenv,
+    #[cfg(feature = "python")]
+    Uv,
+    #[cfg(feature = "python")]
+    Pipenv,
+}
+
+impl std::fmt::Display for PythonManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "python")]
+            PythonManager::Conda => f.write_str("conda"),
+            PythonManager::Virtualenv => f.write_str("virtualenv"),
+            #[cfg(feature = "python")]
+            PythonManager::Uv => f.write_str("uv"),
+            #[cfg(feature = "python")]
+            PythonManager::Pipenv => f.write_str("pipenv"),
+        }
+    }
+}
+
+/// Last known state of CI runs of HEAD
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub(crate) enum GitCiStatus {
+    Passed,
+    Failed,
+    Running,
+}
+
+impl std::fmt::Display for GitCiStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitCiStatus::Passed => f.write_str("passed"),
+            GitCiStatus::Failed => f.write_str("failed"),
+            GitCiStatus::Running => f.write_str("running"),
+        }
+    }
+}
+
+/// Operation in progress in the repository
+#[derive(Debug, Hash)]
+pub(crate) enum GitRepoState {
+    Merge,
+    Revert,
+    CherryPick,
+    Bisect {
+        /// Estimated number of remaining bisect steps
+        steps: Option<usize>,
+    },
+    Rebase,
+    ApplyMailbox,
+}
+
+impl std::fmt::Display for GitRepoState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitRepoState::Merge => f.write_str("merge"),
+            GitRepoState::Revert => f.write_str("revert"),
+            GitRepoState::CherryPick => f.write_str("cherry-pick"),
+            GitRepoState::Bisect { steps: None } => f.write_str("bisect"),
+            GitRepoState::Bisect { steps: Some(steps) } => write!(f, "bisect ~{steps} steps"),
+            GitRepoState::Rebase => f.write_str("rebase"),
+            GitRepoState::ApplyMailbox => f.write_str("am"),
+        }
+    }
+}
 impl ThemeSymbols {
+    /// Field names of symbols with their meaning, in order of `symbols` subcommand table
+    pub(crate) const DESCRIPTIONS: &'static [(&'static str, &'static str)] = &[
+        ("git_branch", "branch name follows"),
+        ("git_has_no_upstream", "branch has no tracking branch"),
+        ("git_branch_detached", "HEAD is detached"),
+        (
+            "git_default_branch",
+            "HEAD is the default branch of the remote",
+        ),
+        ("git_is_ahead", "commits ahead of tracking branch"),
+        ("git_is_behind", "commits behind tracking branch"),
+        ("git_has_conflict", "files with merge conflicts"),
+        ("git_has_untracked", "untracked files"),
+        ("git_has_typechange", "files changed type"),
+        ("git_has_unstaged", "unstaged changes"),
+        ("git_has_staged", "staged changes"),
+        ("git_read_only", "repository is read-only"),
+        ("git_busy", "another git process holds the index"),
+        ("git_shallow", "shallow or partial clone"),
+        ("git_lfs", "repository uses Git LFS"),
+        ("git_lfs_unpulled", "LFS files left as pointers"),
+        ("git_signed", "HEAD commit is signed"),
+        ("git_unsigned", "HEAD commit isn't signed"),
+        (
+            "git_identity_mismatch",
+            "user.email doesn't match expected one",
+        ),
+        ("git_protected", "branch is a protected one"),
+        ("git_large_staged", "a big file is staged"),
+        ("git_secrets_staged", "a staged file looks like a secret"),
+        ("git_hooks", "custom or local hooks are active"),
+        (
+            "git_from_env",
+            "repository is given by GIT_DIR, e.g. in a hook",
+        ),
+        ("git_untrusted", "repository of another user isn't trusted"),
+        ("git_damaged", "repository is damaged"),
+        ("git_reduced", "big repository, status is reduced"),
+        ("git_inside_git_dir", "current folder is inside of .git"),
+        ("git_nested", "repository is nested in another one"),
+        ("git_ci_passed", "CI passed on HEAD"),
+        ("git_ci_failed", "CI failed on HEAD"),
+        ("git_ci_running", "CI is running on HEAD"),
+        ("jobs", "background jobs"),
+        ("cmd_duration", "duration of the last command"),
+        ("vi_command", "line editor is in vi command mode"),
+        ("shell_level", "shell is nested in another one"),
+        ("slow", "segment didn't finish in time"),
+        ("python_separator", "between stacked python environments"),
+        (
+            "separator",
+            "separator between segments of different colors",
+        ),
+        (
+            "separator_thin",
+            "separator between segments of the same color",
+        ),
+        ("cap_left", "before the first segment"),
+        ("cap_right", "after the last segment"),
+    ];
+
     pub(crate) fn utf8_power() -> Self {
         ThemeSymbols {
             git_branch: "\u{e0a0}",          // 
             git_has_no_upstream: "\u{25B2}", // ▲
             git_branch_detached: "\u{2630}", // ☰
+            git_default_branch: "⌂",
             git_is_ahead: "↑",
             git_is_behind: "↓",
             git_has_conflict: "✘",
@@ -95,6 +968,37 @@ impl ThemeSymbols {
             git_has_typechange: "‡",
             git_has_unstaged: "●",
             git_has_staged: "●",
+            git_read_only: "\u{e0a2}", //
+            git_busy: "⧗",
+            git_shallow: "≈",
+            git_lfs: "⛁",
+            git_lfs_unpulled: "⛀",
+            git_signed: "✓",
+            git_unsigned: "⚠",
+            git_identity_mismatch: "✉",
+            git_protected: "⚑",
+            git_large_staged: "⚖",
+            git_secrets_staged: "☢",
+            git_hooks: "⚓",
+            git_from_env: "⚙",
+            git_untrusted: "⛔",
+            git_damaged: "☠",
+            git_reduced: "◐",
+            git_inside_git_dir: ".git!",
+            git_nested: "⧉",
+            git_ci_passed: "✔",
+            git_ci_failed: "✖",
+            git_ci_running: "⟳",
+            jobs: "✦",
+            cmd_duration: "⏱",
+            vi_command: "❮",
+            shell_level: "↕",
+            slow: "⌛",
+            python_separator: ">",
+            separator: "\u{e0b0}",      //
+            separator_thin: "\u{e0b1}", //
+            cap_left: "",
+            cap_right: "\u{e0b0}", //
         }
     }
     pub(crate) fn utf8() -> Self {
@@ -102,6 +1006,7 @@ impl ThemeSymbols {
             git_branch: "ᚠ",
             git_has_no_upstream: "ᛘ",
             git_branch_detached: "\u{2630}", // ☰
+            git_default_branch: "⌂",
             git_is_ahead: "↑",
             git_is_behind: "↓",
             git_has_conflict: "✘",
@@ -109,6 +1014,37 @@ impl ThemeSymbols {
             git_has_typechange: "‡",
             git_has_unstaged: "●",
             git_has_staged: "●",
+            git_read_only: "⊘",
+            git_busy: "⧗",
+            git_shallow: "≈",
+            git_lfs: "⛁",
+            git_lfs_unpulled: "⛀",
+            git_signed: "✓",
+            git_unsigned: "⚠",
+            git_identity_mismatch: "✉",
+            git_protected: "⚑",
+            git_large_staged: "⚖",
+            git_secrets_staged: "☢",
+            git_hooks: "⚓",
+            git_from_env: "⚙",
+            git_untrusted: "⛔",
+            git_damaged: "☠",
+            git_reduced: "◐",
+            git_inside_git_dir: ".git!",
+            git_nested: "⧉",
+            git_ci_passed: "✔",
+            git_ci_failed: "✖",
+            git_ci_running: "⟳",
+            jobs: "✦",
+            cmd_duration: "⏱",
+            vi_command: "❮",
+            shell_level: "↕",
+            slow: "⌛",
+            python_separator: ">",
+            separator: "▶",
+            separator_thin: "›",
+            cap_left: "",
+            cap_right: "",
         }
     }
 
@@ -117,6 +1053,7 @@ impl ThemeSymbols {
             git_branch: "",
             git_has_no_upstream: "&",
             git_branch_detached: "||",
+            git_default_branch: "=",
             git_is_ahead: "^",
             git_is_behind: "v",
             git_has_conflict: "x",
@@ -124,6 +1061,105 @@ impl ThemeSymbols {
             git_has_typechange: "T",
             git_has_unstaged: "*",
             git_has_staged: "*",
+            git_read_only: "RO",
+            git_busy: "...",
+            git_shallow: "~",
+            git_lfs: "L",
+            git_lfs_unpulled: "L!",
+            git_signed: "S",
+            git_unsigned: "!S",
+            git_identity_mismatch: "@!",
+            git_protected: "P!",
+            git_large_staged: "BIG",
+            git_secrets_staged: "KEY!",
+            git_hooks: "H",
+            git_from_env: "ENV",
+            git_untrusted: "!!",
+            git_damaged: "DMG",
+            git_reduced: "-",
+            git_inside_git_dir: ".git!",
+            git_nested: "N",
+            git_ci_passed: "CI+",
+            git_ci_failed: "CI-",
+            git_ci_running: "CI~",
+            jobs: "j",
+            cmd_duration: "took ",
+            vi_command: "<",
+            shell_level: "+",
+            slow: "T/O",
+            python_separator: ">",
+            separator: ">",
+            separator_thin: "|",
+            cap_left: "",
+            cap_right: "",
         }
     }
 }
+
+/// Accessors of symbols by field name, so names are listed once for both of them
+macro_rules! symbol_fields {
+    ($($field:ident),* $(,)?) => {
+        impl ThemeSymbols {
+            /// Symbol by its field name
+            pub(crate) fn symbol(&self, name: &str) -> Option<&'static str> {
+                match name {
+                    $(stringify!($field) => Some(self.$field),)*
+                    _ => None,
+                }
+            }
+
+            /// Symbol by its field name, used to override symbols from config
+            pub(crate) fn symbol_mut(&mut self, name: &str) -> Option<&mut &'static str> {
+                match name {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+symbol_fields!(
+    git_branch,
+    git_has_no_upstream,
+    git_branch_detached,
+    git_default_branch,
+    git_is_ahead,
+    git_is_behind,
+    git_has_conflict,
+    git_has_untracked,
+    git_has_typechange,
+    git_has_unstaged,
+    git_has_staged,
+    git_read_only,
+    git_busy,
+    git_shallow,
+    git_lfs,
+    git_lfs_unpulled,
+    git_signed,
+    git_unsigned,
+    git_identity_mismatch,
+    git_protected,
+    git_large_staged,
+    git_secrets_staged,
+    git_hooks,
+    git_from_env,
+    git_untrusted,
+    git_damaged,
+    git_reduced,
+    git_inside_git_dir,
+    git_nested,
+    git_ci_passed,
+    git_ci_failed,
+    git_ci_running,
+    jobs,
+    cmd_duration,
+    vi_command,
+    shell_level,
+    slow,
+    python_separator,
+    separator,
+    separator_thin,
+    cap_left,
+    cap_right,
+);
@@ -0,0 +1,97 @@
+//! Table of theme symbols with their meaning, printed by `symbols` subcommand
+//! to check the terminal font has all glyphs of a symbol set.
+
+use crate::structs;
+use crate::util;
+
+/// Every symbol next to its name and meaning, empty symbols are shown as `(none)`
+pub(crate) fn table(symbols: &structs::ThemeSymbols) -> String {
+    let rows: Vec<_> = structs::ThemeSymbols::DESCRIPTIONS
+        .iter()
+        .map(|(name, meaning)| {
+            let symbol = symbols.symbol(name).unwrap_or_default();
+            (symbol, *name, *meaning)
+        })
+        .collect();
+    let symbol_width = rows
+        .iter()
+        .map(|(symbol, _, _)| util::display_width(symbol))
+        .max()
+        .unwrap_or_default()
+        .max("(none)".len());
+    let name_width = rows
+        .iter()
+        .map(|(_, name, _)| name.len())
+        .max()
+        .unwrap_or_default();
+
+    rows.into_iter()
+        .map(|(symbol, name, meaning)| {
+            let (symbol, width) = match symbol.is_empty() {
+                true => ("(none)", "(none)".len()),
+                false => (symbol, util::display_width(symbol)),
+            };
+            let padding = " ".repeat(symbol_width - width);
+            format!("{symbol}{padding}  {name:<name_width$}  {meaning}\n")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::table;
+    use crate::structs;
+
+    #[test]
+    fn descriptions_test() {
+        let symbols = structs::ThemeSymbols::ascii();
+        for (name, _) in structs::ThemeSymbols::DESCRIPTIONS {
+            assert!(symbols.symbol(name).is_some(), "{name} isn't a symbol");
+        }
+    }
+
+    /// Markers shown next to counts or flags of the shell, they're read alongside git ones
+    const SHELL_MARKERS: [&str; 4] = ["jobs", "cmd_duration", "vi_command", "shell_level"];
+
+    #[test]
+    fn distinct_markers_test() {
+        let sets = [
+            ("utf8_power", structs::ThemeSymbols::utf8_power()),
+            ("utf8", structs::ThemeSymbols::utf8()),
+            ("ascii", structs::ThemeSymbols::ascii()),
+        ];
+        for (set, symbols) in sets {
+            let markers: Vec<_> = structs::ThemeSymbols::DESCRIPTIONS
+                .iter()
+                .map(|(name, _)| *name)
+                // staged and unstaged changes are told apart by color
+                .filter(|name| name.starts_with("git_") || SHELL_MARKERS.contains(name))
+                .filter(|name| *name != "git_has_unstaged")
+                .filter_map(|name| Some((name, symbols.symbol(name)?)))
+                .filter(|(_, symbol)| !symbol.is_empty())
+                .collect();
+            for (idx, (name, symbol)) in markers.iter().enumerate() {
+                for (other, other_symbol) in &markers[idx + 1..] {
+                    assert_ne!(symbol, other_symbol, "{set}: {name} and {other}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn table_test() {
+        let table = table(&structs::ThemeSymbols::ascii());
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), structs::ThemeSymbols::DESCRIPTIONS.len());
+        assert_eq!(
+            lines[0],
+            "(none)  git_branch             branch name follows"
+        );
+        assert_eq!(
+            lines[1],
+            "&       git_has_no_upstream    branch has no tracking branch"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Battery and load average segments, enabled by `system` feature
+
+use std::fs;
+use std::path::Path;
+
+use crate::structs;
+
+/// Folder with power supplies on Linux
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Collects requested system segments
+pub(crate) fn segments(battery: bool, load_average: bool) -> Vec<structs::CustomSegment> {
+    let battery = battery
+        .then(battery_percent)
+        .flatten()
+        .map(|v| ("battery", format!("{v}%")));
+    let load_average = load_average
+        .then(load_average_1m)
+        .flatten()
+        .map(|v| ("load", format!("{v:.2}")));
+
+    battery
+        .into_iter()
+        .chain(load_average)
+        .map(|(name, text)| structs::CustomSegment {
+            name: name.to_string(),
+            text,
+            color: None,
+        })
+        .collect()
+}
+
+/// Charge of the first battery in percents
+fn battery_percent() -> Option<u8> {
+    sys_battery_percent(Path::new(POWER_SUPPLY)).or_else(pmset_battery_percent)
+}
+
+fn sys_battery_percent(folder: &Path) -> Option<u8> {
+    let mut supplies: Vec<_> = fs::read_dir(folder)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    supplies.sort();
+    supplies
+        .iter()
+        .filter(|p| fs::read_to_string(p.join("type")).is_ok_and(|v| v.trim() == "Battery"))
+        .find_map(|p| fs::read_to_string(p.join("capacity")).ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(target_os = "macos")]
+fn pmset_battery_percent() -> Option<u8> {
+    let output = std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()?;
+    parse_pmset(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn pmset_battery_percent() -> Option<u8> {
+    None
+}
+
+/// Finds percents in `pmset -g batt` output like `-InternalBattery-0 (id=1) 87%; charging`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_pmset(output: &str) -> Option<u8> {
+    output
+        .lines()
+        .filter(|l| l.contains("InternalBattery"))
+        .find_map(|l| l.split_once('%'))
+        .and_then(|(before, _)| before.rsplit(char::is_whitespace).next())
+        .and_then(|v| v.parse().ok())
+}
+
+/// Load average for the last minute
+fn load_average_1m() -> Option<f64> {
+    fs::read_to_string("/proc/loadavg")
+        .ok()
+        .and_then(|v| parse_loadavg(&v))
+        .or_else(getloadavg)
+}
+
+fn parse_loadavg(content: &str) -> Option<f64> {
+    content.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(unix)]
+fn getloadavg() -> Option<f64> {
+    let mut loads = [0f64; 1];
+    let result = unsafe { libc::getloadavg(loads.as_mut_ptr(), 1) };
+    (result == 1).then_some(loads[0])
+}
+
+#[cfg(not(unix))]
+fn getloadavg() -> Option<f64> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::parse_loadavg;
+    use super::parse_pmset;
+
+    #[rstest]
+    #[case("0.52 0.58 0.59 1/467 12345\n", Some(0.52))]
+    #[case("", None)]
+    #[case("n/a", None)]
+    fn parse_loadavg_test(#[case] content: &str, #[case] expected: Option<f64>) {
+        assert_eq!(parse_loadavg(content), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "Now drawing from 'AC Power'\n -InternalBattery-0 (id=4653155)\t87%; charging; 0:41 remaining present: true\n",
+        Some(87)
+    )]
+    #[case("Now drawing from 'AC Power'\n", None)]
+    fn parse_pmset_test(#[case] output: &str, #[case] expected: Option<u8>) {
+        assert_eq!(parse_pmset(output), expected);
+    }
+}
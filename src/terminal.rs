@@ -0,0 +1,129 @@
+use std::env;
+
+/// Terminal width from `COLUMNS` or from the terminal attached to stderr
+pub(crate) fn width() -> Option<usize> {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .or_else(ioctl_width)
+}
+
+#[cfg(unix)]
+fn ioctl_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // stdout is captured by the shell, but stderr is usually still a terminal
+    let result = unsafe { libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) };
+    match result == 0 && size.ws_col > 0 {
+        true => Some(size.ws_col as usize),
+        false => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn ioctl_width() -> Option<usize> {
+    None
+}
+
+/// Flag if the locale of `LC_ALL`, `LC_CTYPE` or `LANG` uses UTF-8, the first set one wins
+pub(crate) fn utf8_locale() -> bool {
+    utf8_locale_from_env(|name| env::var_os(name))
+}
+
+fn utf8_locale_from_env(var: impl Fn(&str) -> Option<std::ffi::OsString>) -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| var(name).filter(|v| !v.is_empty()));
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_string_lossy().to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        // unset locale is `C` on unix, Windows terminals handle UTF-8 without it
+        None => !cfg!(unix),
+    }
+}
+
+/// When colored themes use colors
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum ColorMode {
+    /// Follow `NO_COLOR`, `CLICOLOR_FORCE` and `CLICOLOR` environment variables
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Flag if colors are enabled, explicit mode takes precedence over environment
+pub(crate) fn colors_enabled(mode: ColorMode) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => colors_from_env(|name| env::var_os(name)),
+    }
+}
+
+/// Colors following https://no-color.org and https://bixense.com/clicolors conventions.
+///
+/// Prompt is always shown in a terminal, so colors are on unless they're disabled.
+fn colors_from_env(var: impl Fn(&str) -> Option<std::ffi::OsString>) -> bool {
+    let set = |name| var(name).filter(|v| !v.is_empty());
+    if set("NO_COLOR").is_some() {
+        return false;
+    }
+    if set("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+        return true;
+    }
+    set("CLICOLOR").is_none_or(|v| v != "0")
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use rstest::rstest;
+
+    use super::colors_from_env;
+    use super::utf8_locale_from_env;
+
+    #[rstest]
+    #[case(&[], true)]
+    #[case(&[("NO_COLOR", "1")], false)]
+    #[case(&[("NO_COLOR", "")], true)]
+    #[case(&[("CLICOLOR", "0")], false)]
+    #[case(&[("CLICOLOR", "1")], true)]
+    #[case(&[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")], true)]
+    #[case(&[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "0")], false)]
+    #[case(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")], false)]
+    fn colors_from_env_test(#[case] vars: &[(&str, &str)], #[case] expected: bool) {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| OsString::from(v))
+        };
+        assert_eq!(colors_from_env(var), expected);
+    }
+
+    #[rstest]
+    #[case(&[("LANG", "en_US.UTF-8")], true)]
+    #[case(&[("LANG", "de_DE.utf8")], true)]
+    #[case(&[("LANG", "C")], false)]
+    #[case(&[("LANG", "en_US.ISO-8859-1")], false)]
+    #[case(&[("LC_ALL", "POSIX"), ("LANG", "en_US.UTF-8")], false)]
+    #[case(&[("LC_ALL", ""), ("LC_CTYPE", "C.UTF-8"), ("LANG", "C")], true)]
+    #[cfg_attr(unix, case(&[], false))]
+    fn utf8_locale_from_env_test(#[case] vars: &[(&str, &str)], #[case] expected: bool) {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| OsString::from(v))
+        };
+        assert_eq!(utf8_locale_from_env(var), expected);
+    }
+}
@@ -0,0 +1,73 @@
+//! Time spent in stages of a run, printed to stderr with `--timings`
+
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+static TIMINGS: Mutex<Vec<(&'static str, Duration)>> = Mutex::new(Vec::new());
+
+pub(crate) fn setup(enabled: bool) {
+    let _ = ENABLED.get_or_init(|| enabled);
+}
+
+fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or_default()
+}
+
+/// Runs the stage recording its duration if timings are enabled
+pub(crate) fn measure<T>(stage: &'static str, f: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return f();
+    }
+    let started = Instant::now();
+    let result = f();
+    record(stage, started.elapsed());
+    result
+}
+
+fn record(stage: &'static str, duration: Duration) {
+    if let Ok(mut timings) = TIMINGS.lock() {
+        timings.push((stage, duration));
+    }
+}
+
+/// Stages in order of completion, repeated stages like `git.open` are listed every time
+pub(crate) fn report() -> String {
+    let timings = match TIMINGS.lock() {
+        Ok(v) => v,
+        Err(_) => return String::new(),
+    };
+    format_report(&timings)
+}
+
+fn format_report(timings: &[(&str, Duration)]) -> String {
+    timings
+        .iter()
+        .map(|(stage, duration)| {
+            format!("{stage:<12} {:>9.3}ms\n", duration.as_secs_f64() * 1000.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::format_report;
+
+    #[test]
+    fn format_report_test() {
+        assert_eq!(
+            format_report(&[
+                ("git.open", Duration::from_micros(1500)),
+                ("render", Duration::from_micros(20)),
+            ]),
+            "git.open         1.500ms\nrender           0.020ms\n"
+        );
+    }
+}
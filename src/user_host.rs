@@ -1,13 +1,42 @@
+use crate::context;
 use crate::error::MapLog;
 
-pub fn hostname() -> Option<String> {
+/// Source of user and host names
+pub(crate) trait HostInfoProvider: Sync {
+    /// Host name known without a system call, looking it up is slow on some systems
+    fn fast_hostname(&self, ctx: &context::Context) -> Option<String>;
+    fn hostname(&self, ctx: &context::Context) -> Option<String>;
+    fn username(&self, ctx: &context::Context) -> Option<String>;
+}
+
+/// User and host names of the operating system
+pub(crate) struct SystemHostInfo;
+
+impl HostInfoProvider for SystemHostInfo {
+    fn fast_hostname(&self, ctx: &context::Context) -> Option<String> {
+        std::env::var("HOST")
+            .ok_or_log(ctx) // zsh and tcsh
+            .or_else(|| std::env::var("HOSTNAME").ok_or_log(ctx)) // bash
+            .or_else(|| std::env::var("COMPUTERNAME").ok_or_log(ctx)) // windows
+    }
+
+    fn hostname(&self, ctx: &context::Context) -> Option<String> {
+        hostname(ctx)
+    }
+
+    fn username(&self, ctx: &context::Context) -> Option<String> {
+        username(ctx)
+    }
+}
+
+pub fn hostname(ctx: &context::Context) -> Option<String> {
     hostname::get()
-        .ok_or_log()
+        .ok_or_log(ctx)
         .and_then(|s| s.into_string().ok())
 }
 
-pub fn username() -> Option<String> {
+pub fn username(ctx: &context::Context) -> Option<String> {
     std::env::var("USER")
-        .ok_or_log()
-        .or_else(|| std::env::var("USERNAME").ok_or_log())
+        .ok_or_log(ctx)
+        .or_else(|| std::env::var("USERNAME").ok_or_log(ctx))
 }
@@ -1,3 +1,11 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
+
+use crate::structs;
+
 #[allow(dead_code)]
 pub(crate) fn print_type_of<T>(_: &T) {
     println!("{}", std::any::type_name::<T>())
@@ -5,6 +13,7 @@ pub(crate) fn print_type_of<T>(_: &T) {
 
 pub(crate) trait LastPart {
     fn last_part(&self) -> &Self;
+    #[cfg_attr(not(feature = "python"), allow(dead_code))]
     fn last_two_parts(&self) -> &Self;
 }
 
@@ -23,9 +32,469 @@ impl LastPart for str {
     }
 }
 
+pub(crate) trait ShortHost {
+    fn short_host(&self) -> &Self;
+}
+
+impl ShortHost for str {
+    fn short_host(&self) -> &str {
+        self.split('.').next().unwrap_or_default()
+    }
+}
+
+/// Converts remote url (https, ssh or scp-like) and branch name into a web page url
+pub(crate) fn remote_branch_url(remote_url: &str, branch: &str) -> Option<String> {
+    let (host, path) = match remote_url.split_once("://") {
+        Some((_scheme, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            let host = match host.rsplit_once(':') {
+                Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+                _ => host,
+            };
+            (host, path)
+        }
+        None => {
+            let (authority, path) = remote_url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            (authority.rsplit('@').next()?, path)
+        }
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || path.is_empty() || branch.is_empty() {
+        return None;
+    }
+
+    let tree = match host.contains("gitlab") {
+        true => "-/tree",
+        false => "tree",
+    };
+    Some(format!(
+        "https://{host}/{path}/{tree}/{}",
+        percent_encode_path(branch)
+    ))
+}
+
+/// Percent-encodes the text for a url path, slashes are kept as separators
+fn percent_encode_path(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                char::from(b).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Repository name as the last path component of the remote url without `.git` suffix
+pub(crate) fn repo_name_from_url(remote_url: &str) -> Option<&str> {
+    let path = remote_url.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let name = path.rsplit(['/', ':', '\\']).next()?;
+    match name.is_empty() {
+        true => None,
+        false => Some(name),
+    }
+}
+
+/// Wraps text into an OSC 8 terminal hyperlink escaped for zsh prompt
+pub(crate) fn hyperlink(url: &str, text: &str) -> String {
+    let url = url.replace('%', "%%");
+    format!("%{{\x1b]8;;{url}\x1b\\%}}{text}%{{\x1b]8;;\x1b\\%}}")
+}
+
+/// Part of a prompt line which can be shortened or dropped when the line is too wide
+pub(crate) struct Segment<'a> {
+    /// Segments with lower priority are shortened and dropped first
+    priority: u8,
+    text: Cow<'a, str>,
+    short: Option<Cow<'a, str>>,
+}
+
+impl<'a> Segment<'a> {
+    pub(crate) fn new(priority: u8, text: impl Into<Cow<'a, str>>) -> Self {
+        Segment {
+            priority,
+            text: text.into(),
+            short: None,
+        }
+    }
+
+    pub(crate) fn with_short(mut self, short: impl Into<Cow<'a, str>>) -> Self {
+        self.short = Some(short.into());
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Same segment with text and short text changed by `f`
+    #[cfg_attr(not(feature = "color"), allow(dead_code))]
+    pub(crate) fn map(self, f: impl Fn(&str) -> String) -> Self {
+        Segment {
+            priority: self.priority,
+            text: f(&self.text).into(),
+            short: self.short.map(|v| f(&v).into()),
+        }
+    }
+}
+
+/// Segments of a group become one, it's shortened and dropped as a whole with priority
+/// of its most important segment
+impl<'a> From<structs::Arranged<'_, Segment<'a>>> for Segment<'a> {
+    fn from(arranged: structs::Arranged<'_, Segment<'a>>) -> Self {
+        let (group, segments) = match arranged {
+            structs::Arranged::Segment(segment) => return segment,
+            structs::Arranged::Group(group, segments) => (group, segments),
+        };
+        let join = |texts: Vec<&str>| {
+            format!(
+                "{}{}{}",
+                group.prefix,
+                texts.join(group.separator),
+                group.suffix
+            )
+        };
+        let text = join(segments.iter().map(|v| v.text.as_ref()).collect());
+        let short = segments.iter().any(|v| v.short.is_some()).then(|| {
+            join(
+                segments
+                    .iter()
+                    .map(|v| v.short.as_ref().unwrap_or(&v.text).as_ref())
+                    .collect(),
+            )
+        });
+        Segment {
+            priority: segments
+                .iter()
+                .map(|v| v.priority)
+                .max()
+                .unwrap_or_default(),
+            text: text.into(),
+            short: short.map(Cow::from),
+        }
+    }
+}
+
+impl LastPart for [u8] {
+    fn last_part(&self) -> &[u8] {
+        let index = self
+            .iter()
+            .rposition(|b| *b == b'/')
+            .map_or(0, |idx| idx + 1);
+        &self[index..]
+    }
+
+    fn last_two_parts(&self) -> &[u8] {
+        let first_idx = self.iter().rposition(|b| *b == b'/').unwrap_or(self.len());
+        let second_idx = self[0..first_idx]
+            .iter()
+            .rposition(|b| *b == b'/')
+            .map_or(0, |idx| idx + 1);
+        &self[second_idx..]
+    }
+}
+
+/// Abbreviated commit id, ids shorter than `len` are returned whole.
+/// There is no abbreviation of an empty or non-ASCII id or to zero length
+pub(crate) fn short_oid(oid: &str, len: usize) -> Option<&str> {
+    if oid.is_empty() || len == 0 || !oid.is_ascii() {
+        return None;
+    }
+    Some(&oid[..len.min(oid.len())])
+}
+
+/// Last component of a reference name, e.g. `main` of `refs/heads/main`, non UTF-8 names
+/// are converted lossy. Names without slashes are returned whole, empty names and names
+/// ending with a slash have no short form
+pub(crate) fn short_ref(name: &[u8]) -> Option<String> {
+    let last = name.last_part();
+    (!last.is_empty()).then(|| String::from_utf8_lossy(last).into_owned())
+}
+
+/// Estimates number of bisect steps for the number of candidate commits the same way git does
+pub(crate) fn estimate_bisect_steps(candidates: usize) -> usize {
+    if candidates < 3 {
+        return 0;
+    }
+    let steps = candidates.ilog2();
+    let power = 1usize << steps;
+    match power < 3 * (candidates - power) {
+        true => steps as usize,
+        false => steps as usize - 1,
+    }
+}
+
+/// Characters separating ticket id from the rest of branch name
+const TICKET_SEPARATORS: [char; 4] = ['-', '_', '.', '/'];
+
+/// Splits branch name into the first ticket id match and a short name made of the rest,
+/// folders before the ticket id like `feature/` are dropped
+pub(crate) fn branch_ticket(branch: &str, pattern: &regex::Regex) -> Option<structs::GitTicket> {
+    let found = pattern.find(branch)?;
+    let before = &branch[..found.start()];
+    let before = before.rsplit_once('/').map_or(before, |(_, v)| v);
+    let after = &branch[found.end()..];
+
+    let prefix = before.trim_end_matches(TICKET_SEPARATORS);
+    let suffix = after.trim_start_matches(TICKET_SEPARATORS);
+    // keep a separator only if the ticket id was in the middle
+    let separator = match prefix.is_empty() || suffix.is_empty() {
+        true => "",
+        false => after
+            .strip_suffix(suffix)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(&before[prefix.len()..]),
+    };
+    Some(structs::GitTicket {
+        id: found.as_str().to_string(),
+        name: format!("{prefix}{separator}{suffix}"),
+    })
+}
+
+/// Matches text against a case-insensitive pattern where `*` is any sequence and `?` is any character
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    wildcard_match_chars(&pattern, &text)
+}
+
+/// Matches path against a pattern where `**` component is any number of components,
+/// `*` and `?` match within a single component
+pub(crate) fn path_match(pattern: &str, path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    let pattern: Vec<&str> = pattern.split('/').filter(|v| !v.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|v| !v.is_empty()).collect();
+    components_match(&pattern, &path)
+}
+
+fn components_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| components_match(rest, &path[i..])),
+        Some((first, rest)) => path.split_first().is_some_and(|(component, path)| {
+            let first: Vec<char> = first.chars().collect();
+            let component: Vec<char> = component.chars().collect();
+            wildcard_match_chars(&first, &component) && components_match(rest, path)
+        }),
+    }
+}
+
+fn wildcard_match_chars(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Joins path components with `/` keeping at most `max_depth` trailing ones
+pub(crate) fn truncate_path(path: &Path, max_depth: Option<usize>) -> String {
+    let parts: Vec<Cow<str>> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+
+    match max_depth {
+        Some(0) if !parts.is_empty() => "…".to_string(),
+        Some(max_depth) if parts.len() > max_depth => {
+            format!("…/{}", parts[parts.len() - max_depth..].join("/"))
+        }
+        _ => parts.join("/"),
+    }
+}
+
+/// Width of the text in terminal columns, counting wide CJK and emoji as two
+pub(crate) fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Truncates text to the display width marking the cut with an ellipsis
+pub(crate) fn truncate_to_width(text: &str, max_width: Option<usize>) -> Cow<'_, str> {
+    let max_width = match max_width {
+        Some(max_width) if display_width(text) > max_width => max_width,
+        _ => return Cow::Borrowed(text),
+    };
+    if max_width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let mut width = 0;
+    let mut result = String::new();
+    for c in text.chars() {
+        width += c.width().unwrap_or_default();
+        // leave one column for the ellipsis
+        if width >= max_width {
+            break;
+        }
+        result.push(c);
+    }
+    result.push('…');
+    result.into()
+}
+
+/// Width of the text as displayed by zsh, skipping `%{...%}` escapes
+pub(crate) fn prompt_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut rest = text;
+    while let Some(idx) = rest.find('%') {
+        width += display_width(&rest[..idx]);
+        rest = &rest[idx..];
+        if let Some(escaped) = rest.strip_prefix("%{") {
+            rest = escaped.find("%}").map_or("", |end| &escaped[end + 2..]);
+        } else if let Some(after) = rest.strip_prefix("%%") {
+            width += 1;
+            rest = after;
+        } else {
+            width += 1;
+            rest = &rest[1..];
+        }
+    }
+    width + display_width(rest)
+}
+
+/// Replaces zsh prompt escapes with terminal sequences to print the prompt outside of zsh,
+/// `%~` is replaced with the folder
+pub(crate) fn render_prompt(text: &str, folder: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('%') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        let mut chars = rest.chars();
+        let code = match chars.next() {
+            Some(c) => c,
+            None => break,
+        };
+        rest = chars.as_str();
+        match code {
+            '{' | '}' => {}
+            '%' => result.push('%'),
+            '~' => result.push_str(folder),
+            'B' => result.push_str("\x1b[1m"),
+            'b' => result.push_str("\x1b[22m"),
+            'f' => result.push_str("\x1b[39m"),
+            'k' => result.push_str("\x1b[49m"),
+            'F' | 'K' => {
+                let Some((color, after)) = rest.strip_prefix('{').and_then(|v| v.split_once('}'))
+                else {
+                    continue;
+                };
+                rest = after;
+                if let Some(color) = ansi_color(color) {
+                    let layer = if code == 'F' { 38 } else { 48 };
+                    result.push_str(&format!("\x1b[{layer};{color}m"));
+                }
+            }
+            _ => {
+                result.push('%');
+                result.push(code);
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Parameters of 256 or true color terminal sequence for zsh color
+fn ansi_color(color: &str) -> Option<String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    if let Ok(idx) = color.parse::<u8>() {
+        return Some(format!("5;{idx}"));
+    }
+    if let Some(idx) = NAMES.iter().position(|n| *n == color) {
+        return Some(format!("5;{idx}"));
+    }
+    let hex = color.strip_prefix('#').filter(|v| v.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(format!("2;{};{};{}", channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Joins segments shortening and then dropping ones with the lowest priority
+/// until the line fits into the max width
+pub(crate) fn fit_segments(mut segments: Vec<Segment>, max_width: Option<usize>) -> String {
+    if let Some(max_width) = max_width {
+        let mut order: Vec<usize> = (0..segments.len()).collect();
+        order.sort_by_key(|&idx| segments[idx].priority);
+
+        let fits = |segments: &[Segment]| {
+            segments
+                .iter()
+                .map(|s| prompt_width(&s.text))
+                .sum::<usize>()
+                <= max_width
+        };
+
+        for &idx in &order {
+            if fits(&segments) {
+                break;
+            }
+            if let Some(short) = segments[idx].short.take() {
+                segments[idx].text = short;
+            }
+        }
+
+        for &idx in &order {
+            if fits(&segments) {
+                break;
+            }
+            segments[idx].text = Cow::Borrowed("");
+        }
+    }
+
+    segments.iter().map(|s| s.text.as_ref()).collect()
+}
+
 #[cfg(test)]
 mod test {
+    use std::path::Path;
+
+    use super::branch_ticket;
+    use super::display_width;
+    use super::estimate_bisect_steps;
+    use super::fit_segments;
+    use super::hyperlink;
+    use super::path_match;
+    use super::prompt_width;
+    use super::remote_branch_url;
+    use super::render_prompt;
+    use super::repo_name_from_url;
+    use super::short_oid;
+    use super::short_ref;
+    use super::truncate_path;
+    use super::truncate_to_width;
+    use super::wildcard_match;
     use super::LastPart;
+    use super::Segment;
+    use super::ShortHost;
+    use crate::structs;
+    use crate::structs::GitTicket;
     use rstest::rstest;
 
     #[rstest]
@@ -47,4 +516,290 @@ mod test {
     fn last_last_two_parts_test(#[case] value: &str, #[case] expected: &str) {
         assert_eq!(value.last_two_parts(), expected);
     }
+
+    #[rstest]
+    #[case(b"", b"")]
+    #[case(b"/", b"")]
+    #[case(b"refs/heads/main", b"main")]
+    #[case(b"refs/heads/\xff\xfe", b"\xff\xfe")]
+    fn last_part_bytes_test(#[case] value: &[u8], #[case] expected: &[u8]) {
+        assert_eq!(value.last_part(), expected);
+    }
+
+    #[rstest]
+    #[case("3f9c1e0a7b2d", 8, Some("3f9c1e0a"))]
+    #[case("3f9c", 8, Some("3f9c"))]
+    #[case("3f9c", 4, Some("3f9c"))]
+    #[case("3f9c", 0, None)]
+    #[case("", 8, None)]
+    #[case("ёёёё", 3, None)]
+    fn short_oid_test(#[case] oid: &str, #[case] len: usize, #[case] expected: Option<&str>) {
+        assert_eq!(short_oid(oid, len), expected);
+    }
+
+    #[rstest]
+    #[case(b"refs/heads/main", Some("main"))]
+    #[case(b"refs/heads/feature/login", Some("login"))]
+    #[case(b"HEAD", Some("HEAD"))]
+    #[case(b"refs/heads/\xff", Some("\u{FFFD}"))]
+    #[case(b"", None)]
+    #[case(b"refs/heads/", None)]
+    #[case(b"/", None)]
+    fn short_ref_test(#[case] name: &[u8], #[case] expected: Option<&str>) {
+        assert_eq!(short_ref(name).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case(b"", b"")]
+    #[case(b"a/b", b"a/b")]
+    #[case(b"refs/heads/\xff", b"heads/\xff")]
+    fn last_two_parts_bytes_test(#[case] value: &[u8], #[case] expected: &[u8]) {
+        assert_eq!(value.last_two_parts(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "https://github.com/owner/repo.git",
+        "main",
+        Some("https://github.com/owner/repo/tree/main")
+    )]
+    #[case(
+        "git@github.com:owner/repo.git",
+        "feature/x",
+        Some("https://github.com/owner/repo/tree/feature/x")
+    )]
+    #[case(
+        "ssh://git@gitlab.com:22/group/sub/repo.git",
+        "main",
+        Some("https://gitlab.com/group/sub/repo/-/tree/main")
+    )]
+    #[case(
+        "https://user@example.org/repo/",
+        "dev",
+        Some("https://example.org/repo/tree/dev")
+    )]
+    #[case(
+        "git@github.com:owner/repo.git",
+        "fix/#12 ünïcode&more",
+        Some("https://github.com/owner/repo/tree/fix/%2312%20%C3%BCn%C3%AFcode%26more")
+    )]
+    #[case(
+        "git@github.com:owner/repo.git",
+        "a?b%c",
+        Some("https://github.com/owner/repo/tree/a%3Fb%25c")
+    )]
+    #[case("/srv/git/repo.git", "main", None)]
+    #[case("../repo", "main", None)]
+    #[case("https://github.com/", "main", None)]
+    #[case("git@github.com:owner/repo.git", "", None)]
+    fn remote_branch_url_test(
+        #[case] remote_url: &str,
+        #[case] branch: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(remote_branch_url(remote_url, branch).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("https://github.com/owner/repo.git", Some("repo"))]
+    #[case("git@github.com:owner/repo", Some("repo"))]
+    #[case("git@host:repo.git", Some("repo"))]
+    #[case("/srv/git/repo.git/", Some("repo"))]
+    #[case("C:\\git\\repo", Some("repo"))]
+    #[case("https://github.com/", Some("github.com"))]
+    #[case("", None)]
+    fn repo_name_from_url_test(#[case] remote_url: &str, #[case] expected: Option<&str>) {
+        assert_eq!(repo_name_from_url(remote_url), expected);
+    }
+
+    #[test]
+    fn hyperlink_test() {
+        assert_eq!(
+            hyperlink("https://h/a%20b", "main"),
+            "%{\x1b]8;;https://h/a%%20b\x1b\\%}main%{\x1b]8;;\x1b\\%}"
+        );
+    }
+
+    #[rstest]
+    #[case("", 0)]
+    #[case("abc", 3)]
+    #[case("%{%F{165}%}date%{[0m%}", 4)]
+    #[case("100%%", 4)]
+    #[case("%~>", 3)]
+    #[case("%{unterminated", 0)]
+    fn prompt_width_test(#[case] value: &str, #[case] expected: usize) {
+        assert_eq!(prompt_width(value), expected);
+    }
+
+    #[rstest]
+    #[case("abc", "abc")]
+    #[case("%~>", "~/src>")]
+    #[case("100%%", "100%")]
+    #[case("%{%F{165}%}date%{\x1b[0m%}", "\x1b[38;5;165mdate\x1b[0m")]
+    #[case(
+        "%{%B%F{red}%}x%{%f%k%b%}",
+        "\x1b[1m\x1b[38;5;1mx\x1b[39m\x1b[49m\x1b[22m"
+    )]
+    #[case("%{%K{#ff8000}%}x", "\x1b[48;2;255;128;0mx")]
+    #[case("%F{unknown}x%#", "x%#")]
+    #[case("trailing %", "trailing ")]
+    fn render_prompt_test(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(render_prompt(value, "~/src"), expected);
+    }
+
+    #[rstest]
+    #[case(None, "[date time]user@host.local(git)")]
+    #[case(Some(100), "[date time]user@host.local(git)")]
+    #[case(Some(26), "[time]user@host.local(git)")]
+    #[case(Some(20), "[time]user@host(git)")]
+    #[case(Some(14), "user@host(git)")]
+    #[case(Some(5), "(git)")]
+    #[case(Some(0), "")]
+    fn fit_segments_test(#[case] max_width: Option<usize>, #[case] expected: &str) {
+        let segments = vec![
+            Segment::new(1, "[date time]").with_short("[time]"),
+            Segment::new(2, "user@host.local").with_short("user@host"),
+            Segment::new(3, "(git)"),
+        ];
+        assert_eq!(fit_segments(segments, max_width), expected);
+    }
+
+    #[rstest]
+    #[case(None, "{[date time] (git)}user@host")]
+    #[case(Some(27), "{[time] (git)}user@host")]
+    #[case(Some(14), "{[time] (git)}")]
+    #[case(Some(13), "")]
+    fn fit_segments_group_test(#[case] max_width: Option<usize>, #[case] expected: &str) {
+        let group = structs::SegmentGroup {
+            segments: Vec::new(),
+            separator: " ",
+            prefix: "{",
+            suffix: "}",
+            color: None,
+        };
+        let grouped = structs::Arranged::Group(
+            &group,
+            vec![
+                Segment::new(1, "[date time]").with_short("[time]"),
+                Segment::new(3, "(git)"),
+            ],
+        );
+        let segments = vec![Segment::from(grouped), Segment::new(2, "user@host")];
+        assert_eq!(fit_segments(segments, max_width), expected);
+    }
+
+    #[rstest]
+    #[case("", "")]
+    #[case("host", "host")]
+    #[case("host.local", "host")]
+    #[case("host.example.org", "host")]
+    fn short_host_test(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(value.short_host(), expected);
+    }
+
+    #[rstest]
+    #[case("", 0)]
+    #[case("main", 4)]
+    #[case("機能", 4)]
+    #[case("fix-🐛", 6)]
+    #[case("e\u{301}", 1)]
+    fn display_width_test(#[case] value: &str, #[case] expected: usize) {
+        assert_eq!(display_width(value), expected);
+    }
+
+    #[rstest]
+    #[case("main", None, "main")]
+    #[case("main", Some(4), "main")]
+    #[case("main", Some(0), "")]
+    #[case("feature/long", Some(5), "feat…")]
+    #[case("feature/long", Some(1), "…")]
+    #[case("機能ブランチ", Some(6), "機能…")]
+    #[case("機能ブランチ", Some(5), "機能…")]
+    #[case("🐛🐛🐛", Some(4), "🐛…")]
+    fn truncate_to_width_test(
+        #[case] value: &str,
+        #[case] max_width: Option<usize>,
+        #[case] expected: &str,
+    ) {
+        let result = truncate_to_width(value, max_width);
+        assert_eq!(result, expected);
+        assert!(display_width(&result) <= max_width.unwrap_or(usize::MAX));
+    }
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(2, 0)]
+    #[case(3, 1)]
+    #[case(4, 1)]
+    #[case(5, 1)]
+    #[case(7, 2)]
+    #[case(1024, 9)]
+    #[case(1500, 10)]
+    fn estimate_bisect_steps_test(#[case] candidates: usize, #[case] expected: usize) {
+        assert_eq!(estimate_bisect_steps(candidates), expected);
+    }
+
+    #[rstest]
+    #[case("", None, "")]
+    #[case("crates/cli", None, "crates/cli")]
+    #[case("crates/cli", Some(2), "crates/cli")]
+    #[case("crates/cli/src", Some(2), "…/cli/src")]
+    #[case("crates/cli/src", Some(0), "…")]
+    fn truncate_path_test(
+        #[case] value: &str,
+        #[case] max_depth: Option<usize>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            truncate_path(std::path::Path::new(value), max_depth),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("*@work.com", "me@work.com", true)]
+    #[case("*@work.com", "me@Work.Com", true)]
+    #[case("*@work.com", "me@home.org", false)]
+    #[case("me@*.com", "me@work.com", true)]
+    #[case("m?@work.com", "me@work.com", true)]
+    #[case("m?@work.com", "mee@work.com", false)]
+    #[case("*", "", true)]
+    #[case("", "me", false)]
+    #[case("a*b*c", "aXbYbZc", true)]
+    fn wildcard_match_test(#[case] pattern: &str, #[case] text: &str, #[case] expected: bool) {
+        assert_eq!(wildcard_match(pattern, text), expected);
+    }
+
+    #[rstest]
+    #[case("/home/me/work/**", "/home/me/work", true)]
+    #[case("/home/me/work/**", "/home/me/work/a/b", true)]
+    #[case("/home/me/work/**", "/home/me/workshop", false)]
+    #[case("/home/me/*/src", "/home/me/app/src", true)]
+    #[case("/home/me/*/src", "/home/me/app/lib/src", false)]
+    #[case("/home/**/src/**", "/home/me/app/src/lib", true)]
+    #[case("/home/Me/**", "/home/me", false)]
+    fn path_match_test(#[case] pattern: &str, #[case] path: &str, #[case] expected: bool) {
+        assert_eq!(path_match(pattern, Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case("PROJ-123-fix-thing", Some(("PROJ-123", "fix-thing")))]
+    #[case("feature/PROJ-123-fix-thing", Some(("PROJ-123", "fix-thing")))]
+    #[case("users/me/fix-PROJ-7", Some(("PROJ-7", "fix")))]
+    #[case("PROJ-7/fix-thing", Some(("PROJ-7", "fix-thing")))]
+    #[case("fix_PROJ-7", Some(("PROJ-7", "fix")))]
+    #[case("fix-PROJ-7_thing", Some(("PROJ-7", "fix_thing")))]
+    #[case("PROJ-7", Some(("PROJ-7", "")))]
+    #[case("proj-7-fix", None)]
+    #[case("main", None)]
+    fn branch_ticket_test(#[case] branch: &str, #[case] expected: Option<(&str, &str)>) {
+        let pattern = regex::Regex::new(r"[A-Z]+-\d+").expect("valid pattern");
+        assert_eq!(
+            branch_ticket(branch, &pattern),
+            expected.map(|(id, name)| GitTicket {
+                id: id.to_string(),
+                name: name.to_string(),
+            })
+        );
+    }
 }
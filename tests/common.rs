@@ -26,3 +26,29 @@ pub fn tmp_for<P: AsRef<Path>>(id: P) -> std::io::Result<PathBuf> {
 
     Ok(p)
 }
+
+pub fn clean_tmp_for<P: AsRef<Path>>(id: P) -> std::io::Result<PathBuf> {
+    let p = tmp_root().join(id);
+
+    if p.exists() {
+        std::fs::remove_dir_all(&p)?;
+    }
+
+    tmp_for(p)
+}
+
+/// Initializes a repository with a single empty commit on `master` branch,
+/// `init.defaultBranch` of the machine isn't applied
+pub fn init_repo<P: AsRef<Path>>(path: P) -> Result<git2::Repository, git2::Error> {
+    let repo = git2::Repository::init_opts(
+        path,
+        git2::RepositoryInitOptions::new().initial_head("master"),
+    )?;
+    {
+        let signature = git2::Signature::now("Test", "test@example.com")?;
+        let tree_oid = repo.index()?.write_tree()?;
+        let tree = repo.find_tree(tree_oid)?;
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])?;
+    }
+    Ok(repo)
+}